@@ -0,0 +1,207 @@
+//! `dotfiles adopt`: moves an existing file into a tool directory, adds a `Links` entry for it,
+//! and leaves a symlink behind. Each adoption is journaled in the run record so it can be undone.
+
+use crate::config::{self, Config};
+use crate::config_editor::{self, ToolChanges};
+use crate::links;
+use crate::run_record::{AdoptionRecord, RunRecord};
+use crate::tools::Tools;
+use chrono::Utc;
+use color_eyre::Result;
+use color_eyre::eyre::{WrapErr, eyre};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Moves `path` into the directory of `tool_id`, links it back, and journals the adoption.
+pub(crate) fn adopt(
+    config_path: &Path,
+    path: &Path,
+    tool_id: &str,
+    record: &mut RunRecord,
+) -> Result<AdoptionRecord> {
+    let config = Config::load_from_file(&config_path.to_string_lossy())?;
+    let (tools, _) = Tools::from_config(&config, true)?;
+    let tool = tools
+        .get(tool_id)
+        .ok_or_else(|| eyre!("Tool '{tool_id}' is not defined"))?;
+
+    let original = std::path::absolute(path)
+        .wrap_err_with(|| format!("Failed to resolve {}", path.display()))?;
+    let metadata = fs::symlink_metadata(&original)
+        .wrap_err_with(|| format!("Failed to read {}", original.display()))?;
+    if metadata.is_symlink() {
+        return Err(eyre!("{} is already a symlink", original.display()));
+    }
+    let root = config::expand_home_path(tools.root());
+    if original.starts_with(&root) {
+        return Err(eyre!(
+            "{} is already inside the dotfiles root {}",
+            original.display(),
+            root.display()
+        ));
+    }
+
+    let source = original
+        .file_name()
+        .ok_or_else(|| eyre!("{} has no file name", original.display()))?
+        .to_string_lossy()
+        .into_owned();
+    if tool.links.contains_key(&source) {
+        return Err(eyre!("Tool '{tool_id}' already links '{source}'"));
+    }
+    let stored = tools.tool_dir(tool).join(&source);
+    if fs::symlink_metadata(&stored).is_ok() {
+        return Err(eyre!("{} already exists", stored.display()));
+    }
+
+    fs::create_dir_all(tools.tool_dir(tool))?;
+    move_path(&original, &stored)?;
+    let changes = ToolChanges {
+        add_links: vec![(source.clone(), link_target(&original))],
+        ..Default::default()
+    };
+    if let Err(error) = config_editor::edit_tool(config_path, tool_id, &changes) {
+        move_path(&stored, &original)?;
+        return Err(error);
+    }
+    if let Err(error) = links::symlink(&stored, &original) {
+        let changes = ToolChanges {
+            remove_links: vec![source],
+            ..Default::default()
+        };
+        config_editor::edit_tool(config_path, tool_id, &changes)?;
+        move_path(&stored, &original)?;
+        return Err(error).wrap_err_with(|| format!("Failed to link {}", original.display()));
+    }
+
+    let adoption = AdoptionRecord {
+        adopted_at: Utc::now(),
+        tool_id: tool_id.to_string(),
+        original,
+        stored,
+        source,
+    };
+    record.adoptions.push(adoption.clone());
+    Ok(adoption)
+}
+
+/// Reverses the adoption of `path`, or the most recent one when `path` is `None`: the file
+/// moves back, the symlink goes away, and the `Links` entry is removed.
+pub(crate) fn undo(
+    config_path: &Path,
+    path: Option<&Path>,
+    record: &mut RunRecord,
+) -> Result<AdoptionRecord> {
+    let original = path.map(std::path::absolute).transpose()?;
+    let index = record
+        .adoptions
+        .iter()
+        .rposition(|adoption| {
+            original
+                .as_ref()
+                .is_none_or(|path| &adoption.original == path)
+        })
+        .ok_or_else(|| match &original {
+            Some(path) => eyre!("{} was not adopted on this machine", path.display()),
+            None => eyre!("Nothing has been adopted on this machine"),
+        })?;
+    let adoption = record.adoptions[index].clone();
+
+    let in_place = fs::symlink_metadata(&adoption.original)
+        .is_ok_and(|metadata| metadata.is_symlink())
+        && fs::read_link(&adoption.original).is_ok_and(|target| target == adoption.stored);
+    if !in_place && fs::symlink_metadata(&adoption.original).is_ok() {
+        return Err(eyre!(
+            "{} was replaced after it was adopted; move it aside first",
+            adoption.original.display()
+        ));
+    }
+    if !adoption.stored.exists() {
+        return Err(eyre!("{} no longer exists", adoption.stored.display()));
+    }
+
+    let changes = ToolChanges {
+        remove_links: vec![adoption.source.clone()],
+        ..Default::default()
+    };
+    config_editor::edit_tool(config_path, &adoption.tool_id, &changes)?;
+    if in_place {
+        fs::remove_file(&adoption.original)?;
+    }
+    move_path(&adoption.stored, &adoption.original)?;
+
+    record.adoptions.remove(index);
+    Ok(adoption)
+}
+
+/// `~/...` for paths under `$HOME`, so the entry works on other machines too.
+fn link_target(original: &Path) -> String {
+    match original.strip_prefix(config::expand_home_path("~")) {
+        Ok(relative) => format!("~/{}", relative.display()),
+        Err(_) => original.display().to_string(),
+    }
+}
+
+/// Renames `from` to `to`, copying files across filesystems when a rename is not possible.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    let context = || format!("Failed to move {} to {}", from.display(), to.display());
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == io::ErrorKind::CrossesDevices && from.is_file() => {
+            fs::copy(from, to).wrap_err_with(context)?;
+            fs::remove_file(from).wrap_err_with(context)
+        }
+        Err(error) => Err(error).wrap_err_with(context),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_adopt_and_undo() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        let elsewhere = dir.path().join("elsewhere");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&elsewhere).unwrap();
+        let config_path = root.join("config.yaml");
+        fs::write(
+            &config_path,
+            format!(
+                "Version: 1\nSystemPreferences:\n  Root: {}\nPreferences:\n  ToolsSettings:\n    - Name: zsh\n",
+                root.display()
+            ),
+        )
+        .unwrap();
+        let zshrc = elsewhere.join(".zshrc");
+        fs::write(&zshrc, "export EDITOR=vi\n").unwrap();
+        let mut record = RunRecord::default();
+
+        let adoption = adopt(&config_path, &zshrc, "zsh", &mut record).unwrap();
+
+        assert_eq!(adoption.stored, root.join("zsh").join(".zshrc"));
+        assert_eq!(fs::read_link(&zshrc).unwrap(), adoption.stored);
+        assert_eq!(fs::read_to_string(&zshrc).unwrap(), "export EDITOR=vi\n");
+        let contents = fs::read_to_string(&config_path).unwrap();
+        assert!(contents.contains(&format!(
+            "      Links:\n        .zshrc: {}\n",
+            zshrc.display()
+        )));
+        assert_eq!(record.adoptions.len(), 1);
+        assert!(adopt(&config_path, &zshrc, "zsh", &mut record).is_err());
+
+        let undone = undo(&config_path, None, &mut record).unwrap();
+
+        assert_eq!(undone.original, zshrc);
+        assert!(!fs::symlink_metadata(&zshrc).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&zshrc).unwrap(), "export EDITOR=vi\n");
+        assert!(!adoption.stored.exists());
+        assert!(!fs::read_to_string(&config_path).unwrap().contains("Links"));
+        assert!(record.adoptions.is_empty());
+        assert!(undo(&config_path, None, &mut record).is_err());
+    }
+}
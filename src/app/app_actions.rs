@@ -1,8 +1,44 @@
 use super::App;
+use super::config_watcher::ConfigWatcher;
+use super::tool_form::{FormField, FormMode, ToolForm, ToolModal};
+use crate::config;
+use crate::config_editor::{self, ToolChanges, ToolSpec};
 use crate::tools::Tools;
+use color_eyre::Result as EyreResult;
+use ratatui::DefaultTerminal;
+use std::io;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+/// Editor used when neither `$VISUAL` nor `$EDITOR` is set.
+const FALLBACK_EDITOR: &str = "vi";
 
 impl App {
     pub(crate) fn reload_config(&mut self) -> Result<Option<String>, String> {
+        let result = self.load_and_apply_tools();
+        // Included files and tool directories may have changed, so watch the new set.
+        if self.watcher.is_some() {
+            self.watcher = ConfigWatcher::new(&self.dotfiles.preferences.tools_settings.tools).ok();
+        }
+        result
+    }
+
+    /// Reloads like the `r` key when the watcher saw config or script files change on disk.
+    pub(crate) fn reload_on_file_changes(&mut self) {
+        if !self
+            .watcher
+            .as_ref()
+            .is_some_and(ConfigWatcher::has_changes)
+        {
+            return;
+        }
+        if let Err(message) = self.reload_config() {
+            self.dotfiles.show_reload_error(message.clone());
+            self.workflow.show_reload_error(message);
+        }
+    }
+
+    fn load_and_apply_tools(&mut self) -> Result<Option<String>, String> {
         match Tools::new_relaxed() {
             Ok((tools, warnings)) => {
                 self.dotfiles.apply_tools(tools.clone());
@@ -28,12 +64,127 @@ impl App {
         }
     }
 
+    /// Writes the open tool form or deletion to `config.yaml`, then reloads like the `r` key.
+    /// On failure the modal stays open with the error so the input is not lost.
+    pub(crate) fn submit_tool_modal(&mut self) {
+        let config_path = config::expand_home_path(config::DEFAULT_CONFIG_PATH);
+        let result = match self.dotfiles.modal.as_ref() {
+            Some(ToolModal::Form(form)) => save_tool_form(&config_path, form).map(Some),
+            Some(ToolModal::ConfirmDelete(confirm)) => {
+                config_editor::remove_tool(&config_path, &confirm.id, false).map(|_| None)
+            }
+            None => return,
+        };
+
+        match result {
+            Ok(selected_id) => {
+                self.dotfiles.close_modal();
+                if let Err(message) = self.reload_config() {
+                    self.dotfiles.show_reload_error(message.clone());
+                    self.workflow.show_reload_error(message);
+                } else if let Some(id) = selected_id {
+                    self.dotfiles.select_tool(&id);
+                }
+            }
+            Err(error) => self.dotfiles.show_modal_error(format!("{error:#}")),
+        }
+    }
+
+    /// Hands the terminal to `$VISUAL`/`$EDITOR` for `path`, then redraws the TUI and
+    /// re-reads the script preview.
+    pub(crate) fn edit_script(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        path: &Path,
+    ) -> EyreResult<()> {
+        let (program, args) = editor_command(
+            std::env::var("VISUAL").ok().as_deref(),
+            std::env::var("EDITOR").ok().as_deref(),
+        );
+
+        ratatui::restore();
+        let status = Command::new(&program).args(&args).arg(path).status();
+        *terminal = ratatui::init();
+        terminal.clear()?;
+
+        self.finish_script_edit(&program, status);
+        Ok(())
+    }
+
+    /// Reports how the editor exited; after a successful edit the drift column is recomputed,
+    /// since the script's hash no longer matches the run record.
+    fn finish_script_edit(&mut self, program: &str, status: io::Result<ExitStatus>) {
+        match status {
+            Ok(status) if status.success() => {
+                // Only drop our own warning; one from a config reload (e.g. a broken include)
+                // still applies.
+                if let Some(warning) = self.editor_warning.take()
+                    && self.dotfiles.reload_warning.as_ref() == Some(&warning)
+                {
+                    self.dotfiles.clear_reload_warning();
+                }
+                self.dotfiles.refresh_status();
+            }
+            Ok(status) => {
+                self.show_editor_warning(format!("Editor '{program}' exited with {status}"));
+            }
+            Err(error) => {
+                self.show_editor_warning(format!("Failed to launch editor '{program}': {error}"));
+            }
+        }
+        self.dotfiles.reset_script_view();
+    }
+
+    fn show_editor_warning(&mut self, message: String) {
+        self.editor_warning = Some(message.clone());
+        self.dotfiles.show_reload_warning(message);
+    }
+
     /// Set running to false to quit the application.
     pub(crate) fn quit(&mut self) {
         self.running = false;
     }
 }
 
+/// Returns the id of the created or edited tool.
+fn save_tool_form(config_path: &Path, form: &ToolForm) -> EyreResult<String> {
+    match &form.mode {
+        FormMode::Create => {
+            let spec = ToolSpec {
+                name: form.name.trim().to_string(),
+                root: form.changed(FormField::Root),
+                file: form.changed(FormField::File),
+                dependencies: form.selected_dependencies(),
+                ..Default::default()
+            };
+            config_editor::add_tool(config_path, &spec)
+        }
+        FormMode::Edit { id } => {
+            let changes = ToolChanges {
+                name: form.changed(FormField::Name),
+                root: form.changed(FormField::Root),
+                file: form.changed(FormField::File),
+                dependencies: form.changed_dependencies(),
+                ..Default::default()
+            };
+            config_editor::edit_tool(config_path, id, &changes)?;
+            Ok(id.clone())
+        }
+    }
+}
+
+/// Splits the configured editor into a program and its leading arguments (e.g. `code --wait`).
+fn editor_command(visual: Option<&str>, editor: Option<&str>) -> (String, Vec<String>) {
+    let command = [visual, editor]
+        .into_iter()
+        .flatten()
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or(FALLBACK_EDITOR);
+    let mut parts = command.split_whitespace().map(str::to_string);
+    let program = parts.next().unwrap_or_else(|| FALLBACK_EDITOR.to_string());
+    (program, parts.collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,6 +228,116 @@ Preferences:
         assert!(app.dotfiles.reload_warning.is_none());
     }
 
+    #[test]
+    fn test_submit_tool_modal_persists_and_reloads() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join(".dotfiles");
+        fs::create_dir(&config_path).unwrap();
+        let config_file = config_path.join("config.yaml");
+        fs::write(
+            &config_file,
+            format!(
+                r#"
+SystemPreferences:
+  Root: {}
+Preferences:
+  ToolsSettings:
+    - Id: brew
+      Name: Brew
+"#,
+                config_path.display()
+            ),
+        )
+        .unwrap();
+
+        let original_home = std::env::var("HOME");
+        unsafe {
+            std::env::set_var("HOME", dir.path().to_str().unwrap());
+        }
+
+        let mut app = App::new();
+        app.dotfiles.open_create_form();
+        if let Some(ToolModal::Form(form)) = app.dotfiles.modal.as_mut() {
+            form.name = "Helm".to_string();
+            form.dependencies[0].checked = true;
+        }
+        app.submit_tool_modal();
+        let created = app.dotfiles.selected_tool().map(|tool| tool.id.clone());
+
+        app.dotfiles.open_delete_confirmation();
+        app.submit_tool_modal();
+        let remaining = app.dotfiles.preferences.tools_settings.tools.iter().count();
+
+        unsafe {
+            if let Ok(home) = original_home {
+                std::env::set_var("HOME", home);
+            }
+        }
+
+        assert!(app.dotfiles.modal.is_none());
+        assert_eq!(created.as_deref(), Some("helm"));
+        assert_eq!(remaining, 1);
+        let contents = fs::read_to_string(&config_file).unwrap();
+        assert!(contents.contains("Id: brew"));
+        assert!(!contents.contains("Helm"));
+        assert!(config_path.join("helm").is_dir());
+    }
+
+    #[test]
+    fn test_editor_command_prefers_visual_then_editor() {
+        assert_eq!(
+            editor_command(Some("code --wait"), Some("vim")),
+            ("code".to_string(), vec!["--wait".to_string()])
+        );
+        assert_eq!(
+            editor_command(Some(" "), Some("nvim")),
+            ("nvim".to_string(), vec![])
+        );
+        assert_eq!(editor_command(None, None), ("vi".to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_finish_script_edit_refreshes_status() {
+        let dir = tempdir().unwrap();
+        let mut app = App::new_with_test_tools();
+        app.dotfiles.run_record_path = Some(dir.path().join("runs.json"));
+
+        app.finish_script_edit("false", Command::new("false").status());
+        assert!(app.dotfiles.status.is_none());
+        assert!(app.dotfiles.reload_warning.is_some());
+
+        app.finish_script_edit("true", Command::new("true").status());
+        let status = app.dotfiles.status.as_ref().unwrap();
+        assert_eq!(
+            status.tools.len(),
+            app.dotfiles.preferences.tools_settings.tools.iter().count()
+        );
+        assert!(app.dotfiles.reload_warning.is_none());
+    }
+
+    #[test]
+    fn test_finish_script_edit_keeps_config_warnings() {
+        let mut app = App::new_with_test_tools();
+        app.dotfiles
+            .show_reload_warning("Skipped include 'broken.yaml'".to_string());
+
+        app.finish_script_edit("true", Command::new("true").status());
+        assert_eq!(
+            app.dotfiles.reload_warning.as_deref(),
+            Some("Skipped include 'broken.yaml'")
+        );
+
+        app.finish_script_edit("false", Command::new("false").status());
+        assert!(
+            app.dotfiles
+                .reload_warning
+                .as_deref()
+                .is_some_and(|warning| warning.starts_with("Editor 'false' exited"))
+        );
+        app.finish_script_edit("true", Command::new("true").status());
+        assert!(app.dotfiles.reload_warning.is_none());
+    }
+
     #[test]
     fn test_quit() {
         let mut app = App::new();
@@ -1,5 +1,7 @@
 use super::App;
+use super::dotfiles::ViewTab;
 use super::tabs::SelectedTab;
+use super::tool_form::ModalOutcome;
 use color_eyre::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use std::time::Duration;
@@ -23,6 +25,24 @@ impl App {
     }
 
     fn on_key_event(&mut self, key: KeyEvent) {
+        let is_ctrl_c = key.modifiers == KeyModifiers::CONTROL
+            && matches!(key.code, KeyCode::Char('c') | KeyCode::Char('C'));
+        if self.dotfiles.modal.is_some() && !is_ctrl_c {
+            if self.dotfiles.on_modal_key_event(key) == ModalOutcome::Submit {
+                self.submit_tool_modal();
+            }
+            return;
+        }
+
+        // The graph view needs the arrows and Esc that otherwise switch tabs or quit.
+        if self.selected_tab == SelectedTab::Dotfiles
+            && self.dotfiles.view == ViewTab::Graph
+            && matches!(key.code, KeyCode::Left | KeyCode::Right | KeyCode::Esc)
+        {
+            self.dotfiles.on_key_event(key);
+            return;
+        }
+
         match (key.modifiers, key.code) {
             (_, KeyCode::Esc | KeyCode::Char('q'))
             | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
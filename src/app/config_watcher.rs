@@ -0,0 +1,169 @@
+use crate::config::{self, Config};
+use crate::tools::Tools;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{DebounceEventResult, Debouncer, new_debouncer};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// Quiet period before a burst of file events (an editor save, a `git pull`) triggers a reload.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches the loaded config files and every tool directory for changes.
+pub(crate) struct ConfigWatcher {
+    // Dropping the debouncer stops its background thread.
+    _debouncer: Debouncer<RecommendedWatcher>,
+    receiver: Receiver<DebounceEventResult>,
+    config_files: BTreeSet<PathBuf>,
+}
+
+impl ConfigWatcher {
+    /// Starts watching the files the current configuration was read from and the directories
+    /// of `tools`. Paths that do not exist yet are skipped.
+    pub(crate) fn new(tools: &Tools) -> notify_debouncer_mini::notify::Result<Self> {
+        let config_files = watched_config_files();
+        let tool_dirs = tools
+            .iter()
+            .filter_map(|tool| tools.tool_path(tool).parent().map(Path::to_path_buf))
+            .collect::<BTreeSet<_>>();
+
+        let (sender, receiver) = mpsc::channel();
+        let mut debouncer = new_debouncer(DEBOUNCE, sender)?;
+        let watcher = debouncer.watcher();
+
+        // Editors usually save by renaming a temp file over the original, which drops a watch
+        // on the file itself, so config files are watched through their directory.
+        let config_dirs = config_files
+            .iter()
+            .filter_map(|path| path.parent().map(Path::to_path_buf))
+            .collect::<BTreeSet<_>>();
+        for dir in config_dirs.iter().filter(|dir| dir.is_dir()) {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+        for dir in tool_dirs.iter().filter(|dir| dir.is_dir()) {
+            if !config_dirs.contains(dir) {
+                watcher.watch(dir, RecursiveMode::Recursive)?;
+            }
+        }
+
+        Ok(Self {
+            _debouncer: debouncer,
+            receiver,
+            config_files,
+        })
+    }
+
+    /// Drains pending events and reports whether any of them should trigger a reload.
+    pub(crate) fn has_changes(&self) -> bool {
+        let mut changed = false;
+        while let Ok(result) = self.receiver.try_recv() {
+            if let Ok(events) = result {
+                changed |= events.iter().any(|event| self.is_relevant(&event.path));
+            }
+        }
+        changed
+    }
+
+    fn is_relevant(&self, path: &Path) -> bool {
+        if self.config_files.contains(path) {
+            return true;
+        }
+        // Ignore editor swap/backup files and the temp files written by config_editor.
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        if name.starts_with('.') || name.ends_with('~') || name.ends_with(".swp") {
+            return false;
+        }
+        // Config directories are shared with tool directories (e.g. ~/.dotfiles), so only
+        // YAML files there matter; anything else changing in a tool directory does.
+        let in_config_dir = self
+            .config_files
+            .iter()
+            .any(|file| file.parent() == path.parent());
+        !in_config_dir
+            || matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("yaml" | "yml")
+            )
+    }
+}
+
+/// `config.yaml` plus every file merged through `Include`. Falls back to `config.yaml` alone
+/// when it cannot be loaded, so fixing a broken file still triggers a reload.
+fn watched_config_files() -> BTreeSet<PathBuf> {
+    let mut files = vec![config::expand_home_path(config::DEFAULT_CONFIG_PATH)];
+    if let Ok(config) = Config::load() {
+        files.extend(config.loaded_files().iter().cloned());
+    }
+    // Watchers report resolved paths (e.g. /private/var on macOS), so compare against those.
+    files
+        .into_iter()
+        .map(|path| fs::canonicalize(&path).unwrap_or(path))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Instant;
+    use tempfile::tempdir;
+
+    fn wait_for_changes(watcher: &ConfigWatcher) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if watcher.has_changes() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[test]
+    fn test_watcher_reports_script_changes_and_ignores_swap_files() {
+        let dir = tempdir().unwrap();
+        let tool_dir = dir.path().join("brew");
+        fs::create_dir_all(&tool_dir).unwrap();
+        fs::write(tool_dir.join("brew.zsh"), "echo old\n").unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let mut debouncer = new_debouncer(DEBOUNCE, sender).unwrap();
+        debouncer
+            .watcher()
+            .watch(&tool_dir, RecursiveMode::Recursive)
+            .unwrap();
+        let watcher = ConfigWatcher {
+            _debouncer: debouncer,
+            receiver,
+            config_files: BTreeSet::from([dir.path().join("config.yaml")]),
+        };
+
+        fs::write(tool_dir.join(".brew.zsh.swp"), "swap").unwrap();
+        thread::sleep(DEBOUNCE * 3);
+        assert!(!watcher.has_changes());
+
+        fs::write(tool_dir.join("brew.zsh"), "echo new\n").unwrap();
+        assert!(wait_for_changes(&watcher));
+    }
+
+    #[test]
+    fn test_is_relevant_filters_config_directory() {
+        let dir = tempdir().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let watcher = ConfigWatcher {
+            _debouncer: new_debouncer(DEBOUNCE, sender).unwrap(),
+            receiver,
+            config_files: BTreeSet::from([dir.path().join("config.yaml")]),
+        };
+
+        assert!(watcher.is_relevant(&dir.path().join("config.yaml")));
+        assert!(watcher.is_relevant(&dir.path().join("extra.yaml")));
+        assert!(!watcher.is_relevant(&dir.path().join("config.yaml.bak.20250101")));
+        assert!(!watcher.is_relevant(&dir.path().join(".dotfiles-edit123")));
+        assert!(watcher.is_relevant(&dir.path().join("brew").join("brew.zsh")));
+    }
+}
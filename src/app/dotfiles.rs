@@ -1,6 +1,13 @@
+use super::graph_view::GraphView;
+use super::tool_form::ToolModal;
+use crate::links::LinkSummary;
+use crate::run_record;
+use crate::status::StatusReport;
 use crate::tools::Tools;
 use ratatui::widgets::ListState;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::time::SystemTime;
 
 pub(crate) struct Dotfiles {
     pub preferences: Preferences,
@@ -10,6 +17,17 @@ pub(crate) struct Dotfiles {
     pub view_height: usize,
     pub reload_error: Option<String>,
     pub reload_warning: Option<String>,
+    pub modal: Option<ToolModal>,
+    pub graph: GraphView,
+    /// Drift of each tool against the run record; `None` when no record is consulted.
+    pub status: Option<StatusReport>,
+    pub run_record_path: Option<PathBuf>,
+    /// Modification time of the run record when `status` was computed.
+    pub status_stamp: Option<SystemTime>,
+    /// Link counts for each tool with `Links`, keyed by tool id.
+    pub links: HashMap<String, LinkSummary>,
+    /// Script the main loop should open in the user's editor on its next iteration.
+    pub edit_request: Option<PathBuf>,
 }
 
 pub(crate) struct Preferences {
@@ -25,13 +43,15 @@ pub(crate) struct ToolsSettings {
 pub enum ViewTab {
     Menu,
     Script,
+    /// Stage-by-stage graph of every tool, shown in place of the menu and details.
+    Graph,
 }
 
 impl ViewTab {
     pub fn next(self) -> Self {
         match self {
             ViewTab::Menu => ViewTab::Script,
-            ViewTab::Script => ViewTab::Menu,
+            ViewTab::Script | ViewTab::Graph => ViewTab::Menu,
         }
     }
 }
@@ -50,7 +70,7 @@ impl Dotfiles {
         tools_settings.state.select_first();
 
         let preferences = Preferences { tools_settings };
-        Self {
+        let mut dotfiles = Self {
             preferences,
             view: ViewTab::Menu,
             script_lines: VecDeque::new(),
@@ -58,7 +78,17 @@ impl Dotfiles {
             view_height: 0,
             reload_error: load_error,
             reload_warning: None,
-        }
+            modal: None,
+            graph: GraphView::default(),
+            status: None,
+            run_record_path: Some(run_record::default_path()),
+            status_stamp: None,
+            links: HashMap::new(),
+            edit_request: None,
+        };
+        dotfiles.refresh_status();
+        dotfiles.refresh_links();
+        dotfiles
     }
 
     #[cfg(test)]
@@ -78,6 +108,13 @@ impl Dotfiles {
             view_height: 0,
             reload_error: None,
             reload_warning: None,
+            modal: None,
+            graph: GraphView::default(),
+            status: None,
+            run_record_path: None,
+            status_stamp: None,
+            links: HashMap::new(),
+            edit_request: None,
         }
     }
 
@@ -98,6 +135,13 @@ impl Dotfiles {
             view_height: 0,
             reload_error: None,
             reload_warning: None,
+            modal: None,
+            graph: GraphView::default(),
+            status: None,
+            run_record_path: None,
+            status_stamp: None,
+            links: HashMap::new(),
+            edit_request: None,
         }
     }
 }
@@ -1,6 +1,14 @@
-use super::dotfiles::Dotfiles;
-use crate::tools::Tools;
+use super::dotfiles::{Dotfiles, ViewTab};
+use super::graph_view::GraphView;
+use super::tool_form::{DeleteConfirmation, ToolForm, ToolModal};
+use crate::links::{self, LinkSummary};
+use crate::run_record::RunRecord;
+use crate::status::{Checks, StatusReport};
+use crate::tools::{ToolItem, Tools};
 use ratatui::widgets::ListState;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
 
 impl Dotfiles {
     pub(crate) fn select_next_tool(&mut self) {
@@ -81,6 +89,110 @@ impl Dotfiles {
         self.preferences.tools_settings.tools = tools;
         self.preferences.tools_settings.state = state;
         self.reset_script_view();
+        self.refresh_status();
+        self.refresh_links();
+    }
+
+    /// Recomputes each tool's drift from the run record. Check scripts are not run here.
+    pub(crate) fn refresh_status(&mut self) {
+        let Some(path) = self.run_record_path.as_deref() else {
+            return;
+        };
+        self.status_stamp = modified_time(path);
+        self.status = RunRecord::load(path).ok().map(|record| {
+            StatusReport::collect(
+                &self.preferences.tools_settings.tools,
+                &record,
+                Checks::Skip,
+            )
+        });
+    }
+
+    /// Recomputes the link column; tools without `Links` get no entry.
+    pub(crate) fn refresh_links(&mut self) {
+        let record = self
+            .run_record_path
+            .as_deref()
+            .and_then(|path| RunRecord::load(path).ok())
+            .unwrap_or_default();
+        let tools = &self.preferences.tools_settings.tools;
+        self.links = tools
+            .iter()
+            .filter(|tool| !tool.links.is_empty())
+            .map(|tool| {
+                let summary = LinkSummary::of(&links::statuses(tools, tool, &record));
+                (tool.id.clone(), summary)
+            })
+            .collect();
+    }
+
+    /// Refreshes the drift indicator and link column after a workflow run or `dotfiles link`
+    /// has rewritten the run record.
+    pub(crate) fn refresh_status_if_stale(&mut self) {
+        if let Some(path) = self.run_record_path.as_deref()
+            && modified_time(path) != self.status_stamp
+        {
+            self.refresh_status();
+            self.refresh_links();
+        }
+    }
+
+    pub(crate) fn selected_tool(&self) -> Option<&ToolItem> {
+        let tools_settings = &self.preferences.tools_settings;
+        tools_settings
+            .state
+            .selected()
+            .and_then(|index| tools_settings.tools.get_by_index(index))
+    }
+
+    pub(crate) fn select_tool(&mut self, tool_id: &str) {
+        if let Some(index) = self.preferences.tools_settings.tools.index_of(tool_id) {
+            self.preferences.tools_settings.state.select(Some(index));
+            self.reset_script_view();
+        }
+    }
+
+    /// Switches to the graph view with the cursor on the selected tool.
+    pub(crate) fn open_graph(&mut self) {
+        self.graph = GraphView::new(self.selected_tool().map(|tool| tool.id.as_str()));
+        self.view = ViewTab::Graph;
+    }
+
+    pub(crate) fn request_script_edit(&mut self) {
+        if let Some(tool) = self.selected_tool() {
+            self.edit_request = Some(self.preferences.tools_settings.tools.tool_path(tool));
+        }
+    }
+
+    pub(crate) fn open_create_form(&mut self) {
+        let form = ToolForm::create(&self.preferences.tools_settings.tools);
+        self.modal = Some(ToolModal::Form(Box::new(form)));
+    }
+
+    pub(crate) fn open_edit_form(&mut self) {
+        if let Some(tool) = self.selected_tool() {
+            let form = ToolForm::edit(&self.preferences.tools_settings.tools, tool);
+            self.modal = Some(ToolModal::Form(Box::new(form)));
+        }
+    }
+
+    pub(crate) fn open_delete_confirmation(&mut self) {
+        if let Some(tool) = self.selected_tool() {
+            self.modal = Some(ToolModal::ConfirmDelete(DeleteConfirmation::new(tool)));
+        }
+    }
+
+    pub(crate) fn close_modal(&mut self) {
+        self.modal = None;
+    }
+
+    /// Keeps the modal open and shows why saving failed.
+    pub(crate) fn show_modal_error(&mut self, message: String) {
+        match self.modal.as_mut() {
+            Some(ToolModal::Form(form)) => form.error = Some(message),
+            Some(ToolModal::ConfirmDelete(confirm)) => confirm.error = Some(message),
+            None => {}
+        }
     }
 
     pub(crate) fn show_reload_error(&mut self, message: String) {
@@ -99,6 +211,12 @@ impl Dotfiles {
     }
 }
 
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -263,6 +381,42 @@ mod tests {
         assert_eq!(dotfiles.script_scroll, 0);
     }
 
+    #[test]
+    fn test_request_script_edit_uses_tool_path() {
+        let mut dotfiles = Dotfiles::new_with_test_tools();
+
+        dotfiles.request_script_edit();
+
+        let tools = &dotfiles.preferences.tools_settings.tools;
+        let expected = tools.tool_path(tools.get_by_index(0).unwrap());
+        assert_eq!(dotfiles.edit_request, Some(expected));
+    }
+
+    #[test]
+    fn test_open_and_close_tool_modals() {
+        let mut dotfiles = Dotfiles::new_with_test_tools();
+
+        dotfiles.open_create_form();
+        assert!(matches!(dotfiles.modal, Some(ToolModal::Form(_))));
+
+        dotfiles.open_edit_form();
+        let Some(ToolModal::Form(form)) = dotfiles.modal.as_ref() else {
+            panic!("expected edit form");
+        };
+        assert_eq!(form.name, "Brew");
+
+        dotfiles.open_delete_confirmation();
+        dotfiles.show_modal_error("in use".to_string());
+        let Some(ToolModal::ConfirmDelete(confirm)) = dotfiles.modal.as_ref() else {
+            panic!("expected delete confirmation");
+        };
+        assert_eq!(confirm.id, "brew");
+        assert_eq!(confirm.error.as_deref(), Some("in use"));
+
+        dotfiles.close_modal();
+        assert!(dotfiles.modal.is_none());
+    }
+
     #[test]
     fn test_apply_tools_preserves_selection() {
         use crate::tools::Tools;
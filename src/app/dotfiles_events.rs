@@ -1,10 +1,16 @@
 use super::dotfiles::Dotfiles;
 use super::dotfiles::ViewTab;
+use super::graph_view::GraphOutcome;
+use super::tool_form::{ModalOutcome, ToolModal};
 use crossterm::event::KeyCode;
 use crossterm::event::KeyEvent;
 
 impl Dotfiles {
     pub(crate) fn on_key_event(&mut self, key: KeyEvent) {
+        if self.view == ViewTab::Graph {
+            self.on_graph_key_event(key);
+            return;
+        }
         match (key.modifiers, key.code) {
             (_, KeyCode::Tab) => self.view = self.view.next(),
             (_, KeyCode::Up) => {
@@ -21,19 +27,46 @@ impl Dotfiles {
                     self.scroll_script(1)
                 }
             }
-            (_, KeyCode::Home) => {
-                if self.view == ViewTab::Script {
-                    self.scroll_script_to_top()
-                }
-            }
-            (_, KeyCode::End) => {
-                if self.view == ViewTab::Script {
-                    self.scroll_script_to_bottom()
-                }
+            (_, KeyCode::Home) if self.view == ViewTab::Script => self.scroll_script_to_top(),
+            (_, KeyCode::End) if self.view == ViewTab::Script => self.scroll_script_to_bottom(),
+            (_, KeyCode::Char('o')) => self.request_script_edit(),
+            (_, KeyCode::Char('g')) => self.open_graph(),
+            (_, KeyCode::Char('a')) if self.view == ViewTab::Menu => self.open_create_form(),
+            (_, KeyCode::Char('e')) if self.view == ViewTab::Menu => self.open_edit_form(),
+            (_, KeyCode::Char('d')) if self.view == ViewTab::Menu => {
+                self.open_delete_confirmation()
             }
             _ => {}
         }
     }
+
+    fn on_graph_key_event(&mut self, key: KeyEvent) {
+        match self
+            .graph
+            .on_key_event(key, &self.preferences.tools_settings.tools)
+        {
+            GraphOutcome::Pending => {}
+            GraphOutcome::Close => self.view = ViewTab::Menu,
+            GraphOutcome::Open(id) => {
+                self.select_tool(&id);
+                self.view = ViewTab::Menu;
+            }
+        }
+    }
+
+    /// Forwards a key to the open tool modal. A cancelled modal is closed here; a submitted one
+    /// is left open so the caller can report a failed save inside it.
+    pub(crate) fn on_modal_key_event(&mut self, key: KeyEvent) -> ModalOutcome {
+        let outcome = match self.modal.as_mut() {
+            Some(ToolModal::Form(form)) => form.on_key_event(key),
+            Some(ToolModal::ConfirmDelete(confirm)) => confirm.on_key_event(key),
+            None => ModalOutcome::Cancel,
+        };
+        if outcome == ModalOutcome::Cancel {
+            self.close_modal();
+        }
+        outcome
+    }
 }
 
 #[cfg(test)]
@@ -71,6 +104,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_dotfiles_on_key_event_opens_tool_modals() {
+        let mut dotfiles = Dotfiles::new_with_test_tools();
+
+        dotfiles.on_key_event(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE));
+        assert!(dotfiles.modal.is_some());
+        dotfiles.close_modal();
+
+        dotfiles.view = ViewTab::Script;
+        dotfiles.on_key_event(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE));
+        assert!(dotfiles.modal.is_none());
+    }
+
+    #[test]
+    fn test_dotfiles_on_key_event_graph_view_jumps_to_tool() {
+        let mut dotfiles = Dotfiles::new_with_test_tools();
+
+        dotfiles.on_key_event(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE));
+        assert_eq!(dotfiles.view, ViewTab::Graph);
+        assert_eq!(dotfiles.graph.selected.as_deref(), Some("brew"));
+
+        dotfiles.on_key_event(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE));
+        dotfiles.on_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(dotfiles.view, ViewTab::Menu);
+        assert_eq!(
+            dotfiles.selected_tool().map(|tool| tool.id.as_str()),
+            Some("gcloud")
+        );
+    }
+
     #[test]
     fn test_dotfiles_on_key_event_script_scroll() {
         let mut dotfiles = Dotfiles::new();
@@ -1,16 +1,18 @@
 use super::dotfiles::Dotfiles;
 use super::dotfiles::ViewTab;
+use super::tool_form::{DeleteConfirmation, FormField, ToolForm, ToolModal};
+use crate::links;
 use ratatui::buffer::Buffer;
 use ratatui::layout::Rect;
-use ratatui::layout::{Constraint, Layout};
+use ratatui::layout::{Constraint, Flex, Layout};
 use ratatui::style::Modifier;
 use ratatui::style::palette::tailwind::SLATE;
 use ratatui::style::{Color, Style};
 use ratatui::symbols;
-use ratatui::text::Line;
+use ratatui::text::{Line, Span};
 use ratatui::widgets::Widget;
 use ratatui::widgets::{
-    Block, Borders, HighlightSpacing, List, ListItem, Paragraph, StatefulWidget,
+    Block, Borders, Clear, HighlightSpacing, List, ListItem, Paragraph, StatefulWidget, Wrap,
 };
 
 const SELECTED_STYLE: Style = Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD);
@@ -26,6 +28,7 @@ impl Dotfiles {
     fn render_menu(&mut self, area: Rect, buffer: &mut Buffer) {
         let mut block = Block::new()
             .title(Line::from("Preferences"))
+            .title_bottom(Line::from("a add · e edit · d delete · g graph"))
             .borders(Borders::ALL)
             .border_set(symbols::border::PLAIN)
             .border_style(Style::new().fg(Color::White));
@@ -47,18 +50,40 @@ impl Dotfiles {
     }
 
     fn render_label(&mut self, area: Rect, buffer: &mut Buffer) {
-        let label = "Tools Settings";
-        Paragraph::new(label)
-            .style(Style::new().fg(SLATE.c200))
-            .render(area, buffer);
+        let mut label = Line::from(Span::styled("Tools Settings", Style::new().fg(SLATE.c200)));
+        if let Some(status) = self.status.as_ref().filter(|status| !status.is_clean()) {
+            label.push_span(Span::styled(
+                format!(" · {} drifted", status.drifted_count()),
+                Style::new().fg(Color::Yellow),
+            ));
+        }
+        Paragraph::new(label).render(area, buffer);
     }
     fn render_list(&mut self, area: Rect, buffer: &mut Buffer) {
-        let items = self
-            .preferences
-            .tools_settings
-            .tools
+        let tools = &self.preferences.tools_settings.tools;
+        let name_width = tools
+            .iter()
+            .map(|item| item.display_name().chars().count())
+            .max()
+            .unwrap_or(0);
+        let items = tools
             .iter()
-            .map(|item| ListItem::new(item.display_name()))
+            .map(|item| {
+                let mut line = Line::from(item.display_name());
+                if let Some(summary) = self.links.get(&item.id) {
+                    let color = if summary.conflicts > 0 {
+                        Color::Red
+                    } else if summary.linked == summary.total {
+                        Color::Green
+                    } else {
+                        Color::Yellow
+                    };
+                    let padding = name_width - item.display_name().chars().count() + 2;
+                    line.push_span(Span::raw(" ".repeat(padding)));
+                    line.push_span(Span::styled(summary.short(), Style::new().fg(color)));
+                }
+                ListItem::new(line)
+            })
             .collect::<Vec<ListItem>>();
         let list = List::new(items)
             .highlight_style(SELECTED_STYLE)
@@ -143,13 +168,38 @@ impl Dotfiles {
                 format!("Stage {}", index + 1)
             });
 
-        let info_text = format!(
-            "Tool: {}\nID: {}\nPath: {}\nOrder: {}",
+        let transitive = |ids| {
+            let names = tools
+                .in_run_order(&ids)
+                .into_iter()
+                .map(|tool| tool.name.as_str())
+                .collect::<Vec<_>>();
+            if names.is_empty() {
+                "(none)".to_string()
+            } else {
+                names.join(", ")
+            }
+        };
+
+        let mut info_text = format!(
+            "Tool: {}\nID: {}\nPath: {}\nOrder: {}\nWaits for: {}\nNeeded by: {}",
             selected_tool.name,
             selected_tool.id,
             tools.file_path(selected_tool),
             stage_text,
+            transitive(tools.ancestors(&selected_tool.id)),
+            transitive(tools.descendants(&selected_tool.id)),
         );
+        if let Some(status) = self
+            .status
+            .as_ref()
+            .and_then(|status| status.tool(&selected_tool.id))
+        {
+            info_text.push_str(&format!("\nStatus: {}", status.summary()));
+        }
+        if let Some(summary) = self.links.get(&selected_tool.id) {
+            info_text.push_str(&format!("\nLinks: {summary}"));
+        }
 
         let dependency_map_text = tools
             .dependency_map_lines(Some(&selected_tool.id))
@@ -157,9 +207,19 @@ impl Dotfiles {
 
         let dependency_map_height = (dependency_map_text.lines().count() + 2) as u16;
 
-        let script = tools
+        let mut script = tools
             .raw_script(selected_tool)
             .unwrap_or_else(|| "(Failed to read script)".to_string());
+        for (source, target) in selected_tool
+            .links
+            .iter()
+            .filter(|(source, _)| links::is_template(source))
+        {
+            let rendered = links::render(tools, &tools.tool_dir(selected_tool).join(source))
+                .unwrap_or_else(|error| format!("({error})"));
+            script.truncate(script.trim_end().len());
+            script.push_str(&format!("\n\n── {source} → {target} ──\n{rendered}"));
+        }
 
         Some(ToolViewData {
             info_text,
@@ -175,10 +235,9 @@ impl Dotfiles {
             let lines = message.lines().count().max(1) as u16 + 1;
             constraints.push(Constraint::Length(lines));
         }
-        const INFO_SECTION_HEIGHT: u16 = 5;
         const SCRIPT_SECTION_MIN_HEIGHT: u16 = 3;
         constraints.extend([
-            Constraint::Length(INFO_SECTION_HEIGHT),
+            Constraint::Length(data.info_text.lines().count() as u16 + 1),
             Constraint::Length(data.dependency_map_height),
             Constraint::Min(SCRIPT_SECTION_MIN_HEIGHT),
         ]);
@@ -233,15 +292,153 @@ impl Dotfiles {
             .block(script_block)
             .render(area, buffer);
     }
+
+    fn render_modal(&mut self, area: Rect, buffer: &mut Buffer) {
+        match self.modal.as_mut() {
+            Some(ToolModal::Form(form)) => Self::render_tool_form(form, area, buffer),
+            Some(ToolModal::ConfirmDelete(confirm)) => {
+                Self::render_delete_confirmation(confirm, area, buffer)
+            }
+            None => {}
+        }
+    }
+
+    fn render_tool_form(form: &mut ToolForm, area: Rect, buffer: &mut Buffer) {
+        let popup = popup_area(area, 60, 20);
+        Clear.render(popup, buffer);
+
+        let block = Block::new()
+            .title(Line::from(form.title()))
+            .title_bottom(Line::from(
+                "Tab next field · Space toggle · Enter save · Esc cancel",
+            ))
+            .borders(Borders::ALL)
+            .border_set(symbols::border::PLAIN)
+            .border_style(Style::new().fg(Color::Yellow));
+        let inner = block.inner(popup);
+        block.render(popup, buffer);
+
+        let [
+            name_area,
+            root_area,
+            file_area,
+            label_area,
+            list_area,
+            error_area,
+        ] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(2),
+        ])
+        .areas(inner);
+
+        let fields = [
+            (FormField::Name, "Name", &form.name, name_area),
+            (FormField::Root, "Root", &form.root, root_area),
+            (FormField::File, "File", &form.file, file_area),
+        ];
+        for (field, label, value, field_area) in fields {
+            let focused = form.focus == field;
+            let value = if value.is_empty() && !focused {
+                Span::styled("(default)", Style::new().fg(SLATE.c500))
+            } else if focused {
+                Span::styled(format!("{value}_"), SELECTED_STYLE)
+            } else {
+                Span::raw(value.as_str())
+            };
+            Paragraph::new(Line::from(vec![Span::raw(format!("{label:<6}")), value]))
+                .render(field_area, buffer);
+        }
+
+        let mut label_style = Style::new().fg(SLATE.c200);
+        if form.focus == FormField::Dependencies {
+            label_style = label_style.add_modifier(Modifier::BOLD);
+        }
+        Paragraph::new("Dependencies")
+            .style(label_style)
+            .render(label_area, buffer);
+
+        let items = form
+            .dependencies
+            .iter()
+            .map(|choice| {
+                let mark = if choice.checked { "[x]" } else { "[ ]" };
+                ListItem::new(format!("{mark} {}", choice.label))
+            })
+            .collect::<Vec<ListItem>>();
+        let mut list = List::new(items)
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        if form.focus == FormField::Dependencies {
+            list = list.highlight_style(SELECTED_STYLE);
+        }
+        StatefulWidget::render(list, list_area, buffer, &mut form.dependency_state);
+
+        if let Some(error) = form.error.as_ref() {
+            Paragraph::new(error.as_str())
+                .style(Style::new().fg(Color::Red))
+                .wrap(Wrap { trim: true })
+                .render(error_area, buffer);
+        }
+    }
+
+    fn render_delete_confirmation(confirm: &DeleteConfirmation, area: Rect, buffer: &mut Buffer) {
+        let popup = popup_area(area, 50, 7);
+        Clear.render(popup, buffer);
+
+        let block = Block::new()
+            .title(Line::from("Delete Tool"))
+            .title_bottom(Line::from("y/Enter delete · n/Esc cancel"))
+            .borders(Borders::ALL)
+            .border_set(symbols::border::PLAIN)
+            .border_style(Style::new().fg(Color::Red));
+
+        let mut lines = vec![
+            Line::from(format!(
+                "Remove {} ({}) from the configuration?",
+                confirm.name, confirm.id
+            )),
+            Line::from("The tool directory and script are kept."),
+        ];
+        if let Some(error) = confirm.error.as_ref() {
+            lines.push(Line::styled(error.as_str(), Style::new().fg(Color::Red)));
+        }
+
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .render(popup, buffer);
+    }
+}
+
+/// Centers a box of at most `width` x `height` cells inside `area`.
+fn popup_area(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
 }
 
 impl Widget for &mut Dotfiles {
     fn render(self, area: Rect, buffer: &mut Buffer) {
+        if self.view == ViewTab::Graph {
+            self.graph
+                .render(&self.preferences.tools_settings.tools, area, buffer);
+            self.render_modal(area, buffer);
+            return;
+        }
         let [menu_area, view_area] =
             Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)])
                 .areas(area);
         self.render_menu(menu_area, buffer);
         self.render_view(view_area, buffer);
+        self.render_modal(area, buffer);
     }
 }
 
@@ -397,6 +594,113 @@ mod tests {
         insta::assert_snapshot!(rendered);
     }
 
+    #[test]
+    fn test_snapshot_dotfiles_with_drift_status() {
+        use crate::status::{Drift, StatusReport, ToolStatus};
+
+        let mut dotfiles = Dotfiles::new_with_test_tools();
+        dotfiles.view = ViewTab::Menu;
+        dotfiles.status = Some(StatusReport {
+            tools: dotfiles
+                .preferences
+                .tools_settings
+                .tools
+                .iter()
+                .map(|tool| ToolStatus {
+                    id: tool.id.clone(),
+                    name: tool.name.clone(),
+                    last_success: None,
+                    drift: if tool.id == "brew" {
+                        vec![Drift::ScriptChanged]
+                    } else {
+                        vec![]
+                    },
+                })
+                .collect(),
+            untracked: vec![],
+        });
+
+        let backend = TestBackend::new(120, 35);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal
+            .draw(|frame| frame.render_widget(&mut dotfiles, frame.area()))
+            .unwrap();
+
+        let rendered = buffer_to_string(terminal.backend());
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_snapshot_dotfiles_with_links() {
+        use crate::links::LinkSummary;
+
+        let mut dotfiles = Dotfiles::new_with_test_tools();
+        dotfiles.view = ViewTab::Menu;
+        dotfiles.links.insert(
+            "brew".to_string(),
+            LinkSummary {
+                linked: 2,
+                total: 2,
+                conflicts: 0,
+                edited: 0,
+            },
+        );
+        dotfiles.links.insert(
+            "gcloud".to_string(),
+            LinkSummary {
+                linked: 0,
+                total: 1,
+                conflicts: 1,
+                edited: 0,
+            },
+        );
+
+        let backend = TestBackend::new(120, 35);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal
+            .draw(|frame| frame.render_widget(&mut dotfiles, frame.area()))
+            .unwrap();
+
+        let rendered = buffer_to_string(terminal.backend());
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_snapshot_dotfiles_template_preview() {
+        let mut dotfiles = Dotfiles::new_with_test_tools();
+        dotfiles.view = ViewTab::Menu;
+        dotfiles.preferences.tools_settings.state.select(Some(4));
+
+        let backend = TestBackend::new(120, 35);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal
+            .draw(|frame| frame.render_widget(&mut dotfiles, frame.area()))
+            .unwrap();
+
+        let rendered = buffer_to_string(terminal.backend());
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_snapshot_dotfiles_graph_view() {
+        let mut dotfiles = Dotfiles::new_with_test_tools();
+        dotfiles.open_graph();
+        dotfiles.graph.selected = Some("gcloud".to_string());
+
+        let backend = TestBackend::new(120, 30);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal
+            .draw(|frame| frame.render_widget(&mut dotfiles, frame.area()))
+            .unwrap();
+
+        let rendered = buffer_to_string(terminal.backend());
+        insta::assert_snapshot!(rendered);
+    }
+
     #[test]
     fn test_snapshot_dotfiles_with_tools_script_view() {
         let mut dotfiles = Dotfiles::new_with_test_tools();
@@ -433,6 +737,39 @@ mod tests {
         insta::assert_snapshot!(rendered);
     }
 
+    #[test]
+    fn test_snapshot_dotfiles_edit_tool_form() {
+        let mut dotfiles = Dotfiles::new_with_test_tools();
+        dotfiles.preferences.tools_settings.state.select(Some(5));
+        dotfiles.open_edit_form();
+
+        let backend = TestBackend::new(120, 35);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal
+            .draw(|frame| frame.render_widget(&mut dotfiles, frame.area()))
+            .unwrap();
+
+        let rendered = buffer_to_string(terminal.backend());
+        insta::assert_snapshot!(rendered);
+    }
+
+    #[test]
+    fn test_snapshot_dotfiles_delete_confirmation() {
+        let mut dotfiles = Dotfiles::new_with_test_tools();
+        dotfiles.open_delete_confirmation();
+
+        let backend = TestBackend::new(120, 35);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal
+            .draw(|frame| frame.render_widget(&mut dotfiles, frame.area()))
+            .unwrap();
+
+        let rendered = buffer_to_string(terminal.backend());
+        insta::assert_snapshot!(rendered);
+    }
+
     #[test]
     fn test_snapshot_dotfiles_with_warning_and_tools() {
         let mut dotfiles = Dotfiles::new_with_test_tools();
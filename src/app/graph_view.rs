@@ -0,0 +1,427 @@
+use crate::tools::Tools;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols;
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Widget};
+use std::collections::{BTreeSet, HashMap};
+
+const HEADER_HEIGHT: u16 = 2;
+const NODE_HEIGHT: u16 = 3;
+const ROW_GAP: u16 = 1;
+const COLUMN_GAP: u16 = 6;
+const MARGIN: u16 = 1;
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+/// Full-screen view of the Dotfiles tab that lays tools out in execution-stage columns.
+#[derive(Debug, Default)]
+pub(crate) struct GraphView {
+    /// Id of the tool under the cursor.
+    pub selected: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum GraphOutcome {
+    Pending,
+    Close,
+    /// Show the details of this tool.
+    Open(String),
+}
+
+impl GraphView {
+    pub(crate) fn new(selected: Option<&str>) -> Self {
+        Self {
+            selected: selected.map(str::to_string),
+        }
+    }
+
+    pub(crate) fn on_key_event(&mut self, key: KeyEvent, tools: &Tools) -> GraphOutcome {
+        let layout = GraphLayout::new(tools);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('g') => return GraphOutcome::Close,
+            KeyCode::Enter => {
+                return match self.selected.as_ref() {
+                    Some(id) if layout.position(id).is_some() => GraphOutcome::Open(id.clone()),
+                    _ => GraphOutcome::Pending,
+                };
+            }
+            _ => {}
+        }
+
+        let Some((column, row)) = self.selected.as_deref().and_then(|id| layout.position(id))
+        else {
+            self.selected = layout.node_id(0, 0);
+            return GraphOutcome::Pending;
+        };
+        let (column, row) = match key.code {
+            KeyCode::Up => (column, row.saturating_sub(1)),
+            KeyCode::Down => (column, row + 1),
+            KeyCode::Left => (column.saturating_sub(1), row),
+            KeyCode::Right => (column + 1, row),
+            KeyCode::Home => (column, 0),
+            KeyCode::End => (column, usize::MAX),
+            _ => return GraphOutcome::Pending,
+        };
+        if let Some(id) = layout.clamped_node_id(column, row) {
+            self.selected = Some(id);
+        }
+        GraphOutcome::Pending
+    }
+
+    pub(crate) fn render(&self, tools: &Tools, area: Rect, buffer: &mut Buffer) {
+        let block = Block::new()
+            .title(Line::from("Dependency Graph"))
+            .title_bottom(Line::from(
+                "Arrows move · Enter details · Esc/g close · dim edges are optional",
+            ))
+            .borders(Borders::ALL)
+            .border_set(symbols::border::PLAIN)
+            .border_style(Style::new().fg(Color::Yellow));
+        let inner = block.inner(area);
+        block.render(area, buffer);
+
+        let layout = GraphLayout::new(tools);
+        if layout.columns.is_empty() {
+            Paragraph::new("No tools configured.").render(inner, buffer);
+            return;
+        }
+
+        let canvas_area = Rect::new(0, 0, layout.width, layout.height);
+        let mut canvas = Buffer::empty(canvas_area);
+        self.draw(tools, &layout, &mut canvas);
+
+        // Scroll just far enough to keep the selected node on screen.
+        let (offset_x, offset_y) = self
+            .selected
+            .as_deref()
+            .and_then(|id| layout.node(id))
+            .map_or((0, 0), |node| {
+                (
+                    (node.x + node.width + MARGIN).saturating_sub(inner.width),
+                    (node.y + NODE_HEIGHT + MARGIN).saturating_sub(inner.height),
+                )
+            });
+        for y in 0..inner.height {
+            for x in 0..inner.width {
+                if let (Some(source), Some(target)) = (
+                    canvas.cell((x + offset_x, y + offset_y)),
+                    buffer.cell_mut((inner.x + x, inner.y + y)),
+                ) {
+                    *target = source.clone();
+                }
+            }
+        }
+    }
+
+    fn draw(&self, tools: &Tools, layout: &GraphLayout, canvas: &mut Buffer) {
+        let selected = self.selected.as_deref();
+        let related = selected
+            .map(|id| {
+                let mut related = tools.ancestors(id);
+                related.extend(tools.descendants(id));
+                related
+            })
+            .unwrap_or_default();
+
+        for (index, column) in layout.columns.iter().enumerate() {
+            if let Some(first) = column.first() {
+                canvas.set_string(
+                    first.x,
+                    0,
+                    format!("Stage {}", index + 1),
+                    Style::new().fg(Color::Gray).add_modifier(Modifier::BOLD),
+                );
+            }
+        }
+
+        let mut edges = EdgeCanvas::default();
+        for tool in tools.iter() {
+            let Some(target) = layout.node(&tool.id) else {
+                continue;
+            };
+            let dependencies = tool
+                .dependencies
+                .iter()
+                .map(|id| (id, EdgeTone::Required))
+                .chain(
+                    tool.optional_dependencies
+                        .iter()
+                        .map(|id| (id, EdgeTone::Optional)),
+                );
+            for (dependency, tone) in dependencies {
+                let Some(source) = layout.node(dependency) else {
+                    continue;
+                };
+                let tone = if selected == Some(&tool.id) || selected == Some(dependency) {
+                    EdgeTone::Selected
+                } else {
+                    tone
+                };
+                edges.connect(source, target, tone);
+            }
+        }
+        edges.draw(canvas);
+
+        for node in layout.columns.iter().flatten() {
+            let style = if selected == Some(node.id.as_str()) {
+                Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else if related.contains(&node.id) {
+                Style::new().fg(Color::Cyan)
+            } else {
+                Style::new().fg(Color::White)
+            };
+            let area = Rect::new(node.x, node.y, node.width, NODE_HEIGHT);
+            Clear.render(area, canvas);
+            Paragraph::new(node.label.as_str())
+                .style(style)
+                .block(
+                    Block::new()
+                        .borders(Borders::ALL)
+                        .border_set(symbols::border::ROUNDED)
+                        .border_style(style),
+                )
+                .render(area, canvas);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NodeBox {
+    id: String,
+    label: String,
+    x: u16,
+    y: u16,
+    width: u16,
+}
+
+/// Positions of every tool: one column per execution stage, tools sorted by name.
+struct GraphLayout {
+    columns: Vec<Vec<NodeBox>>,
+    width: u16,
+    height: u16,
+}
+
+impl GraphLayout {
+    fn new(tools: &Tools) -> Self {
+        let mut columns = Vec::new();
+        let mut x = MARGIN;
+        let mut height = 0;
+        for stage in tools.execution_stages() {
+            let column_width = stage
+                .iter()
+                .map(|tool| tool.name.chars().count() as u16 + 4)
+                .max()
+                .unwrap_or_default();
+            let column = stage
+                .iter()
+                .enumerate()
+                .map(|(row, tool)| NodeBox {
+                    id: tool.id.clone(),
+                    label: format!(" {}", tool.name),
+                    x,
+                    y: HEADER_HEIGHT + row as u16 * (NODE_HEIGHT + ROW_GAP),
+                    width: column_width,
+                })
+                .collect::<Vec<_>>();
+            height = height.max(HEADER_HEIGHT + column.len() as u16 * (NODE_HEIGHT + ROW_GAP));
+            x += column_width + COLUMN_GAP;
+            columns.push(column);
+        }
+        Self {
+            columns,
+            width: x.saturating_sub(COLUMN_GAP) + MARGIN,
+            height,
+        }
+    }
+
+    fn position(&self, id: &str) -> Option<(usize, usize)> {
+        self.columns.iter().enumerate().find_map(|(column, nodes)| {
+            nodes
+                .iter()
+                .position(|node| node.id == id)
+                .map(|row| (column, row))
+        })
+    }
+
+    fn node(&self, id: &str) -> Option<&NodeBox> {
+        let (column, row) = self.position(id)?;
+        Some(&self.columns[column][row])
+    }
+
+    fn node_id(&self, column: usize, row: usize) -> Option<String> {
+        Some(self.columns.get(column)?.get(row)?.id.clone())
+    }
+
+    /// Node at (`column`, `row`), moving to the last column or row when either is past the end.
+    fn clamped_node_id(&self, column: usize, row: usize) -> Option<String> {
+        let column = column.min(self.columns.len().checked_sub(1)?);
+        let row = row.min(self.columns[column].len().checked_sub(1)?);
+        self.node_id(column, row)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EdgeTone {
+    Optional,
+    Required,
+    Selected,
+}
+
+impl EdgeTone {
+    fn style(self) -> Style {
+        match self {
+            EdgeTone::Optional => Style::new().fg(Color::DarkGray),
+            EdgeTone::Required => Style::new().fg(Color::Gray),
+            EdgeTone::Selected => Style::new().fg(Color::Yellow),
+        }
+    }
+}
+
+/// Edge segments collected per cell so that crossing and merging lines get proper junctions.
+#[derive(Default)]
+struct EdgeCanvas {
+    cells: HashMap<(u16, u16), (u8, EdgeTone)>,
+    arrows: HashMap<(u16, u16), EdgeTone>,
+}
+
+impl EdgeCanvas {
+    /// Routes an edge out of the right side of `from`, down or up the lane just before the
+    /// column of `to`, and into its left side.
+    fn connect(&mut self, from: &NodeBox, to: &NodeBox, tone: EdgeTone) {
+        let start = from.x + from.width;
+        let lane = to.x - COLUMN_GAP / 2;
+        let arrow = to.x - 1;
+        let (from_y, to_y) = (from.y + NODE_HEIGHT / 2, to.y + NODE_HEIGHT / 2);
+
+        self.mark(start, from_y, LEFT, tone);
+        self.horizontal(from_y, start, lane, tone);
+        self.vertical(lane, from_y, to_y, tone);
+        self.horizontal(to_y, lane, arrow, tone);
+        let entry = self.arrows.entry((arrow, to_y)).or_insert(tone);
+        *entry = (*entry).max(tone);
+    }
+
+    fn horizontal(&mut self, y: u16, from: u16, to: u16, tone: EdgeTone) {
+        for x in from..to {
+            self.mark(x, y, RIGHT, tone);
+            self.mark(x + 1, y, LEFT, tone);
+        }
+    }
+
+    fn vertical(&mut self, x: u16, from: u16, to: u16, tone: EdgeTone) {
+        let (top, bottom) = (from.min(to), from.max(to));
+        for y in top..bottom {
+            self.mark(x, y, DOWN, tone);
+            self.mark(x, y + 1, UP, tone);
+        }
+    }
+
+    fn mark(&mut self, x: u16, y: u16, direction: u8, tone: EdgeTone) {
+        let cell = self.cells.entry((x, y)).or_insert((0, tone));
+        cell.0 |= direction;
+        cell.1 = cell.1.max(tone);
+    }
+
+    fn draw(&self, canvas: &mut Buffer) {
+        // Highlighted cells are drawn last so they stay on top.
+        let mut cells = self.cells.iter().collect::<Vec<_>>();
+        cells.sort_by_key(|(position, (_, tone))| (*tone, **position));
+        for (&(x, y), &(directions, tone)) in cells {
+            if let Some(cell) = canvas.cell_mut((x, y)) {
+                cell.set_symbol(junction(directions))
+                    .set_style(tone.style());
+            }
+        }
+        let arrows = self.arrows.iter().collect::<BTreeSet<_>>();
+        for (&(x, y), &tone) in arrows {
+            if let Some(cell) = canvas.cell_mut((x, y)) {
+                cell.set_symbol("▶").set_style(tone.style());
+            }
+        }
+    }
+}
+
+fn junction(directions: u8) -> &'static str {
+    match directions {
+        d if d == UP | DOWN | LEFT | RIGHT => "┼",
+        d if d == UP | DOWN | RIGHT => "├",
+        d if d == UP | DOWN | LEFT => "┤",
+        d if d == LEFT | RIGHT | DOWN => "┬",
+        d if d == LEFT | RIGHT | UP => "┴",
+        d if d == DOWN | RIGHT => "┌",
+        d if d == DOWN | LEFT => "┐",
+        d if d == UP | RIGHT => "└",
+        d if d == UP | LEFT => "┘",
+        d if d & (UP | DOWN) != 0 => "│",
+        _ => "─",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn press(view: &mut GraphView, tools: &Tools, code: KeyCode) -> GraphOutcome {
+        view.on_key_event(KeyEvent::new(code, KeyModifiers::NONE), tools)
+    }
+
+    #[test]
+    fn test_graph_view_navigates_between_stages() {
+        let tools = Tools::new_with_test_data();
+        let mut view = GraphView::new(None);
+
+        press(&mut view, &tools, KeyCode::Down);
+        assert_eq!(view.selected.as_deref(), Some("brew"));
+
+        press(&mut view, &tools, KeyCode::Right);
+        assert_eq!(view.selected.as_deref(), Some("gcloud"));
+        press(&mut view, &tools, KeyCode::End);
+        let last_in_stage = view.selected.clone().unwrap();
+        assert_ne!(last_in_stage, "gcloud");
+        press(&mut view, &tools, KeyCode::Down);
+        assert_eq!(view.selected.as_deref(), Some(last_in_stage.as_str()));
+
+        press(&mut view, &tools, KeyCode::Right);
+        press(&mut view, &tools, KeyCode::Right);
+        assert_eq!(view.selected.as_deref(), Some("zsh"));
+
+        press(&mut view, &tools, KeyCode::Left);
+        press(&mut view, &tools, KeyCode::Left);
+        press(&mut view, &tools, KeyCode::Left);
+        assert_eq!(view.selected.as_deref(), Some("brew"));
+    }
+
+    #[test]
+    fn test_graph_view_enter_opens_and_escape_closes() {
+        let tools = Tools::new_with_test_data();
+        let mut view = GraphView::new(Some("helm"));
+
+        assert_eq!(
+            press(&mut view, &tools, KeyCode::Enter),
+            GraphOutcome::Open("helm".to_string())
+        );
+        assert_eq!(press(&mut view, &tools, KeyCode::Esc), GraphOutcome::Close);
+
+        let mut stale = GraphView::new(Some("removed"));
+        assert_eq!(
+            press(&mut stale, &tools, KeyCode::Enter),
+            GraphOutcome::Pending
+        );
+    }
+
+    #[test]
+    fn test_junction_symbols() {
+        assert_eq!(junction(LEFT | RIGHT), "─");
+        assert_eq!(junction(LEFT | DOWN), "┐");
+        assert_eq!(junction(UP | DOWN | RIGHT), "├");
+        assert_eq!(junction(LEFT), "─");
+        assert_eq!(junction(UP), "│");
+    }
+}
@@ -1,12 +1,15 @@
 mod app_actions;
 mod app_events;
 mod app_ui;
+mod config_watcher;
 mod dotfiles;
 mod dotfiles_actions;
 mod dotfiles_events;
 mod dotfiles_ui;
+mod graph_view;
 mod tabs;
 mod tabs_ui;
+mod tool_form;
 mod workflow;
 mod workflow_actions;
 mod workflow_events;
@@ -15,6 +18,7 @@ mod workflow_menu;
 mod workflow_ui;
 
 use color_eyre::Result;
+use config_watcher::ConfigWatcher;
 use ratatui::DefaultTerminal;
 use tabs::SelectedTab;
 use workflow::Workflow;
@@ -27,15 +31,23 @@ pub(crate) struct App {
     pub workflow: Workflow,
     pub dotfiles: Dotfiles,
     pub selected_tab: SelectedTab,
+    /// Triggers a reload when config or script files change; `None` if watching is unavailable.
+    watcher: Option<ConfigWatcher>,
+    /// Warning left by the last failed editor run, cleared by the next successful edit.
+    editor_warning: Option<String>,
 }
 
 impl App {
     pub(crate) fn new() -> Self {
+        let dotfiles = Dotfiles::new();
+        let watcher = ConfigWatcher::new(&dotfiles.preferences.tools_settings.tools).ok();
         Self {
             running: true,
             workflow: Workflow::new(),
-            dotfiles: Dotfiles::new(),
+            dotfiles,
             selected_tab: SelectedTab::new(),
+            watcher,
+            editor_warning: None,
         }
     }
 
@@ -46,6 +58,8 @@ impl App {
             workflow: Workflow::new_with_test_tools(),
             dotfiles: Dotfiles::new_with_test_tools(),
             selected_tab: SelectedTab::new(),
+            watcher: None,
+            editor_warning: None,
         }
     }
 
@@ -54,8 +68,13 @@ impl App {
         self.running = true;
         while self.running {
             self.workflow.drain_log_messages();
+            self.reload_on_file_changes();
+            self.dotfiles.refresh_status_if_stale();
             terminal.draw(|frame| frame.render_widget(&mut self, frame.area()))?;
             self.handle_crossterm_events()?;
+            if let Some(path) = self.dotfiles.edit_request.take() {
+                self.edit_script(&mut terminal, &path)?;
+            }
         }
         Ok(())
     }
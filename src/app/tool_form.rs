@@ -0,0 +1,335 @@
+use crate::tools::{ToolItem, Tools};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::widgets::ListState;
+
+/// Modal shown over the Dotfiles tab while a tool is being created, edited, or deleted.
+pub(crate) enum ToolModal {
+    Form(Box<ToolForm>),
+    ConfirmDelete(DeleteConfirmation),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FormMode {
+    Create,
+    Edit { id: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FormField {
+    Name,
+    Root,
+    File,
+    Dependencies,
+}
+
+impl FormField {
+    fn next(self) -> Self {
+        match self {
+            FormField::Name => FormField::Root,
+            FormField::Root => FormField::File,
+            FormField::File => FormField::Dependencies,
+            FormField::Dependencies => FormField::Name,
+        }
+    }
+
+    fn previous(self) -> Self {
+        match self {
+            FormField::Name => FormField::Dependencies,
+            FormField::Root => FormField::Name,
+            FormField::File => FormField::Root,
+            FormField::Dependencies => FormField::File,
+        }
+    }
+}
+
+pub(crate) struct DependencyChoice {
+    pub id: String,
+    pub label: String,
+    pub checked: bool,
+}
+
+pub(crate) struct ToolForm {
+    pub mode: FormMode,
+    pub name: String,
+    pub root: String,
+    pub file: String,
+    pub dependencies: Vec<DependencyChoice>,
+    pub dependency_state: ListState,
+    pub focus: FormField,
+    pub error: Option<String>,
+    /// Field values the form was opened with; used to write only what changed.
+    original: Option<ToolItem>,
+}
+
+pub(crate) struct DeleteConfirmation {
+    pub id: String,
+    pub name: String,
+    pub error: Option<String>,
+}
+
+/// What the caller should do after a key was handled by the modal.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ModalOutcome {
+    Pending,
+    Cancel,
+    Submit,
+}
+
+impl ToolForm {
+    pub(crate) fn create(tools: &Tools) -> Self {
+        Self::build(FormMode::Create, tools, None)
+    }
+
+    pub(crate) fn edit(tools: &Tools, tool: &ToolItem) -> Self {
+        Self::build(
+            FormMode::Edit {
+                id: tool.id.clone(),
+            },
+            tools,
+            Some(tool.clone()),
+        )
+    }
+
+    fn build(mode: FormMode, tools: &Tools, original: Option<ToolItem>) -> Self {
+        let dependencies = tools
+            .iter()
+            .filter(|item| original.as_ref().is_none_or(|tool| tool.id != item.id))
+            .map(|item| DependencyChoice {
+                id: item.id.clone(),
+                label: item.display_name(),
+                checked: original
+                    .as_ref()
+                    .is_some_and(|tool| tool.dependencies.contains(&item.id)),
+            })
+            .collect::<Vec<_>>();
+
+        let mut dependency_state = ListState::default();
+        if !dependencies.is_empty() {
+            dependency_state.select(Some(0));
+        }
+
+        let (name, root, file) = original.as_ref().map_or_else(Default::default, |tool| {
+            (tool.name.clone(), tool.root.clone(), tool.file.clone())
+        });
+
+        Self {
+            mode,
+            name,
+            root,
+            file,
+            dependencies,
+            dependency_state,
+            focus: FormField::Name,
+            error: None,
+            original,
+        }
+    }
+
+    pub(crate) fn title(&self) -> &'static str {
+        match self.mode {
+            FormMode::Create => "New Tool",
+            FormMode::Edit { .. } => "Edit Tool",
+        }
+    }
+
+    pub(crate) fn selected_dependencies(&self) -> Vec<String> {
+        self.dependencies
+            .iter()
+            .filter(|choice| choice.checked)
+            .map(|choice| choice.id.clone())
+            .collect()
+    }
+
+    /// Returns the checked ids when they differ from the dependencies the form was opened with.
+    pub(crate) fn changed_dependencies(&self) -> Option<Vec<String>> {
+        let selected = self.selected_dependencies();
+        let unchanged = self.original.as_ref().is_some_and(|tool| {
+            tool.dependencies.len() == selected.len()
+                && selected.iter().all(|id| tool.dependencies.contains(id))
+        });
+        (!unchanged).then_some(selected)
+    }
+
+    /// Returns `Some(value)` when a text field differs from the value the form was opened with.
+    pub(crate) fn changed(&self, field: FormField) -> Option<String> {
+        let (current, original) = match field {
+            FormField::Name => (&self.name, self.original.as_ref().map(|tool| &tool.name)),
+            FormField::Root => (&self.root, self.original.as_ref().map(|tool| &tool.root)),
+            FormField::File => (&self.file, self.original.as_ref().map(|tool| &tool.file)),
+            FormField::Dependencies => return None,
+        };
+        let value = current.trim();
+        if value.is_empty() || original.is_some_and(|original| original == value) {
+            None
+        } else {
+            Some(value.to_string())
+        }
+    }
+
+    pub(crate) fn on_key_event(&mut self, key: KeyEvent) -> ModalOutcome {
+        match key.code {
+            KeyCode::Esc => return ModalOutcome::Cancel,
+            KeyCode::Enter => {
+                if self.name.trim().is_empty() {
+                    self.error = Some("Name is required".to_string());
+                    self.focus = FormField::Name;
+                    return ModalOutcome::Pending;
+                }
+                return ModalOutcome::Submit;
+            }
+            KeyCode::Tab => self.focus = self.focus.next(),
+            KeyCode::BackTab => self.focus = self.focus.previous(),
+            KeyCode::Up if self.focus == FormField::Dependencies => {
+                if self.dependency_state.selected() == Some(0) {
+                    self.focus = self.focus.previous();
+                } else {
+                    self.dependency_state.select_previous();
+                }
+            }
+            KeyCode::Down if self.focus == FormField::Dependencies => {
+                self.dependency_state.select_next();
+            }
+            KeyCode::Up => self.focus = self.focus.previous(),
+            KeyCode::Down => self.focus = self.focus.next(),
+            KeyCode::Char(' ') if self.focus == FormField::Dependencies => {
+                if let Some(choice) = self
+                    .dependency_state
+                    .selected()
+                    .and_then(|index| self.dependencies.get_mut(index))
+                {
+                    choice.checked = !choice.checked;
+                }
+            }
+            KeyCode::Char(c) => {
+                if let Some(value) = self.focused_text_mut() {
+                    value.push(c);
+                }
+            }
+            KeyCode::Backspace => {
+                if let Some(value) = self.focused_text_mut() {
+                    value.pop();
+                }
+            }
+            _ => {}
+        }
+        ModalOutcome::Pending
+    }
+
+    fn focused_text_mut(&mut self) -> Option<&mut String> {
+        match self.focus {
+            FormField::Name => Some(&mut self.name),
+            FormField::Root => Some(&mut self.root),
+            FormField::File => Some(&mut self.file),
+            FormField::Dependencies => None,
+        }
+    }
+}
+
+impl DeleteConfirmation {
+    pub(crate) fn new(tool: &ToolItem) -> Self {
+        Self {
+            id: tool.id.clone(),
+            name: tool.name.clone(),
+            error: None,
+        }
+    }
+
+    pub(crate) fn on_key_event(&mut self, key: KeyEvent) -> ModalOutcome {
+        match key.code {
+            KeyCode::Char('y' | 'Y') | KeyCode::Enter => ModalOutcome::Submit,
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => ModalOutcome::Cancel,
+            _ => ModalOutcome::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_create_form_lists_every_tool_as_dependency() {
+        let tools = Tools::new_with_test_data();
+        let form = ToolForm::create(&tools);
+
+        assert_eq!(form.mode, FormMode::Create);
+        assert_eq!(form.dependencies.len(), tools.iter().count());
+        assert!(form.selected_dependencies().is_empty());
+        assert_eq!(form.dependency_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn test_edit_form_prefills_fields_and_skips_itself() {
+        let tools = Tools::new_with_test_data();
+        let zsh = tools.iter().find(|tool| tool.id == "zsh").unwrap().clone();
+        let form = ToolForm::edit(&tools, &zsh);
+
+        assert_eq!(form.name, "Zsh");
+        assert!(form.dependencies.iter().all(|choice| choice.id != "zsh"));
+        let mut selected = form.selected_dependencies();
+        selected.sort();
+        let mut expected = zsh.dependencies.clone();
+        expected.sort();
+        assert_eq!(selected, expected);
+        assert_eq!(form.changed(FormField::Name), None);
+        assert_eq!(form.changed_dependencies(), None);
+    }
+
+    #[test]
+    fn test_form_typing_toggling_and_submit() {
+        let tools = Tools::new_with_test_data();
+        let mut form = ToolForm::create(&tools);
+
+        assert_eq!(
+            form.on_key_event(press(KeyCode::Enter)),
+            ModalOutcome::Pending
+        );
+        assert_eq!(form.error.as_deref(), Some("Name is required"));
+
+        for c in "Helmx".chars() {
+            form.on_key_event(press(KeyCode::Char(c)));
+        }
+        form.on_key_event(press(KeyCode::Backspace));
+        assert_eq!(form.name, "Helm");
+        assert_eq!(form.changed(FormField::Name).as_deref(), Some("Helm"));
+
+        form.on_key_event(press(KeyCode::BackTab));
+        assert_eq!(form.focus, FormField::Dependencies);
+        form.on_key_event(press(KeyCode::Down));
+        form.on_key_event(press(KeyCode::Char(' ')));
+        assert_eq!(
+            form.selected_dependencies(),
+            vec![form.dependencies[1].id.clone()]
+        );
+
+        assert_eq!(
+            form.on_key_event(press(KeyCode::Enter)),
+            ModalOutcome::Submit
+        );
+        assert_eq!(form.on_key_event(press(KeyCode::Esc)), ModalOutcome::Cancel);
+    }
+
+    #[test]
+    fn test_delete_confirmation_keys() {
+        let tools = Tools::new_with_test_data();
+        let mut confirm = DeleteConfirmation::new(tools.get_by_index(0).unwrap());
+
+        assert_eq!(
+            confirm.on_key_event(press(KeyCode::Char('x'))),
+            ModalOutcome::Pending
+        );
+        assert_eq!(
+            confirm.on_key_event(press(KeyCode::Char('y'))),
+            ModalOutcome::Submit
+        );
+        assert_eq!(
+            confirm.on_key_event(press(KeyCode::Esc)),
+            ModalOutcome::Cancel
+        );
+    }
+}
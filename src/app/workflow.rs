@@ -1,8 +1,10 @@
 use super::workflow_menu::Menu;
+use crate::run_record;
 use crate::tools::Tools;
 
 use super::workflow_menu::MenuItemAction;
 use std::collections::VecDeque;
+use std::path::PathBuf;
 use tokio::runtime::Runtime;
 use tokio::sync::mpsc;
 
@@ -33,6 +35,8 @@ pub(crate) struct Workflow {
     pub view: ViewTab,
     pub tools: Tools,
     pub reload_warning: Option<String>,
+    /// Where finished runs are recorded for drift detection; `None` disables recording.
+    pub run_record_path: Option<PathBuf>,
 }
 
 impl Workflow {
@@ -57,6 +61,7 @@ impl Workflow {
             view: ViewTab::Menu,
             tools,
             reload_warning: load_error,
+            run_record_path: Some(run_record::default_path()),
         }
     }
 
@@ -78,6 +83,7 @@ impl Workflow {
             view: ViewTab::Menu,
             tools: Tools::new_empty(),
             reload_warning: None,
+            run_record_path: None,
         }
     }
 
@@ -99,6 +105,7 @@ impl Workflow {
             view: ViewTab::Menu,
             tools: Tools::new_with_test_data(),
             reload_warning: None,
+            run_record_path: None,
         }
     }
 }
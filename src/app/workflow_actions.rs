@@ -1,7 +1,10 @@
 use super::workflow::{ViewTab, Workflow};
 use super::workflow_log::forward_stream;
 use super::workflow_menu::MenuItemAction;
+use crate::run_record::{self, RunOutcome};
 use crate::tools::Tools;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use tokio::io::AsyncRead;
 use tokio::process::Command as TokioCommand;
@@ -49,7 +52,12 @@ impl ToolRunResult {
 
 #[derive(Debug, Clone)]
 struct PreparedTool {
+    id: String,
+    /// Digest of the script when the run was scheduled, stored in the run record.
+    script_sha256: Option<String>,
     name: String,
+    interpreter: String,
+    env: BTreeMap<String, String>,
     script_path: String,
 }
 
@@ -120,9 +128,11 @@ impl Workflow {
 
         let tool_groups = self.prepare_tool_groups();
         let sender = self.log_sender.clone();
+        let record_path = self.run_record_path.clone();
 
-        self.runtime
-            .spawn(async move { Workflow::execute_tool_groups(tool_groups, sender).await });
+        self.runtime.spawn(async move {
+            Workflow::execute_tool_groups(tool_groups, sender, record_path).await
+        });
     }
 
     fn log_message<S: Into<String>>(&self, message: S) {
@@ -131,10 +141,12 @@ impl Workflow {
 
     async fn run_tool_script(
         tool_name: String,
+        interpreter: String,
+        env: BTreeMap<String, String>,
         file: String,
         sender: mpsc::UnboundedSender<String>,
     ) -> ToolRunResult {
-        let mut child = match Self::spawn_tool_child(&file) {
+        let mut child = match Self::spawn_tool_child(&interpreter, &env, &file) {
             Ok(child) => child,
             Err(error) => return Self::handle_command_spawn_error(tool_name, error, &sender),
         };
@@ -191,7 +203,11 @@ impl Workflow {
                 stage
                     .into_iter()
                     .map(|tool| PreparedTool {
+                        id: tool.id.clone(),
+                        script_sha256: run_record::script_digest(&self.tools.tool_path(&tool)).ok(),
                         name: tool.name.clone(),
+                        interpreter: tool.interpreter.clone(),
+                        env: tool.env.clone(),
                         script_path: self.tools.file_path(&tool),
                     })
                     .collect::<Vec<_>>()
@@ -202,8 +218,10 @@ impl Workflow {
     async fn execute_tool_groups(
         tool_groups: Vec<Vec<PreparedTool>>,
         sender: mpsc::UnboundedSender<String>,
+        record_path: Option<PathBuf>,
     ) {
         let mut all_results = Vec::new();
+        let mut outcomes = Vec::new();
 
         for stage in tool_groups {
             if stage.is_empty() {
@@ -213,20 +231,29 @@ impl Workflow {
             let mut handles = Vec::with_capacity(stage.len());
             for tool in stage {
                 let sender = sender.clone();
-                handles.push(tokio::spawn(async move {
-                    Workflow::run_prepared_tool(tool, sender).await
-                }));
+                let recorded = (tool.id.clone(), tool.script_sha256.clone());
+                handles.push((
+                    recorded,
+                    tokio::spawn(async move { Workflow::run_prepared_tool(tool, sender).await }),
+                ));
             }
 
-            for handle in handles {
-                match handle.await {
-                    Ok(result) => all_results.push(result),
+            for ((id, script_sha256), handle) in handles {
+                let result = match handle.await {
+                    Ok(result) => result,
                     Err(join_error) => {
                         let reason = format!("background task join error: {}", join_error);
                         let _ = sender.send(format!("Worker join failure detected: {reason}\n"));
-                        all_results.push(ToolRunResult::failed("<unknown>".to_string(), reason));
+                        ToolRunResult::failed("<unknown>".to_string(), reason)
                     }
-                }
+                };
+                outcomes.push(RunOutcome {
+                    id,
+                    script_sha256,
+                    success: result.is_success(),
+                    reason: result.failure_reason().map(str::to_string),
+                });
+                all_results.push(result);
             }
         }
 
@@ -236,16 +263,37 @@ impl Workflow {
         }
 
         Self::report_tool_run_summary(&all_results, &sender);
+        if let Some(path) = record_path {
+            Self::save_run_record(&path, outcomes, &sender);
+        }
+    }
+
+    /// Stores the outcomes for `dotfiles status`; a failure is logged but does not fail the run.
+    fn save_run_record(
+        path: &Path,
+        outcomes: Vec<RunOutcome>,
+        sender: &mpsc::UnboundedSender<String>,
+    ) {
+        if let Err(error) = run_record::append(path, outcomes) {
+            let _ = sender.send(format!("Failed to update run record: {error}\n"));
+        }
     }
 
     async fn run_prepared_tool(
         tool: PreparedTool,
         sender: mpsc::UnboundedSender<String>,
     ) -> ToolRunResult {
-        let PreparedTool { name, script_path } = tool;
+        let PreparedTool {
+            id: _,
+            script_sha256: _,
+            name,
+            interpreter,
+            env,
+            script_path,
+        } = tool;
         let _ = sender.send(format!("{name} | Starting...\n"));
         let _ = sender.send(format!("{name} | Running {script_path}\n"));
-        Self::run_tool_script(name, script_path, sender).await
+        Self::run_tool_script(name, interpreter, env, script_path, sender).await
     }
 
     fn report_tool_run_summary(results: &[ToolRunResult], sender: &mpsc::UnboundedSender<String>) {
@@ -323,7 +371,7 @@ impl Workflow {
         sender: &mpsc::UnboundedSender<String>,
     ) -> ToolRunResult {
         let hint = match error.kind() {
-            std::io::ErrorKind::NotFound => "Script not found or zsh missing?",
+            std::io::ErrorKind::NotFound => "Script not found or interpreter missing?",
             std::io::ErrorKind::PermissionDenied => "Try chmod +x or run with sudo",
             std::io::ErrorKind::Other => "unknown error",
             _ => "unknown error",
@@ -333,8 +381,13 @@ impl Workflow {
         ToolRunResult::failed(tool_name, format!("failed to spawn command: {error}"))
     }
 
-    fn spawn_tool_child(file: &str) -> std::io::Result<tokio::process::Child> {
-        TokioCommand::new("zsh")
+    fn spawn_tool_child(
+        interpreter: &str,
+        env: &BTreeMap<String, String>,
+        file: &str,
+    ) -> std::io::Result<tokio::process::Child> {
+        TokioCommand::new(interpreter)
+            .envs(env)
             .arg("--")
             .arg(file)
             .stdout(Stdio::piped())
@@ -488,14 +541,55 @@ mod tests {
     #[test]
     fn test_prepared_tool_structure() {
         let tool = PreparedTool {
+            id: "test_tool".to_string(),
+            script_sha256: None,
             name: "test_tool".to_string(),
+            interpreter: "zsh".to_string(),
+            env: BTreeMap::new(),
             script_path: "/path/to/script.sh".to_string(),
         };
 
         assert_eq!(tool.name, "test_tool");
+        assert_eq!(tool.interpreter, "zsh");
         assert_eq!(tool.script_path, "/path/to/script.sh");
     }
 
+    #[test]
+    fn test_execute_tool_groups_records_outcomes() {
+        let dir = tempfile::tempdir().unwrap();
+        let record_path = dir.path().join("runs.json");
+        let tool = |id: &str, body: &str| {
+            let script_path = dir.path().join(format!("{id}.sh"));
+            fs::write(&script_path, body).unwrap();
+            PreparedTool {
+                id: id.to_string(),
+                script_sha256: Some(format!("{id}-digest")),
+                name: id.to_string(),
+                interpreter: "sh".to_string(),
+                env: BTreeMap::new(),
+                script_path: script_path.to_string_lossy().into_owned(),
+            }
+        };
+        let groups = vec![vec![tool("ok", "exit 0\n"), tool("broken", "exit 1\n")]];
+
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        Runtime::new()
+            .unwrap()
+            .block_on(Workflow::execute_tool_groups(
+                groups,
+                sender,
+                Some(record_path.clone()),
+            ));
+
+        let record = run_record::RunRecord::load(&record_path).unwrap();
+        let ok = record.get("ok").unwrap();
+        assert!(ok.last_run.success);
+        assert_eq!(ok.last_run.script_sha256.as_deref(), Some("ok-digest"));
+        let broken = record.get("broken").unwrap();
+        assert!(!broken.last_run.success);
+        assert!(broken.last_success.is_none());
+    }
+
     #[test]
     fn test_execute_selected_with_run_tools() {
         let mut workflow = Workflow::new_for_test();
@@ -530,6 +624,8 @@ mod tests {
         let result = runtime.block_on(async {
             Workflow::run_tool_script(
                 "Space Tool".to_string(),
+                "zsh".to_string(),
+                BTreeMap::new(),
                 script_path.to_string_lossy().into_owned(),
                 sender,
             )
@@ -546,4 +642,33 @@ mod tests {
                 .any(|message| message.contains("space-test"))
         );
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_tool_script_with_custom_interpreter() {
+        let dir = tempfile::tempdir().unwrap();
+        let script_path = dir.path().join("script.sh");
+        fs::write(&script_path, "echo interpreter-test $GREETING\n").unwrap();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let runtime = Runtime::new().unwrap();
+        let result = runtime.block_on(async {
+            Workflow::run_tool_script(
+                "Sh Tool".to_string(),
+                "sh".to_string(),
+                BTreeMap::from([("GREETING".to_string(), "hello".to_string())]),
+                script_path.to_string_lossy().into_owned(),
+                sender,
+            )
+            .await
+        });
+
+        assert!(result.is_success());
+        let messages: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert!(
+            messages
+                .iter()
+                .any(|message| message.contains("interpreter-test hello"))
+        );
+    }
 }
@@ -36,11 +36,7 @@ impl Workflow {
                     self.scroll_log(1)
                 }
             }
-            (_, KeyCode::Enter) => {
-                if self.view == ViewTab::Menu {
-                    self.execute_selected()
-                }
-            }
+            (_, KeyCode::Enter) if self.view == ViewTab::Menu => self.execute_selected(),
             (_, KeyCode::Tab) => self.view = self.view.next(),
             _ => {}
         }
@@ -1,4 +1,5 @@
 use super::workflow::Workflow;
+use crate::secrets;
 use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::sync::mpsc;
 
@@ -18,12 +19,12 @@ pub(crate) async fn forward_stream<R>(
         match reader.read_line(&mut line).await {
             Ok(0) => {
                 if !line.is_empty() {
-                    let _ = sender.send(format!("{}{}", prefix.as_str(), line));
+                    let _ = sender.send(format!("{}{}", prefix.as_str(), secrets::redact(&line)));
                 }
                 break;
             }
             Ok(_) => {
-                let _ = sender.send(format!("{}{}", prefix.as_str(), line));
+                let _ = sender.send(format!("{}{}", prefix.as_str(), secrets::redact(&line)));
             }
             Err(e) => {
                 let _ = sender.send(format!("{} read error: {}\n", label, e));
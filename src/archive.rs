@@ -0,0 +1,278 @@
+//! Read-only views of an export for `dotfiles archive list` and `dotfiles archive show`.
+
+use crate::package::{self, ArchiveFormat, Manifest, ManifestFile, PackageError};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ArchiveSummary {
+    pub format: &'static str,
+    pub generated_at: String,
+    pub original_root: String,
+    pub tools: Vec<ToolSummary>,
+    pub files: Vec<FileSummary>,
+    pub totals: Totals,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ToolSummary {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub optional_dependencies: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<String>,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FileSummary {
+    /// Relative to the root; directories end with `/`.
+    pub path: String,
+    /// Id of the tool the file belongs to; `None` for the config and included files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    pub size: u64,
+    /// Permission bits in octal, e.g. `0644`.
+    pub mode: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub sha256: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub encrypted: bool,
+}
+
+impl FileSummary {
+    fn new(entry: &ManifestFile, tool: Option<&str>) -> Self {
+        Self {
+            path: entry.path.clone(),
+            tool: tool.map(str::to_string),
+            size: entry.size,
+            mode: format!("{:04o}", entry.mode & 0o7777),
+            sha256: entry.sha256.clone(),
+            encrypted: entry.encrypted,
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        self.path.ends_with('/')
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Totals {
+    pub tools: usize,
+    pub files: usize,
+    pub directories: usize,
+    pub bytes: u64,
+}
+
+impl ArchiveSummary {
+    /// Reads the manifest of the archive at `path` (or stdin for `-`) without extracting it.
+    pub(crate) fn read(path: &Path) -> Result<Self, PackageError> {
+        let (format, manifest) = package::read_manifest(path)?;
+        Ok(Self::new(format, &manifest))
+    }
+
+    fn new(format: ArchiveFormat, manifest: &Manifest) -> Self {
+        let mut files = std::iter::once(&manifest.config)
+            .chain(&manifest.includes)
+            .map(|entry| FileSummary::new(entry, None))
+            .collect::<Vec<_>>();
+        let mut tools = Vec::new();
+        for entry in &manifest.tools {
+            let start = files.len();
+            files.extend(
+                std::iter::once(&entry.artifact)
+                    .chain(&entry.related_files)
+                    .map(|file| FileSummary::new(file, Some(&entry.id))),
+            );
+            let tool_files = files[start..].iter().filter(|file| !file.is_dir());
+            tools.push(ToolSummary {
+                id: entry.id.clone(),
+                name: entry.name.clone(),
+                dependencies: entry.dependencies.clone(),
+                optional_dependencies: entry.optional_dependencies.clone(),
+                conflicts: entry.conflicts.clone(),
+                files: tool_files.clone().count(),
+                bytes: tool_files.map(|file| file.size).sum(),
+            });
+        }
+
+        let mut totals = Totals {
+            tools: tools.len(),
+            ..Totals::default()
+        };
+        for file in &files {
+            if file.is_dir() {
+                totals.directories += 1;
+            } else {
+                totals.files += 1;
+                totals.bytes += file.size;
+            }
+        }
+
+        Self {
+            format: format.extension(),
+            generated_at: manifest.generated_at.clone(),
+            original_root: manifest.original_root.clone(),
+            tools,
+            files,
+            totals,
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("archive summary serializes to JSON")
+    }
+
+    /// Only the files and totals, for `archive list --json`.
+    pub(crate) fn files_to_json(&self) -> String {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "files": self.files,
+            "totals": self.totals,
+        }))
+        .expect("archive files serialize to JSON")
+    }
+
+    /// When and where the archive was made, its tools, and totals.
+    pub(crate) fn show_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Format:        {}", self.format),
+            format!("Generated at:  {}", self.generated_at),
+            format!("Original root: {}", self.original_root),
+            format!("Tools ({}):", self.tools.len()),
+        ];
+        for tool in &self.tools {
+            let name = if tool.name == tool.id {
+                tool.id.clone()
+            } else {
+                format!("{} ({})", tool.id, tool.name)
+            };
+            lines.push(format!(
+                "  {name}: {} file(s), {} bytes",
+                tool.files, tool.bytes
+            ));
+            for (label, ids) in [
+                ("depends on", &tool.dependencies),
+                ("optional", &tool.optional_dependencies),
+                ("conflicts with", &tool.conflicts),
+            ] {
+                if !ids.is_empty() {
+                    lines.push(format!("      {label}: {}", ids.join(", ")));
+                }
+            }
+        }
+        lines.push(self.totals_line());
+        lines
+    }
+
+    /// One row per file with its mode, size, and hash, followed by the totals.
+    pub(crate) fn list_lines(&self) -> Vec<String> {
+        let size_width = self
+            .files
+            .iter()
+            .map(|file| file.size.to_string().len())
+            .max()
+            .unwrap_or_default()
+            .max("SIZE".len());
+        let mut lines = vec![format!(
+            "MODE {:>size_width$} {:<64} PATH",
+            "SIZE", "SHA256"
+        )];
+        for file in &self.files {
+            let sha256 = if file.sha256.is_empty() {
+                "-"
+            } else {
+                &file.sha256
+            };
+            let encrypted = if file.encrypted { " (encrypted)" } else { "" };
+            lines.push(format!(
+                "{} {:>size_width$} {sha256:<64} {}{encrypted}",
+                file.mode, file.size, file.path
+            ));
+        }
+        lines.push(self.totals_line());
+        lines
+    }
+
+    fn totals_line(&self) -> String {
+        format!(
+            "Total: {} tool(s), {} file(s), {} bytes",
+            self.totals.tools, self.totals.files, self.totals.bytes
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::ManifestToolEntry;
+
+    fn manifest_file(path: &str, mode: u32, size: u64) -> ManifestFile {
+        ManifestFile {
+            path: path.to_string(),
+            sha256: if path.ends_with('/') {
+                String::new()
+            } else {
+                "ab".repeat(32)
+            },
+            mode,
+            size,
+            encrypted: false,
+        }
+    }
+
+    #[test]
+    fn test_summary_lists_tools_files_and_totals() {
+        let manifest = Manifest {
+            version: package::MANIFEST_VERSION,
+            generated_at: "2024-01-01T00:00:00+00:00".to_string(),
+            original_root: "~/.dotfiles".to_string(),
+            config: manifest_file("config.yaml", 0o100644, 120),
+            includes: vec![],
+            tools: vec![ManifestToolEntry {
+                id: "helm".to_string(),
+                name: "Helm".to_string(),
+                root: "helm".to_string(),
+                file: "helm.sh".to_string(),
+                dependencies: vec!["brew".to_string()],
+                optional_dependencies: vec![],
+                conflicts: vec![],
+                artifact: manifest_file("helm/helm.sh", 0o100755, 30),
+                related_files: vec![
+                    manifest_file("helm/", 0o40755, 0),
+                    manifest_file("helm/values.yaml", 0o100600, 12),
+                ],
+            }],
+        };
+
+        let summary = ArchiveSummary::new(ArchiveFormat::TarZst, &manifest);
+        assert_eq!(summary.totals.files, 3);
+        assert_eq!(summary.totals.directories, 1);
+        assert_eq!(summary.totals.bytes, 162);
+        assert_eq!(
+            summary.show_lines(),
+            vec![
+                "Format:        tar.zst",
+                "Generated at:  2024-01-01T00:00:00+00:00",
+                "Original root: ~/.dotfiles",
+                "Tools (1):",
+                "  helm (Helm): 2 file(s), 42 bytes",
+                "      depends on: brew",
+                "Total: 1 tool(s), 3 file(s), 162 bytes",
+            ]
+        );
+
+        let list = summary.list_lines();
+        assert_eq!(list.len(), 6);
+        assert_eq!(
+            list[2],
+            format!("0755   30 {} helm/helm.sh", "ab".repeat(32))
+        );
+        assert_eq!(list[3], format!("0755    0 {:<64} helm/", "-"));
+        assert!(summary.files_to_json().contains("\"tool\": \"helm\""));
+    }
+}
@@ -1,47 +1,146 @@
+use crate::expand::{self, Undefined};
+use crate::variables::{self, Variables};
+use chrono::Utc;
 use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use schemars::JsonSchema;
 use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
-use std::fs::{self, File, OpenOptions};
-use std::io::{BufReader, Write};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
 #[cfg(unix)]
 use std::os::unix::fs::OpenOptionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub(crate) struct Config {
+    /// Schema version of this file; older files are upgraded when loaded.
+    // Always CONFIG_VERSION after loading; kept so the schema documents the key.
+    #[allow(dead_code)]
+    #[serde(rename = "Version", default)]
+    version: u32,
     #[serde(rename = "SystemPreferences")]
     system_preferences: SystemPreferences,
     #[serde(rename = "Preferences")]
     preferences: Preferences,
+    /// Glob patterns, relative to this file, of extra files holding `ToolsSettings` entries.
+    #[serde(rename = "Include", default)]
+    include: Vec<String>,
+    /// Values referenced as `{{ name }}` in tool `Root`, `File`, and `Env` fields.
+    #[serde(rename = "Variables", default)]
+    variables: BTreeMap<String, String>,
+    /// Named sets of `Variables` overrides, selected with `--profile`, `DOTFILES_PROFILE`, or
+    /// a name matching this machine's hostname.
+    #[serde(rename = "Profiles", default)]
+    profiles: BTreeMap<String, Profile>,
+    #[serde(skip)]
+    loaded_files: Vec<PathBuf>,
+    #[serde(skip)]
+    resolved_variables: Variables,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+struct Profile {
+    /// Overrides for top-level `Variables`.
+    #[serde(rename = "Variables", default)]
+    variables: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
 struct SystemPreferences {
+    /// Directory that stores all managed tool directories.
     #[serde(rename = "Root")]
     root: String,
+    /// Globs, relative to `Root`, of files holding secrets. Patterns without a `/` match the file
+    /// name in any directory.
+    #[serde(rename = "Secrets", default)]
+    secrets: Vec<String>,
+    /// Gitignore-style patterns, relative to `Root`, of files left out of exports.
+    #[serde(rename = "Exclude", default)]
+    exclude: Vec<String>,
+    /// Ed25519 public keys (hex, or paths to `.pub` files) whose signed archives `install` trusts.
+    #[serde(rename = "TrustedKeys", default)]
+    trusted_keys: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct Preferences {
+    /// Tools to manage.
     #[serde(rename = "ToolsSettings")]
     tools_settings: Vec<Tool>,
 }
 
+/// Layout of a file pulled in through `Include`.
 #[derive(Debug, Deserialize)]
+struct IncludedConfig {
+    #[serde(rename = "ToolsSettings", default)]
+    tools_settings: Vec<Tool>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
 pub(crate) struct Tool {
+    /// Unique identifier used to reference dependencies; generated from `Name` when omitted.
     #[serde(rename = "Id", default)]
     pub id: Option<String>,
+    /// Display label shown in the UI.
     #[serde(rename = "Name", default)]
     pub name: Option<String>,
+    /// Directory under `SystemPreferences.Root`; defaults to the lowercase `Name`.
     #[serde(rename = "Root", default)]
     pub root: Option<String>,
+    /// Script file executed when running the tool; defaults to `<name>-settings.zsh`.
     #[serde(rename = "File", default)]
     pub file: Option<String>,
+    /// Program used to run `File`; defaults to `zsh`.
+    #[serde(rename = "Interpreter", default)]
+    pub interpreter: Option<String>,
+    /// Ids of tools that must run before this one.
     #[serde(rename = "Dependencies", default)]
     pub dependencies: Vec<String>,
+    /// Ids of tools that run first when they are configured; absent ones are ignored.
+    #[serde(rename = "OptionalDependencies", default)]
+    pub optional_dependencies: Vec<String>,
+    /// Ids of tools that must not be configured together with this one.
+    #[serde(rename = "Conflicts", default)]
+    pub conflicts: Vec<String>,
+    /// Free-form labels used to group and filter tools.
+    #[serde(rename = "Tags", default)]
+    pub tags: Vec<String>,
+    /// Script in the tool directory that exits 0 while this machine still matches `File`.
+    #[serde(rename = "Check", default)]
+    pub check: Option<String>,
+    /// Files in the tool directory mapped to the paths they are symlinked to, e.g.
+    /// `.zshrc: ~/.zshrc`.
+    #[serde(rename = "Links", default)]
+    pub links: BTreeMap<String, String>,
+    /// Environment variables set when running `File`.
+    #[serde(rename = "Env", default)]
+    pub env: BTreeMap<String, String>,
+    /// File the entry was read from; used to report conflicts across files.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
 }
 
 pub(crate) const DEFAULT_CONFIG_PATH: &str = "~/.dotfiles/config.yaml";
+/// File name looked up in each tool directory when discovering tools.
+pub(crate) const TOOL_CONFIG_NAME: &str = "tool.yaml";
+/// Program used to run tool scripts when `Interpreter` is not set.
+pub(crate) const DEFAULT_INTERPRETER: &str = "zsh";
+/// Schema version written by this build. Files without a `Version` key are version 0.
+pub(crate) const CONFIG_VERSION: u32 = 1;
+
+/// Upgrade steps applied in order; entry `n` turns a version `n` document into version `n + 1`.
+const MIGRATIONS: &[fn(&mut Mapping) -> Result<()>] = &[migrate_v0_to_v1];
+
+#[derive(Debug)]
+pub(crate) struct MigrationReport {
+    pub path: PathBuf,
+    pub from_version: u32,
+    pub to_version: u32,
+    pub backup: Option<PathBuf>,
+}
 
 impl Config {
     pub(crate) fn new() -> Result<Self> {
@@ -56,22 +155,225 @@ impl Config {
     }
 
     pub(crate) fn load_from_file(path: &str) -> Result<Self> {
+        Self::load_from_file_with_profile(path, None)
+    }
+
+    /// Loads `path` with `profile` active instead of the one picked from the environment.
+    pub(crate) fn load_from_file_with_profile(path: &str, profile: Option<&str>) -> Result<Self> {
         let path = expand_home_path(path);
-        let file = File::open(&path)?;
-        let reader = BufReader::new(file);
+        let contents = fs::read_to_string(&path)?;
 
-        Ok(serde_yaml::from_reader(reader)?)
+        let mut document: Value = serde_yaml::from_str(&contents)?;
+        let from_version = migrate_document(&mut document)?;
+        let mut config: Config = if from_version == CONFIG_VERSION {
+            // Deserialize from text when nothing changed so errors keep their line numbers.
+            serde_yaml::from_str(&contents)?
+        } else {
+            serde_yaml::from_value(document)?
+        };
+        for tool in &mut config.preferences.tools_settings {
+            tool.source = Some(path.clone());
+        }
+        config.loaded_files.push(path.clone());
+        expand_home_path_strict(config.root()).map_err(|error| {
+            eyre!(
+                "Invalid SystemPreferences.Root '{}': {error}",
+                config.root()
+            )
+        })?;
+
+        config.merge_includes(&path)?;
+        config.merge_discovered_tools()?;
+        config.check_conflicting_ids()?;
+        config.apply_variables(profile)?;
+        Ok(config)
     }
 
     pub(crate) fn root(&self) -> &str {
         &self.system_preferences.root
     }
 
+    pub(crate) fn secrets(&self) -> &[String] {
+        &self.system_preferences.secrets
+    }
+
+    pub(crate) fn exclude(&self) -> &[String] {
+        &self.system_preferences.exclude
+    }
+
+    pub(crate) fn trusted_keys(&self) -> &[String] {
+        &self.system_preferences.trusted_keys
+    }
+
     pub(crate) fn tools(&self) -> &[Tool] {
         &self.preferences.tools_settings
     }
+
+    /// Variables after merging the active profile.
+    pub(crate) fn variables(&self) -> &Variables {
+        &self.resolved_variables
+    }
+
+    /// Resolves `Variables` for the active profile and interpolates them into tool fields.
+    fn apply_variables(&mut self, profile: Option<&str>) -> Result<()> {
+        let profile = variables::select_profile(profile, self.profiles.keys())?;
+        let overrides = profile
+            .as_deref()
+            .and_then(|name| self.profiles.get(name).map(|p| (name, &p.variables)));
+        let resolved = Variables::resolve(&self.variables, overrides)?;
+
+        for tool in &mut self.preferences.tools_settings {
+            let context = |error| eyre!("Tool '{}': {error}", tool.name());
+            let interpolate = |value: &Option<String>| {
+                value
+                    .as_deref()
+                    .map(|value| resolved.interpolate(value))
+                    .transpose()
+            };
+            let root = interpolate(&tool.root).map_err(context)?;
+            let file = interpolate(&tool.file).map_err(context)?;
+            let check = interpolate(&tool.check).map_err(context)?;
+            let links = tool
+                .links
+                .iter()
+                .map(|(source, target)| {
+                    Ok((resolved.interpolate(source)?, resolved.interpolate(target)?))
+                })
+                .collect::<Result<BTreeMap<_, _>>>()
+                .map_err(context)?;
+            let env = tool
+                .env
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), resolved.interpolate(value)?)))
+                .collect::<Result<BTreeMap<_, _>>>()
+                .map_err(context)?;
+            tool.root = root;
+            tool.file = file;
+            tool.check = check;
+            tool.links = links;
+            tool.env = env;
+        }
+
+        self.resolved_variables = resolved;
+        Ok(())
+    }
+
+    /// Every config file that contributed to this configuration, main file first.
+    pub(crate) fn loaded_files(&self) -> &[PathBuf] {
+        &self.loaded_files
+    }
+
+    fn merge_includes(&mut self, config_path: &Path) -> Result<()> {
+        let base_dir = config_path.parent().unwrap_or_else(|| Path::new(""));
+
+        for pattern in &self.include {
+            let mut expanded = expand_home_path_strict(pattern.trim())
+                .map_err(|error| eyre!("Invalid Include pattern '{pattern}': {error}"))?;
+            if expanded.is_relative() {
+                expanded = base_dir.join(expanded);
+            }
+
+            let entries = glob::glob(&expanded.to_string_lossy())
+                .map_err(|error| eyre!("Invalid Include pattern '{pattern}': {error}"))?;
+            let mut matches = entries.filter_map(|entry| entry.ok()).collect::<Vec<_>>();
+            matches.sort();
+
+            for included in matches {
+                if !included.is_file() || self.loaded_files.contains(&included) {
+                    continue;
+                }
+
+                let contents = fs::read_to_string(&included)?;
+                let parsed: IncludedConfig = serde_yaml::from_str(&contents).map_err(|error| {
+                    eyre!(
+                        "Failed to parse included config {}: {error}",
+                        included.display()
+                    )
+                })?;
+                for mut tool in parsed.tools_settings {
+                    tool.source = Some(included.clone());
+                    self.preferences.tools_settings.push(tool);
+                }
+                self.loaded_files.push(included);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn merge_discovered_tools(&mut self) -> Result<()> {
+        let root = expand_home_path(&self.system_preferences.root);
+        let Ok(entries) = fs::read_dir(&root) else {
+            return Ok(());
+        };
+
+        let mut candidates = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path().join(TOOL_CONFIG_NAME))
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        for candidate in candidates {
+            if self.loaded_files.contains(&candidate) {
+                continue;
+            }
+
+            let contents = fs::read_to_string(&candidate)?;
+            let mut tool: Tool = serde_yaml::from_str(&contents).map_err(|error| {
+                eyre!(
+                    "Failed to parse tool config {}: {error}",
+                    candidate.display()
+                )
+            })?;
+
+            let directory_name = candidate
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if tool.root.as_deref().is_none_or(str::is_empty) {
+                tool.root = Some(directory_name.clone());
+            }
+            if tool.name.is_none() {
+                tool.name = Some(directory_name);
+            }
+
+            tool.source = Some(candidate.clone());
+            self.preferences.tools_settings.push(tool);
+            self.loaded_files.push(candidate);
+        }
+
+        Ok(())
+    }
+
+    /// Rejects tools that resolve to the same id but come from different files.
+    /// Duplicates within a single file are left to `Tools` to resolve or report.
+    fn check_conflicting_ids(&self) -> Result<()> {
+        let mut seen: HashMap<String, &Tool> = HashMap::new();
+
+        for tool in self.tools() {
+            let id = tool.identifier().unwrap_or_else(|| tool.base_id());
+            match seen.get(&id) {
+                Some(existing) if existing.source != tool.source => {
+                    return Err(eyre!(
+                        "Tool id '{id}' is defined in both {} and {}",
+                        display_source(existing),
+                        display_source(tool)
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    seen.insert(id, tool);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn create_config_dir() -> Result<()> {
-        let home = env::var("HOME").expect("HOME environment variable is not set");
+        let home = env::var("HOME").map_err(|_| eyre!("HOME environment variable is not set"))?;
         let config_dir = PathBuf::from(home).join(".dotfiles");
         if !config_dir.exists() {
             fs::create_dir_all(&config_dir)?;
@@ -80,9 +382,14 @@ impl Config {
         if !config_file.exists() {
             const DEFAULT_CONFIG: &str = concat!(
                 "# Dotfiles Manager configuration\n",
+                "# Version: schema version of this file (upgrade with `dotfiles config migrate`)\n",
+                "Version: 1\n",
                 "# SystemPreferences.Root: absolute path that stores all managed tool directories\n",
                 "SystemPreferences:\n",
                 "  Root: ~/.dotfiles\n",
+                "#   Exclude: Optional gitignore-style patterns of files `dotfiles export` leaves out\n",
+                "#   Secrets: Optional globs (relative to Root) of files holding credentials; see `dotfiles secret`\n",
+                "#   TrustedKeys: Optional public keys (hex or .pub paths) whose signed archives `dotfiles install` trusts\n",
                 "# Preferences.ToolsSettings: list of tools to manage\n",
                 "#   Id: Optional unique identifier used to reference dependencies\n",
                 "#       (if omitted, an identifier is generated automatically)\n",
@@ -91,7 +398,22 @@ impl Config {
                 "#   Name: Optional display label (defaults to Root or 'unknown')\n",
                 "#   Root: Optional directory segment; defaults to lowercase Name\n",
                 "#   File: Optional script filename; defaults to '<name>-settings.zsh'\n",
+                "#   Interpreter: Optional program that runs File; defaults to 'zsh'\n",
+                "#   Env: Optional map of environment variables set when File runs\n",
                 "#   Dependencies: List other tool Ids this tool requires (never include its own Id)\n",
+                "#   OptionalDependencies: Tool Ids to run first when they are configured (order only)\n",
+                "#   Conflicts: Tool Ids that must not be configured alongside this tool\n",
+                "#   Tags: Optional labels used to group tools (e.g. in `dotfiles graph`)\n",
+                "#   Check: Optional script that exits 0 while the tool is still applied (used by `dotfiles status`)\n",
+                "#   Links: Optional map of files in the tool directory to the paths `dotfiles link` symlinks them to\n",
+                "#          (sources ending in `.tmpl` are rendered with Variables and written instead,\n",
+                "#          sources ending in `.enc` are decrypted and written readable by you only)\n",
+                "# Include: Optional list of glob patterns (relative to this file) whose files add more\n",
+                "#          ToolsSettings entries, e.g. `Include: [tools/*.yaml]`.\n",
+                "# A `tool.yaml` placed in a tool directory under Root is picked up automatically.\n",
+                "# Variables: Optional map of values referenced as `{{ name }}` in a tool's Root, File, Links, and Env.\n",
+                "# Profiles: Optional named `Variables` overrides, chosen with DOTFILES_PROFILE or a profile\n",
+                "#           named after this machine's hostname (see `dotfiles vars`).\n",
                 "Preferences:\n",
                 "  ToolsSettings:\n",
                 "    # - Name: Brew            # Label shown in the UI\n",
@@ -105,7 +427,7 @@ impl Config {
         }
         Ok(())
     }
-    fn create_tools_dir(&self) -> Result<()> {
+    pub(crate) fn create_tools_dir(&self) -> Result<()> {
         let root = expand_home_path(&self.system_preferences.root);
 
         for tool in self.tools() {
@@ -162,53 +484,189 @@ impl Tool {
             .clone()
             .unwrap_or_else(|| format!("{}-settings.zsh", self.name().to_lowercase()))
     }
+    pub fn interpreter(&self) -> String {
+        self.interpreter
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(DEFAULT_INTERPRETER)
+            .to_string()
+    }
+    /// Id derived from `Name` before duplicate suffixes are applied.
+    pub fn base_id(&self) -> String {
+        let base = self.name().to_lowercase().replace(' ', "-");
+        if base.is_empty() {
+            "tool".to_string()
+        } else {
+            base
+        }
+    }
     pub fn dependencies(&self) -> Vec<String> {
-        self.dependencies
+        trimmed_ids(&self.dependencies)
+    }
+
+    pub fn optional_dependencies(&self) -> Vec<String> {
+        trimmed_ids(&self.optional_dependencies)
+    }
+
+    pub fn conflicts(&self) -> Vec<String> {
+        trimmed_ids(&self.conflicts)
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        trimmed_ids(&self.tags)
+    }
+    /// `Links` with blank entries dropped.
+    pub fn links(&self) -> BTreeMap<String, String> {
+        self.links
             .iter()
-            .map(|dependency| dependency.trim())
-            .filter(|dependency| !dependency.is_empty())
-            .map(|dependency| dependency.to_string())
+            .map(|(source, target)| (source.trim(), target.trim()))
+            .filter(|(source, target)| !source.is_empty() && !target.is_empty())
+            .map(|(source, target)| (source.to_string(), target.to_string()))
             .collect()
     }
+    pub fn check_file(&self) -> Option<String> {
+        self.check
+            .as_deref()
+            .map(str::trim)
+            .filter(|check| !check.is_empty())
+            .map(str::to_string)
+    }
 }
-/// Expands environment variables in the format `${VAR_NAME}` and also handles `~/` prefix.
-/// If an environment variable is not found, the original `${VAR_NAME}` is preserved.
-pub(crate) fn expand_home_path(path: &str) -> PathBuf {
-    let expanded = expand_env_vars(path);
 
-    // Handle ~/  prefix for backward compatibility
-    if let Some(stripped) = expanded.strip_prefix("~/")
-        && let Ok(home) = env::var("HOME")
-    {
-        PathBuf::from(home).join(stripped)
-    } else {
-        PathBuf::from(expanded)
+fn trimmed_ids(ids: &[String]) -> Vec<String> {
+    ids.iter()
+        .map(|id| id.trim())
+        .filter(|id| !id.is_empty())
+        .map(|id| id.to_string())
+        .collect()
+}
+
+/// Upgrades `document` in place to [`CONFIG_VERSION`] and returns the version it started at.
+fn migrate_document(document: &mut Value) -> Result<u32> {
+    let mapping = document
+        .as_mapping_mut()
+        .ok_or_else(|| eyre!("Config must be a mapping at the top level"))?;
+
+    let from_version = match mapping.get("Version") {
+        None => 0,
+        Some(value) => value
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| eyre!("Config Version must be a non-negative integer"))?,
+    };
+    if from_version > CONFIG_VERSION {
+        return Err(eyre!(
+            "Config version {from_version} is newer than supported version {CONFIG_VERSION}; upgrade dotfiles"
+        ));
+    }
+
+    for step in &MIGRATIONS[from_version as usize..] {
+        step(mapping)?;
     }
+    mapping.insert(
+        Value::String("Version".to_string()),
+        Value::Number(CONFIG_VERSION.into()),
+    );
+
+    Ok(from_version)
+}
+
+fn migrate_v0_to_v1(_document: &mut Mapping) -> Result<()> {
+    // Version 1 only introduced the `Version` key; the layout is otherwise unchanged.
+    Ok(())
 }
 
-/// Expands environment variables using the shell.
-/// Supports `${VAR}`, `$VAR`, and other shell expansion patterns.
-/// If expansion fails, returns the original input unchanged.
-fn expand_env_vars(input: &str) -> String {
-    // Skip shell invocation if there's no $ character (no env vars to expand)
-    if !input.contains('$') {
-        return input.to_string();
+/// Rewrites the config file at `path` to the current schema version, backing up the original.
+///
+/// When the upgrade only changes the `Version` key the file is edited line by line so comments
+/// survive; otherwise the migrated document is re-serialized.
+pub(crate) fn migrate_file(path: &Path) -> Result<MigrationReport> {
+    let contents = fs::read_to_string(path)?;
+    let original: Value = serde_yaml::from_str(&contents)?;
+    let mut migrated = original.clone();
+    let from_version = migrate_document(&mut migrated)?;
+
+    let mut report = MigrationReport {
+        path: path.to_path_buf(),
+        from_version,
+        to_version: CONFIG_VERSION,
+        backup: None,
+    };
+    if from_version == CONFIG_VERSION {
+        return Ok(report);
     }
 
-    use std::process::Command;
+    let output = if without_version(&original) == without_version(&migrated) {
+        set_version_line(&contents, CONFIG_VERSION)
+    } else {
+        serde_yaml::to_string(&migrated)?
+    };
 
-    // Use shell to expand environment variables
-    let output = Command::new("sh")
-        .arg("-c")
-        .arg(format!("printf '%s' \"{}\"", input.replace('"', "\\\"")))
-        .output();
+    let timestamp = Utc::now().format("%Y%m%d%H%M%S");
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "config.yaml".to_string());
+    let backup = path.with_file_name(format!("{file_name}.bak.{timestamp}"));
+    fs::copy(path, &backup)?;
+    fs::write(path, output)?;
 
-    match output {
-        Ok(result) if result.status.success() => {
-            String::from_utf8(result.stdout).unwrap_or_else(|_| input.to_string())
-        }
-        _ => input.to_string(),
+    report.backup = Some(backup);
+    Ok(report)
+}
+
+fn without_version(document: &Value) -> Value {
+    let mut document = document.clone();
+    if let Some(mapping) = document.as_mapping_mut() {
+        mapping.remove("Version");
     }
+    document
+}
+
+fn set_version_line(contents: &str, version: u32) -> String {
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+
+    if let Some(line) = lines.iter_mut().find(|line| line.starts_with("Version:")) {
+        *line = format!("Version: {version}");
+    } else {
+        let insert_at = lines
+            .iter()
+            .position(|line| {
+                let trimmed = line.trim_start();
+                !trimmed.is_empty() && !trimmed.starts_with('#') && trimmed != "---"
+            })
+            .unwrap_or(lines.len());
+        lines.insert(insert_at, format!("Version: {version}"));
+    }
+
+    let mut output = lines.join("\n");
+    output.push('\n');
+    output
+}
+
+/// JSON Schema describing `config.yaml`, for editor completion and validation.
+pub(crate) fn json_schema() -> Result<String> {
+    let schema = schemars::schema_for!(Config);
+    Ok(serde_json::to_string_pretty(&schema)?)
+}
+
+fn display_source(tool: &Tool) -> String {
+    tool.source
+        .as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "<unknown>".to_string())
+}
+
+/// Expands environment variables and a leading `~`/`~user`. Undefined variables become empty
+/// strings; if expansion fails the input is used unchanged.
+pub(crate) fn expand_home_path(path: &str) -> PathBuf {
+    expand::expand_path(path, Undefined::Empty).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Like [`expand_home_path`], but fails on undefined variables, `${VAR:?msg}`, or unknown users.
+pub(crate) fn expand_home_path_strict(path: &str) -> Result<PathBuf> {
+    Ok(expand::expand_path(path, Undefined::Error)?)
 }
 
 #[cfg(test)]
@@ -241,6 +699,227 @@ Preferences:
         assert_eq!(config.tools()[0].name(), "TestTool");
     }
 
+    #[test]
+    fn test_load_from_file_rejects_undefined_root_variable() {
+        let dir = tempdir().unwrap();
+        let config_file = dir.path().join("config.yaml");
+        fs::write(
+            &config_file,
+            r#"
+SystemPreferences:
+  Root: ${DOTFILES_UNDEFINED_ROOT_12345}/dotfiles
+Preferences:
+  ToolsSettings: []
+"#,
+        )
+        .unwrap();
+
+        let error = Config::load_from_file(config_file.to_str().unwrap()).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("SystemPreferences.Root"));
+        assert!(message.contains("DOTFILES_UNDEFINED_ROOT_12345"));
+    }
+
+    #[test]
+    fn test_load_from_file_interpolates_variables_with_profile() {
+        let dir = tempdir().unwrap();
+        let config_file = dir.path().join("config.yaml");
+        fs::write(
+            &config_file,
+            r#"
+SystemPreferences:
+  Root: /test/root
+Variables:
+  project: personal
+  email: me@home
+Profiles:
+  work:
+    Variables:
+      project: acme
+Preferences:
+  ToolsSettings:
+    - Name: Gcloud
+      Root: gcloud-{{ project }}
+      File: "{{project}}.zsh"
+      Env:
+        GIT_EMAIL: "{{ email }}"
+"#,
+        )
+        .unwrap();
+        let path = config_file.to_str().unwrap();
+
+        let config = Config::load_from_file_with_profile(path, Some("work")).unwrap();
+        let tool = &config.tools()[0];
+        assert_eq!(tool.root_name(), "gcloud-acme");
+        assert_eq!(tool.file_name(), "acme.zsh");
+        assert_eq!(tool.env["GIT_EMAIL"], "me@home");
+        assert_eq!(config.variables().profile(), Some("work"));
+
+        let error = Config::load_from_file_with_profile(path, Some("travel")).unwrap_err();
+        assert!(error.to_string().contains("Profile 'travel'"));
+    }
+
+    #[test]
+    fn test_load_from_file_merges_includes() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("tools")).unwrap();
+        fs::write(
+            dir.path().join("config.yaml"),
+            r#"
+SystemPreferences:
+  Root: /test/root
+Include:
+  - tools/*.yaml
+Preferences:
+  ToolsSettings:
+    - Name: Brew
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("tools").join("k8s.yaml"),
+            r#"
+ToolsSettings:
+  - Name: Helm
+    Dependencies:
+      - brew
+  - Name: Krew
+"#,
+        )
+        .unwrap();
+
+        let config_file = dir.path().join("config.yaml");
+        let config = Config::load_from_file(config_file.to_str().unwrap()).unwrap();
+        let names: Vec<_> = config.tools().iter().map(Tool::name).collect();
+        assert_eq!(names, vec!["Brew", "Helm", "Krew"]);
+        assert_eq!(config.loaded_files().len(), 2);
+        assert_eq!(
+            config.tools()[1].source.as_deref(),
+            Some(dir.path().join("tools").join("k8s.yaml").as_path())
+        );
+    }
+
+    #[test]
+    fn test_load_from_file_discovers_tool_yaml() {
+        let dir = tempdir().unwrap();
+        let tool_dir = dir.path().join("gcloud");
+        fs::create_dir_all(&tool_dir).unwrap();
+        fs::write(
+            tool_dir.join(TOOL_CONFIG_NAME),
+            "File: gcloud.zsh\nDependencies:\n  - brew\n",
+        )
+        .unwrap();
+        let config_file = dir.path().join("config.yaml");
+        fs::write(
+            &config_file,
+            format!(
+                "SystemPreferences:\n  Root: {}\nPreferences:\n  ToolsSettings:\n    - Name: Brew\n",
+                dir.path().display()
+            ),
+        )
+        .unwrap();
+
+        let config = Config::load_from_file(config_file.to_str().unwrap()).unwrap();
+        assert_eq!(config.tools().len(), 2);
+        let discovered = &config.tools()[1];
+        assert_eq!(discovered.name(), "gcloud");
+        assert_eq!(discovered.root_name(), "gcloud");
+        assert_eq!(discovered.file_name(), "gcloud.zsh");
+        assert_eq!(discovered.dependencies(), vec!["brew"]);
+    }
+
+    #[test]
+    fn test_load_from_file_conflicting_ids_name_both_files() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("config.yaml"),
+            r#"
+SystemPreferences:
+  Root: /test/root
+Include:
+  - extra.yaml
+Preferences:
+  ToolsSettings:
+    - Id: brew
+      Name: Brew
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("extra.yaml"),
+            "ToolsSettings:\n  - Name: Brew\n",
+        )
+        .unwrap();
+
+        let config_file = dir.path().join("config.yaml");
+        let error = Config::load_from_file(config_file.to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Tool id 'brew'"));
+        assert!(error.contains("config.yaml"));
+        assert!(error.contains("extra.yaml"));
+    }
+
+    #[test]
+    fn test_load_from_file_migrates_unversioned_config() {
+        let dir = tempdir().unwrap();
+        let config_file = dir.path().join("config.yaml");
+        fs::write(
+            &config_file,
+            "SystemPreferences:\n  Root: /test/root\nPreferences:\n  ToolsSettings: []\n",
+        )
+        .unwrap();
+
+        let config = Config::load_from_file(config_file.to_str().unwrap()).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn test_load_from_file_rejects_newer_version() {
+        let dir = tempdir().unwrap();
+        let config_file = dir.path().join("config.yaml");
+        fs::write(
+            &config_file,
+            "Version: 99\nSystemPreferences:\n  Root: /test/root\nPreferences:\n  ToolsSettings: []\n",
+        )
+        .unwrap();
+
+        let error = Config::load_from_file(config_file.to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("newer than supported"));
+    }
+
+    #[test]
+    fn test_migrate_file_preserves_comments_and_backs_up() {
+        let dir = tempdir().unwrap();
+        let config_file = dir.path().join("config.yaml");
+        let original = "# managed by dotfiles\nSystemPreferences:\n  Root: /test/root # base\nPreferences:\n  ToolsSettings: []\n";
+        fs::write(&config_file, original).unwrap();
+
+        let report = migrate_file(&config_file).unwrap();
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.to_version, CONFIG_VERSION);
+
+        let migrated = fs::read_to_string(&config_file).unwrap();
+        assert!(migrated.starts_with("# managed by dotfiles\nVersion: 1\n"));
+        assert!(migrated.contains("Root: /test/root # base"));
+        let backup = report.backup.expect("expected a backup");
+        assert_eq!(fs::read_to_string(backup).unwrap(), original);
+
+        let report = migrate_file(&config_file).unwrap();
+        assert!(report.backup.is_none());
+    }
+
+    #[test]
+    fn test_json_schema_uses_config_keys() {
+        let schema = json_schema().unwrap();
+        assert!(schema.contains("\"SystemPreferences\""));
+        assert!(schema.contains("\"ToolsSettings\""));
+        assert!(schema.contains("\"Dependencies\""));
+        assert!(!schema.contains("\"source\""));
+    }
+
     #[test]
     fn test_tool_defaults() {
         let tool = Tool {
@@ -249,17 +928,19 @@ Preferences:
             root: None,
             file: None,
             dependencies: vec![],
+            ..Default::default()
         };
 
         assert_eq!(tool.name(), "MyTool");
         assert_eq!(tool.root_name(), "mytool");
         assert_eq!(tool.file_name(), "mytool-settings.zsh");
+        assert_eq!(tool.interpreter(), "zsh");
         assert!(tool.identifier().is_none());
     }
 
     #[test]
     fn test_expand_home_path() {
-        // Use actual system HOME to avoid shell subprocess env issues
+        // Use actual system HOME so parallel tests changing it do not interfere
         if let Ok(home) = std::env::var("HOME") {
             let expanded = expand_home_path("~/test/path");
             assert_eq!(expanded, PathBuf::from(format!("{}/test/path", home)));
@@ -277,6 +958,7 @@ Preferences:
             root: None,
             file: None,
             dependencies: vec![],
+            ..Default::default()
         };
 
         assert_eq!(tool.identifier(), Some("my-tool".to_string()));
@@ -290,6 +972,7 @@ Preferences:
             root: None,
             file: None,
             dependencies: vec![],
+            ..Default::default()
         };
 
         assert!(tool.identifier().is_none());
@@ -309,6 +992,7 @@ Preferences:
                 "   ".to_string(),
                 "dep3".to_string(),
             ],
+            ..Default::default()
         };
 
         let deps = tool.dependencies();
@@ -326,6 +1010,7 @@ Preferences:
             root: Some("".to_string()),
             file: None,
             dependencies: vec![],
+            ..Default::default()
         };
 
         assert_eq!(tool.root_name(), "mytool");
@@ -339,6 +1024,7 @@ Preferences:
             root: None,
             file: None,
             dependencies: vec![],
+            ..Default::default()
         };
 
         assert_eq!(tool.file_name(), "my tool-settings.zsh");
@@ -352,6 +1038,7 @@ Preferences:
             root: None,
             file: None,
             dependencies: vec![],
+            ..Default::default()
         };
 
         assert_eq!(tool.name(), "unknown");
@@ -365,32 +1052,32 @@ Preferences:
 
     #[test]
     fn test_expand_env_vars_single() {
-        // Use actual system HOME to avoid shell subprocess env issues
+        // Use actual system HOME so parallel tests changing it do not interfere
         if let Ok(home) = std::env::var("HOME") {
-            let expanded = expand_env_vars("${HOME}/.dotfiles");
+            let expanded = expand::expand("${HOME}/.dotfiles", Undefined::Empty).unwrap();
             assert_eq!(expanded, format!("{}/.dotfiles", home));
         }
     }
 
     #[test]
     fn test_expand_env_vars_multiple() {
-        // Use actual system env vars to avoid shell subprocess env issues
+        // Use actual system env vars so parallel tests changing them do not interfere
         if let (Ok(home), Ok(user)) = (std::env::var("HOME"), std::env::var("USER")) {
-            let expanded = expand_env_vars("${HOME}/path/${USER}/dir");
+            let expanded = expand::expand("${HOME}/path/${USER}/dir", Undefined::Empty).unwrap();
             assert_eq!(expanded, format!("{}/path/{}/dir", home, user));
         }
     }
 
     #[test]
     fn test_expand_env_vars_undefined() {
-        // Shell expands undefined variables to empty string
-        let expanded = expand_env_vars("${UNDEFINED_VAR_12345}/path");
+        // Undefined variables expand to an empty string, like the shell
+        let expanded = expand::expand("${UNDEFINED_VAR_12345}/path", Undefined::Empty).unwrap();
         assert_eq!(expanded, "/path");
     }
 
     #[test]
     fn test_expand_home_path_with_env_var() {
-        // Use actual system HOME to avoid shell subprocess env issues
+        // Use actual system HOME so parallel tests changing it do not interfere
         if let Ok(home) = std::env::var("HOME") {
             let expanded = expand_home_path("${HOME}/.dotfiles");
             assert_eq!(expanded, PathBuf::from(format!("{}/.dotfiles", home)));
@@ -399,14 +1086,13 @@ Preferences:
 
     #[test]
     fn test_expand_env_vars_no_vars() {
-        let expanded = expand_env_vars("/absolute/path/without/vars");
+        let expanded = expand::expand("/absolute/path/without/vars", Undefined::Empty).unwrap();
         assert_eq!(expanded, "/absolute/path/without/vars");
     }
 
     #[test]
     fn test_expand_env_vars_mixed_with_tilde() {
         // Test that ~/ and ${VAR} can coexist using actual system environment
-        // This avoids issues with shell subprocess not seeing test-modified env vars
         if let (Ok(home), Ok(user)) = (std::env::var("HOME"), std::env::var("USER")) {
             let expanded = expand_home_path("~/${USER}/config");
             let expected = PathBuf::from(format!("{}/{}/config", home, user));
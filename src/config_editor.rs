@@ -0,0 +1,1003 @@
+use crate::config::{self, Config, TOOL_CONFIG_NAME};
+use crate::tools::{self, Tools};
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Fields for a new `ToolsSettings` entry.
+#[derive(Debug, Default)]
+pub(crate) struct ToolSpec {
+    pub name: String,
+    pub id: Option<String>,
+    pub root: Option<String>,
+    pub file: Option<String>,
+    pub interpreter: Option<String>,
+    pub dependencies: Vec<String>,
+}
+
+/// Changes applied to an existing entry; `None` leaves a field untouched.
+#[derive(Debug, Default)]
+pub(crate) struct ToolChanges {
+    pub name: Option<String>,
+    pub root: Option<String>,
+    pub file: Option<String>,
+    pub interpreter: Option<String>,
+    /// Replaces the whole dependency list before additions and removals are applied.
+    pub dependencies: Option<Vec<String>>,
+    pub add_dependencies: Vec<String>,
+    pub remove_dependencies: Vec<String>,
+    /// `Links` entries to add or replace, keyed by source.
+    pub add_links: Vec<(String, String)>,
+    /// Sources whose `Links` entries are removed.
+    pub remove_links: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct RemovedTool {
+    pub source: PathBuf,
+    pub deleted_dir: Option<PathBuf>,
+}
+
+/// Appends a tool to `config_path`, validates the resulting graph, and scaffolds its script.
+/// Returns the id the new tool resolves to.
+pub(crate) fn add_tool(config_path: &Path, spec: &ToolSpec) -> Result<String> {
+    if spec.name.trim().is_empty() {
+        return Err(eyre!("Tool name must not be empty"));
+    }
+
+    let mut document = Document::read(config_path)?;
+    let list = document.tools_list()?;
+    document.insert_item(&list, spec);
+
+    let config = write_validated(config_path, config_path, Some(document.render()))?;
+    config.create_tools_dir()?;
+
+    let ids = tools::resolve_tool_ids(config.tools());
+    let index = config
+        .tools()
+        .iter()
+        .rposition(|tool| tool.source.as_deref() == Some(config_path))
+        .ok_or_else(|| eyre!("Added tool could not be found after writing the config"))?;
+    Ok(ids[index].clone())
+}
+
+/// Removes the tool `id` from the file that defines it. Fails when other tools still depend on it.
+pub(crate) fn remove_tool(config_path: &Path, id: &str, delete_dir: bool) -> Result<RemovedTool> {
+    let config = load_config(config_path)?;
+    let located = locate_tool(&config, id)?;
+    let tool = &config.tools()[located.index];
+    let tool_dir = config::expand_home_path(config.root()).join(tool.root_name());
+    let shared_dir = config
+        .tools()
+        .iter()
+        .enumerate()
+        .any(|(index, other)| index != located.index && other.root_name() == tool.root_name());
+
+    if delete_dir && shared_dir {
+        return Err(eyre!(
+            "Cannot delete {} because other tools use it",
+            tool_dir.display()
+        ));
+    }
+
+    let contents = if is_tool_file(&located.source) {
+        None
+    } else {
+        let mut document = Document::read(&located.source)?;
+        let list = document.tools_list()?;
+        let span = list.items.get(located.position).cloned().ok_or_else(|| {
+            eyre!(
+                "Tool '{id}' could not be located in {}",
+                located.source.display()
+            )
+        })?;
+        document.remove_item(&span);
+        Some(document.render())
+    };
+    write_validated(config_path, &located.source, contents)?;
+
+    let mut removed = RemovedTool {
+        source: located.source,
+        deleted_dir: None,
+    };
+    if delete_dir && tool_dir.is_dir() {
+        fs::remove_dir_all(&tool_dir)?;
+        removed.deleted_dir = Some(tool_dir);
+    }
+    Ok(removed)
+}
+
+/// Contents of every file `config` was loaded from with the tools not in `keep` removed,
+/// main config first. A `tool.yaml` whose tool is removed maps to `None`. Nothing is written.
+pub(crate) fn pruned_sources(
+    config: &Config,
+    keep: &BTreeSet<String>,
+) -> Result<Vec<(PathBuf, Option<String>)>> {
+    let ids = tools::resolve_tool_ids(config.tools());
+    let mut dropped: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+    for id in ids.iter().filter(|id| !keep.contains(*id)) {
+        let located = locate_tool(config, id)?;
+        dropped
+            .entry(located.source)
+            .or_default()
+            .push(located.position);
+    }
+
+    config
+        .loaded_files()
+        .iter()
+        .map(|file| {
+            let Some(positions) = dropped.get(file) else {
+                return Ok((file.clone(), Some(fs::read_to_string(file)?)));
+            };
+            if is_tool_file(file) {
+                return Ok((file.clone(), None));
+            }
+            let mut document = Document::read(file)?;
+            let list = document.tools_list()?;
+            for position in positions.iter().rev() {
+                if let Some(span) = list.items.get(*position) {
+                    document.remove_item(span);
+                }
+            }
+            if positions.len() == list.items.len() {
+                let indent = " ".repeat(list.key_indent);
+                document.lines[list.key_line] = format!("{indent}ToolsSettings: []");
+            }
+            Ok((file.clone(), Some(document.render())))
+        })
+        .collect()
+}
+
+/// Applies `changes` to the tool `id` in the file that defines it and returns that file.
+pub(crate) fn edit_tool(config_path: &Path, id: &str, changes: &ToolChanges) -> Result<PathBuf> {
+    let config = load_config(config_path)?;
+    let located = locate_tool(&config, id)?;
+    let tool = &config.tools()[located.index];
+
+    let mut document = Document::read(&located.source)?;
+    let span_for = |document: &Document| -> Result<ItemSpan> {
+        if is_tool_file(&located.source) {
+            Ok(document.whole_span())
+        } else {
+            document
+                .tools_list()?
+                .items
+                .get(located.position)
+                .cloned()
+                .ok_or_else(|| {
+                    eyre!(
+                        "Tool '{id}' could not be located in {}",
+                        located.source.display()
+                    )
+                })
+        }
+    };
+
+    let scalars = [
+        ("Name", &changes.name),
+        ("Root", &changes.root),
+        ("File", &changes.file),
+        ("Interpreter", &changes.interpreter),
+    ];
+    for (key, value) in scalars {
+        if let Some(value) = value {
+            let span = span_for(&document)?;
+            document.set_field(&span, key, &FieldValue::Scalar(value.clone()));
+        }
+    }
+
+    let mut dependencies = changes
+        .dependencies
+        .clone()
+        .unwrap_or_else(|| tool.dependencies());
+    for dependency in &changes.add_dependencies {
+        if !dependencies.contains(dependency) {
+            dependencies.push(dependency.clone());
+        }
+    }
+    dependencies.retain(|dependency| !changes.remove_dependencies.contains(dependency));
+    if dependencies != tool.dependencies() {
+        let span = span_for(&document)?;
+        document.set_field(&span, "Dependencies", &FieldValue::List(dependencies));
+    }
+
+    for (source, target) in &changes.add_links {
+        let span = span_for(&document)?;
+        document.set_map_entry(&span, "Links", source, target)?;
+    }
+    for source in &changes.remove_links {
+        let span = span_for(&document)?;
+        document.remove_map_entry(&span, "Links", source);
+    }
+
+    let config = write_validated(config_path, &located.source, Some(document.render()))?;
+    config.create_tools_dir()?;
+    Ok(located.source)
+}
+
+/// Points every `Links` entry whose source is `from` at `to`, keeping its target, and returns
+/// the ids of the tools that changed. Both paths are absolute; `to` must stay in the tool's
+/// directory. Used when `secret encrypt` or `secret decrypt` renames a file.
+pub(crate) fn rename_link_source(
+    config_path: &Path,
+    tools: &Tools,
+    from: &Path,
+    to: &Path,
+) -> Result<Vec<String>> {
+    let mut renamed = Vec::new();
+    for tool in tools.iter() {
+        let dir = tools.tool_dir(tool);
+        let Ok(new_source) = to.strip_prefix(&dir) else {
+            continue;
+        };
+        for (source, target) in &tool.links {
+            if dir.join(source) != from {
+                continue;
+            }
+            let changes = ToolChanges {
+                add_links: vec![(
+                    new_source.to_string_lossy().replace('\\', "/"),
+                    target.clone(),
+                )],
+                remove_links: vec![source.clone()],
+                ..Default::default()
+            };
+            edit_tool(config_path, &tool.id, &changes)?;
+            renamed.push(tool.id.clone());
+        }
+    }
+    Ok(renamed)
+}
+
+struct LocatedTool {
+    /// Index into `Config::tools`.
+    index: usize,
+    source: PathBuf,
+    /// Position among the entries of `source`.
+    position: usize,
+}
+
+fn load_config(config_path: &Path) -> Result<Config> {
+    Config::load_from_file(&config_path.to_string_lossy())
+}
+
+fn locate_tool(config: &Config, id: &str) -> Result<LocatedTool> {
+    let ids = tools::resolve_tool_ids(config.tools());
+    let index = ids
+        .iter()
+        .position(|candidate| candidate == id)
+        .ok_or_else(|| eyre!("Tool '{id}' is not defined"))?;
+    let source = config.tools()[index]
+        .source
+        .clone()
+        .ok_or_else(|| eyre!("Tool '{id}' has no source file"))?;
+    let position = config.tools()[..index]
+        .iter()
+        .filter(|tool| tool.source.as_deref() == Some(source.as_path()))
+        .count();
+
+    Ok(LocatedTool {
+        index,
+        source,
+        position,
+    })
+}
+
+fn is_tool_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name == std::ffi::OsStr::new(TOOL_CONFIG_NAME))
+}
+
+/// Writes `contents` to `target` (or deletes it when `None`), then reloads `config_path` and
+/// checks the tool graph strictly. The previous contents are restored if validation fails.
+fn write_validated(config_path: &Path, target: &Path, contents: Option<String>) -> Result<Config> {
+    let original = fs::read_to_string(target)?;
+    match &contents {
+        Some(contents) => write_atomic(target, contents)?,
+        None => fs::remove_file(target)?,
+    }
+
+    let validated = load_config(config_path).and_then(|config| {
+        Tools::from_config(&config, true)?;
+        Ok(config)
+    });
+    if validated.is_err() {
+        write_atomic(target, &original)?;
+    }
+    validated
+}
+
+fn write_atomic(target: &Path, contents: &str) -> Result<()> {
+    let directory = target.parent().unwrap_or_else(|| Path::new("."));
+    let mut temp = tempfile::Builder::new()
+        .prefix(".dotfiles-edit")
+        .suffix(".tmp")
+        .tempfile_in(directory)?;
+    temp.write_all(contents.as_bytes())?;
+    copy_permissions(target, &temp)?;
+    temp.persist(target)?;
+    Ok(())
+}
+
+fn copy_permissions(target: &Path, temp: &NamedTempFile) -> Result<()> {
+    if let Ok(metadata) = fs::metadata(target) {
+        fs::set_permissions(temp.path(), metadata.permissions())?;
+    }
+    Ok(())
+}
+
+enum FieldValue {
+    Scalar(String),
+    List(Vec<String>),
+}
+
+/// Line range of one mapping inside a document.
+#[derive(Clone, Debug)]
+struct ItemSpan {
+    start: usize,
+    end: usize,
+    field_indent: usize,
+    /// Line holding the `- ` that opens a list item, if any.
+    dash_line: Option<usize>,
+}
+
+struct ToolsList {
+    key_line: usize,
+    key_indent: usize,
+    flow_empty: bool,
+    item_indent: Option<usize>,
+    items: Vec<ItemSpan>,
+    /// Line after the last line that belongs to the list.
+    end: usize,
+}
+
+/// A YAML file edited line by line so that comments and layout survive.
+struct Document {
+    lines: Vec<String>,
+    trailing_newline: bool,
+}
+
+impl Document {
+    fn read(path: &Path) -> Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    fn parse(contents: &str) -> Self {
+        Self {
+            lines: contents.lines().map(str::to_string).collect(),
+            trailing_newline: contents.is_empty() || contents.ends_with('\n'),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut output = self.lines.join("\n");
+        if self.trailing_newline {
+            output.push('\n');
+        }
+        output
+    }
+
+    fn tools_list(&self) -> Result<ToolsList> {
+        let key_line = self
+            .lines
+            .iter()
+            .position(|line| !is_ignorable(line) && line.trim_start().starts_with("ToolsSettings:"))
+            .ok_or_else(|| eyre!("Could not find a ToolsSettings list to edit"))?;
+        let key_indent = indent_of(&self.lines[key_line]);
+        let rest = strip_comment(
+            self.lines[key_line]
+                .trim_start()
+                .trim_start_matches("ToolsSettings:"),
+        )
+        .trim()
+        .to_string();
+
+        let mut list = ToolsList {
+            key_line,
+            key_indent,
+            flow_empty: rest == "[]",
+            item_indent: None,
+            items: Vec::new(),
+            end: key_line + 1,
+        };
+        if list.flow_empty {
+            return Ok(list);
+        }
+        if !rest.is_empty() {
+            return Err(eyre!(
+                "ToolsSettings uses flow style, which cannot be edited safely; convert it to a block list"
+            ));
+        }
+
+        let mut current: Option<ItemSpan> = None;
+        for (index, line) in self.lines.iter().enumerate().skip(key_line + 1) {
+            if is_ignorable(line) {
+                // Comments that belong to an empty list (like the seeded examples) stay above new items.
+                if list.item_indent.is_none() && indent_of(line) > key_indent {
+                    list.end = index + 1;
+                }
+                continue;
+            }
+
+            let indent = indent_of(line);
+            let is_dash = is_dash_line(line);
+            match list.item_indent {
+                None if is_dash && indent >= key_indent => {
+                    list.item_indent = Some(indent);
+                    current = Some(ItemSpan {
+                        start: index,
+                        end: index + 1,
+                        field_indent: indent + 2,
+                        dash_line: Some(index),
+                    });
+                }
+                Some(item_indent) if is_dash && indent == item_indent => {
+                    list.items.extend(current.take());
+                    current = Some(ItemSpan {
+                        start: index,
+                        end: index + 1,
+                        field_indent: indent + 2,
+                        dash_line: Some(index),
+                    });
+                }
+                Some(item_indent) if indent > item_indent => {
+                    if let Some(span) = current.as_mut() {
+                        span.end = index + 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        list.items.extend(current);
+        if let Some(last) = list.items.last() {
+            list.end = last.end;
+        }
+
+        Ok(list)
+    }
+
+    /// Span covering a single-tool document such as `tool.yaml`.
+    fn whole_span(&self) -> ItemSpan {
+        let end = self
+            .lines
+            .iter()
+            .rposition(|line| !is_ignorable(line))
+            .map_or(0, |index| index + 1);
+        ItemSpan {
+            start: 0,
+            end,
+            field_indent: 0,
+            dash_line: None,
+        }
+    }
+
+    fn insert_item(&mut self, list: &ToolsList, spec: &ToolSpec) {
+        let item_indent = list.item_indent.unwrap_or(list.key_indent + 2);
+        let field_indent = " ".repeat(item_indent + 2);
+
+        let mut fields = Vec::new();
+        if let Some(id) = &spec.id {
+            fields.push(format!("Id: {}", yaml_scalar(id)));
+        }
+        fields.push(format!("Name: {}", yaml_scalar(&spec.name)));
+        for (key, value) in [
+            ("Root", &spec.root),
+            ("File", &spec.file),
+            ("Interpreter", &spec.interpreter),
+        ] {
+            if let Some(value) = value {
+                fields.push(format!("{key}: {}", yaml_scalar(value)));
+            }
+        }
+
+        let mut lines = Vec::new();
+        for (index, field) in fields.into_iter().enumerate() {
+            if index == 0 {
+                lines.push(format!("{}- {field}", " ".repeat(item_indent)));
+            } else {
+                lines.push(format!("{field_indent}{field}"));
+            }
+        }
+        if !spec.dependencies.is_empty() {
+            lines.push(format!("{field_indent}Dependencies:"));
+            for dependency in &spec.dependencies {
+                lines.push(format!("{field_indent}  - {}", yaml_scalar(dependency)));
+            }
+        }
+
+        let mut insert_at = list.end;
+        if list.flow_empty {
+            self.lines[list.key_line] = format!("{}ToolsSettings:", " ".repeat(list.key_indent));
+            insert_at = list.key_line + 1;
+        }
+        self.lines.splice(insert_at..insert_at, lines);
+    }
+
+    fn remove_item(&mut self, span: &ItemSpan) {
+        // Take comment lines directly above the entry with it.
+        let mut start = span.start;
+        while start > 0 {
+            let previous = self.lines[start - 1].trim_start();
+            if previous.starts_with('#')
+                && indent_of(&self.lines[start - 1]) >= span.field_indent - 2
+            {
+                start -= 1;
+            } else {
+                break;
+            }
+        }
+        self.lines.drain(start..span.end);
+    }
+
+    fn set_field(&mut self, span: &ItemSpan, key: &str, value: &FieldValue) {
+        let rendered = match value {
+            FieldValue::Scalar(value) => vec![format!("{key}: {}", yaml_scalar(value))],
+            FieldValue::List(values) if values.is_empty() => vec![format!("{key}: []")],
+            FieldValue::List(values) => std::iter::once(format!("{key}:"))
+                .chain(
+                    values
+                        .iter()
+                        .map(|value| format!("  - {}", yaml_scalar(value))),
+                )
+                .collect(),
+        };
+        let indent = " ".repeat(span.field_indent);
+
+        if let Some(line_index) = self.find_field(span, key) {
+            let extent = self.field_extent(span, line_index);
+            let prefix = if Some(line_index) == span.dash_line {
+                format!("{}- ", " ".repeat(span.field_indent - 2))
+            } else {
+                indent.clone()
+            };
+            let replacement = rendered
+                .into_iter()
+                .enumerate()
+                .map(|(index, line)| {
+                    if index == 0 {
+                        format!("{prefix}{line}")
+                    } else {
+                        format!("{indent}{line}")
+                    }
+                })
+                .collect::<Vec<_>>();
+            self.lines.splice(line_index..extent, replacement);
+        } else {
+            let lines = rendered
+                .into_iter()
+                .map(|line| format!("{indent}{line}"))
+                .collect::<Vec<_>>();
+            self.lines.splice(span.end..span.end, lines);
+        }
+    }
+
+    /// Sets `entry: value` inside the block mapping `key`, creating the mapping when absent.
+    /// Other entries, including their comments and `{{ }}` placeholders, are left as written.
+    fn set_map_entry(
+        &mut self,
+        span: &ItemSpan,
+        key: &str,
+        entry: &str,
+        value: &str,
+    ) -> Result<()> {
+        let line = format!("{}: {}", yaml_scalar(entry), yaml_scalar(value));
+        let Some(line_index) = self.find_field(span, key) else {
+            let indent = " ".repeat(span.field_indent);
+            self.lines.splice(
+                span.end..span.end,
+                [format!("{indent}{key}:"), format!("{indent}  {line}")],
+            );
+            return Ok(());
+        };
+
+        let inline = strip_comment(
+            self.lines[line_index]
+                .split_once(':')
+                .map_or("", |(_, rest)| rest),
+        )
+        .trim()
+        .to_string();
+        let entry_indent = " ".repeat(span.field_indent + 2);
+        match inline.as_str() {
+            "" => {}
+            "{}" => {
+                let field = self.lines[line_index]
+                    .split_once(':')
+                    .map_or(String::new(), |(field, _)| field.to_string());
+                self.lines[line_index] = format!("{field}:");
+                self.lines
+                    .insert(line_index + 1, format!("{entry_indent}{line}"));
+                return Ok(());
+            }
+            _ => {
+                return Err(eyre!(
+                    "{key} uses flow style, which cannot be edited safely; convert it to a block mapping"
+                ));
+            }
+        }
+
+        let extent = self.field_extent(span, line_index);
+        match self.find_map_entry(line_index, extent, entry) {
+            Some(index) => {
+                let indent = " ".repeat(indent_of(&self.lines[index]));
+                self.lines[index] = format!("{indent}{line}");
+            }
+            None => {
+                let indent = (line_index + 1..extent)
+                    .find(|&index| !is_ignorable(&self.lines[index]))
+                    .map_or(entry_indent, |index| {
+                        " ".repeat(indent_of(&self.lines[index]))
+                    });
+                self.lines.insert(extent, format!("{indent}{line}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes `entry` from the block mapping `key`, and the mapping itself once it is empty.
+    fn remove_map_entry(&mut self, span: &ItemSpan, key: &str, entry: &str) {
+        let Some(line_index) = self.find_field(span, key) else {
+            return;
+        };
+        let extent = self.field_extent(span, line_index);
+        let Some(index) = self.find_map_entry(line_index, extent, entry) else {
+            return;
+        };
+        self.lines.remove(index);
+        let extent = extent - 1;
+        if (line_index + 1..extent).all(|index| is_ignorable(&self.lines[index])) {
+            self.lines.drain(line_index..extent);
+        }
+    }
+
+    fn find_map_entry(&self, key_line: usize, extent: usize, entry: &str) -> Option<usize> {
+        let quoted = yaml_scalar(entry);
+        (key_line + 1..extent).find(|&index| {
+            let line = &self.lines[index];
+            !is_ignorable(line)
+                && line.split_once(':').is_some_and(|(candidate, _)| {
+                    let candidate = candidate.trim();
+                    candidate == entry || candidate == quoted
+                })
+        })
+    }
+
+    fn find_field(&self, span: &ItemSpan, key: &str) -> Option<usize> {
+        (span.start..span.end).find(|&index| {
+            let line = &self.lines[index];
+            if is_ignorable(line) {
+                return false;
+            }
+            let content = if Some(index) == span.dash_line {
+                line.get(span.field_indent..).unwrap_or_default()
+            } else if indent_of(line) == span.field_indent {
+                line.trim_start()
+            } else {
+                return false;
+            };
+            content
+                .split_once(':')
+                .is_some_and(|(candidate, _)| candidate.trim() == key)
+        })
+    }
+
+    /// Line after the last line that belongs to the field starting at `line_index`.
+    fn field_extent(&self, span: &ItemSpan, line_index: usize) -> usize {
+        let mut last = line_index;
+        for index in line_index + 1..span.end {
+            let line = &self.lines[index];
+            if is_ignorable(line) {
+                continue;
+            }
+            let indent = indent_of(line);
+            if indent > span.field_indent || (indent == span.field_indent && is_dash_line(line)) {
+                last = index;
+            } else {
+                break;
+            }
+        }
+        last + 1
+    }
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_ignorable(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+fn is_dash_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed == "-" || trimmed.starts_with("- ")
+}
+
+fn strip_comment(value: &str) -> &str {
+    match value.find(" #") {
+        Some(index) => &value[..index],
+        None if value.trim_start().starts_with('#') => "",
+        None => value,
+    }
+}
+
+fn yaml_scalar(value: &str) -> String {
+    serde_yaml::to_string(value)
+        .map(|text| text.trim_end().to_string())
+        .unwrap_or_else(|_| format!("{value:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const CONFIG: &str = r#"# Managed tools
+SystemPreferences:
+  Root: ROOT
+Preferences:
+  ToolsSettings:
+    # Package manager
+    - Id: brew
+      Name: Brew # keep this comment
+    - Name: Helm
+      Dependencies:
+        - brew
+"#;
+
+    fn write_config(dir: &Path, contents: &str) -> PathBuf {
+        let path = dir.join("config.yaml");
+        fs::write(&path, contents.replace("ROOT", &dir.to_string_lossy())).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_add_tool_appends_entry_and_scaffolds_script() {
+        let dir = tempdir().unwrap();
+        let path = write_config(dir.path(), CONFIG);
+
+        let spec = ToolSpec {
+            name: "Krew".to_string(),
+            interpreter: Some("bash".to_string()),
+            dependencies: vec!["brew".to_string()],
+            ..Default::default()
+        };
+        let id = add_tool(&path, &spec).unwrap();
+        assert_eq!(id, "krew");
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("# Managed tools"));
+        assert!(contents.contains("Name: Brew # keep this comment"));
+        assert!(contents.ends_with(
+            "    - Name: Krew\n      Interpreter: bash\n      Dependencies:\n        - brew\n"
+        ));
+        assert!(dir.path().join("krew").join("krew-settings.zsh").exists());
+    }
+
+    #[test]
+    fn test_add_tool_into_empty_flow_list() {
+        let dir = tempdir().unwrap();
+        let path = write_config(
+            dir.path(),
+            "SystemPreferences:\n  Root: ROOT\nPreferences:\n  ToolsSettings: []\n",
+        );
+
+        add_tool(
+            &path,
+            &ToolSpec {
+                name: "Brew".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.ends_with("  ToolsSettings:\n    - Name: Brew\n"));
+    }
+
+    #[test]
+    fn test_add_tool_rejects_missing_dependency_and_restores_file() {
+        let dir = tempdir().unwrap();
+        let path = write_config(dir.path(), CONFIG);
+        let before = fs::read_to_string(&path).unwrap();
+
+        let spec = ToolSpec {
+            name: "Krew".to_string(),
+            dependencies: vec!["missing".to_string()],
+            ..Default::default()
+        };
+        let error = add_tool(&path, &spec).unwrap_err().to_string();
+        assert!(error.contains("missing"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn test_remove_tool_refuses_when_depended_on() {
+        let dir = tempdir().unwrap();
+        let path = write_config(dir.path(), CONFIG);
+
+        assert!(remove_tool(&path, "brew", false).is_err());
+        assert!(fs::read_to_string(&path).unwrap().contains("Id: brew"));
+    }
+
+    #[test]
+    fn test_remove_tool_drops_entry_and_directory() {
+        let dir = tempdir().unwrap();
+        let path = write_config(dir.path(), CONFIG);
+        fs::create_dir_all(dir.path().join("helm")).unwrap();
+
+        let removed = remove_tool(&path, "helm", true).unwrap();
+        assert_eq!(removed.source, path);
+        assert_eq!(removed.deleted_dir, Some(dir.path().join("helm")));
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("Helm"));
+        assert!(contents.contains("    # Package manager\n    - Id: brew\n"));
+    }
+
+    #[test]
+    fn test_pruned_sources_keeps_only_selected_tools() {
+        let dir = tempdir().unwrap();
+        let path = write_config(dir.path(), CONFIG);
+        fs::create_dir_all(dir.path().join("zsh")).unwrap();
+        fs::write(dir.path().join("zsh").join(TOOL_CONFIG_NAME), "Name: Zsh\n").unwrap();
+        let config = load_config(&path).unwrap();
+        let pruned = |keep: &[&str]| {
+            let keep = keep.iter().map(|id| id.to_string()).collect();
+            pruned_sources(&config, &keep).unwrap()
+        };
+
+        let brew_only = pruned(&["brew"]);
+        assert_eq!(brew_only.len(), 2);
+        let main = brew_only[0].1.as_deref().unwrap();
+        assert!(main.contains(
+            "    # Package manager\n    - Id: brew\n      Name: Brew # keep this comment\n"
+        ));
+        assert!(!main.contains("Helm"));
+        assert_eq!(
+            brew_only[1],
+            (dir.path().join("zsh").join(TOOL_CONFIG_NAME), None)
+        );
+
+        let only_zsh = pruned(&["zsh"]);
+        assert!(
+            only_zsh[0]
+                .1
+                .as_deref()
+                .unwrap()
+                .contains("  ToolsSettings: []\n")
+        );
+        assert!(!only_zsh[0].1.as_deref().unwrap().contains("brew"));
+        assert_eq!(only_zsh[1].1.as_deref(), Some("Name: Zsh\n"));
+        assert!(!fs::read_to_string(&path).unwrap().contains("[]"));
+    }
+
+    #[test]
+    fn test_edit_tool_updates_fields_and_dependencies() {
+        let dir = tempdir().unwrap();
+        let path = write_config(dir.path(), CONFIG);
+
+        let changes = ToolChanges {
+            name: Some("Homebrew".to_string()),
+            file: Some("brew.zsh".to_string()),
+            ..Default::default()
+        };
+        edit_tool(&path, "brew", &changes).unwrap();
+
+        let changes = ToolChanges {
+            remove_dependencies: vec!["brew".to_string()],
+            ..Default::default()
+        };
+        edit_tool(&path, "helm", &changes).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(
+            "    - Id: brew\n      Name: Homebrew\n      File: brew.zsh\n    - Name: Helm\n      Dependencies: []\n"
+        ));
+    }
+
+    #[test]
+    fn test_edit_tool_in_tool_yaml() {
+        let dir = tempdir().unwrap();
+        let path = write_config(dir.path(), CONFIG);
+        fs::create_dir_all(dir.path().join("rust")).unwrap();
+        let tool_file = dir.path().join("rust").join(TOOL_CONFIG_NAME);
+        fs::write(&tool_file, "# rustup\nName: Rust\n").unwrap();
+
+        let changes = ToolChanges {
+            add_dependencies: vec!["brew".to_string()],
+            ..Default::default()
+        };
+        let edited = edit_tool(&path, "rust", &changes).unwrap();
+        assert_eq!(edited, tool_file);
+        assert_eq!(
+            fs::read_to_string(&tool_file).unwrap(),
+            "# rustup\nName: Rust\nDependencies:\n  - brew\n"
+        );
+    }
+
+    #[test]
+    fn test_edit_tool_adds_and_removes_links() {
+        let dir = tempdir().unwrap();
+        let path = write_config(
+            dir.path(),
+            "Variables:\n  project: acme\nSystemPreferences:\n  Root: ROOT\nPreferences:\n  ToolsSettings:\n    - Name: Zsh\n      Links:\n        # shell\n        '{{ project }}.zsh': ~/.acme.zsh\n    - Name: Git\n",
+        );
+
+        let add = |id: &str, source: &str, target: &str| {
+            edit_tool(
+                &path,
+                id,
+                &ToolChanges {
+                    add_links: vec![(source.to_string(), target.to_string())],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        };
+        add("zsh", ".zshrc", "~/.zshrc");
+        add("git", ".gitconfig", "~/.gitconfig");
+        add("zsh", ".zshrc", "~/.config/zsh/.zshrc");
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.ends_with(
+            "    - Name: Zsh\n      Links:\n        # shell\n        '{{ project }}.zsh': ~/.acme.zsh\n        .zshrc: ~/.config/zsh/.zshrc\n    - Name: Git\n      Links:\n        .gitconfig: ~/.gitconfig\n"
+        ));
+
+        edit_tool(
+            &path,
+            "git",
+            &ToolChanges {
+                remove_links: vec![".gitconfig".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.ends_with("        .zshrc: ~/.config/zsh/.zshrc\n    - Name: Git\n"));
+    }
+
+    #[test]
+    fn test_rename_link_source_keeps_target() {
+        let dir = tempdir().unwrap();
+        let path = write_config(
+            dir.path(),
+            "SystemPreferences:\n  Root: ROOT\nPreferences:\n  ToolsSettings:\n    - Name: Npm\n      Links:\n        .npmrc: ~/.npmrc\n        .npm-init.js: ~/.npm-init.js\n    - Name: Git\n",
+        );
+        let config = Config::load_from_file(&path.to_string_lossy()).unwrap();
+        let (tools, _) = Tools::from_config(&config, true).unwrap();
+        let npm_dir = tools.tool_dir(tools.get("npm").unwrap());
+
+        let renamed = rename_link_source(
+            &path,
+            &tools,
+            &npm_dir.join(".npmrc"),
+            &npm_dir.join(".npmrc.enc"),
+        )
+        .unwrap();
+
+        assert_eq!(renamed, ["npm"]);
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(
+            "      Links:\n        .npm-init.js: ~/.npm-init.js\n        .npmrc.enc: ~/.npmrc\n    - Name: Git\n"
+        ));
+        assert!(
+            rename_link_source(&path, &tools, &npm_dir.join("other"), &npm_dir.join("x"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_yaml_scalar_quotes_when_needed() {
+        assert_eq!(yaml_scalar("brew"), "brew");
+        assert_eq!(yaml_scalar("a: b"), "'a: b'");
+        assert_eq!(yaml_scalar("true"), "'true'");
+    }
+}
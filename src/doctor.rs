@@ -0,0 +1,486 @@
+//! Environment diagnostics for `dotfiles doctor`.
+
+use crate::config::{self, Config};
+use crate::secrets::{self, SecretPatterns};
+use crate::tools::Tools;
+use crossterm::style::Stylize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Markers in the names of files left behind by `install` and `config migrate`.
+const BACKUP_MARKERS: &[&str] = &[".bak.", ".backup-"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Diagnostic {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+impl Diagnostic {
+    fn new(name: &'static str, status: CheckStatus, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+
+    fn with_details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct DoctorReport {
+    pub checks: Vec<Diagnostic>,
+}
+
+impl DoctorReport {
+    /// Runs every check against the config at `config_path`. Checks that need a loaded
+    /// config are skipped when it cannot be loaded.
+    pub(crate) fn run(config_path: &Path) -> Self {
+        let mut checks = vec![check_home()];
+
+        let loaded = Config::load_from_file(&config_path.to_string_lossy())
+            .map_err(|error| error.to_string())
+            .and_then(|config| {
+                Tools::from_config(&config, true)
+                    .map(|(tools, _)| (config, tools))
+                    .map_err(|error| error.to_string())
+            });
+        match loaded {
+            Ok((config, tools)) => {
+                checks.push(Diagnostic::new(
+                    "config",
+                    CheckStatus::Pass,
+                    format!("{} loads", config_path.display()),
+                ));
+                let root = config::expand_home_path(config.root());
+                checks.push(check_interpreters(&tools));
+                checks.push(check_root_writable(&root));
+                checks.push(check_scripts(&tools));
+                checks.push(check_secrets(&root, config.secrets()));
+                checks.push(check_root_symlink(config_path, &root));
+                checks.push(check_backups(&[
+                    (root, usize::MAX),
+                    (parent_dir(config_path), 1),
+                ]));
+            }
+            Err(error) => {
+                checks.push(Diagnostic::new(
+                    "config",
+                    CheckStatus::Fail,
+                    format!("{} does not load: {error}", config_path.display()),
+                ));
+                checks.push(check_backups(&[(parent_dir(config_path), 1)]));
+            }
+        }
+
+        Self { checks }
+    }
+
+    pub(crate) fn has_failures(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| check.status == CheckStatus::Fail)
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("doctor report serializes to JSON")
+    }
+
+    /// Checklist lines; `color` adds terminal colors to the status markers.
+    pub(crate) fn lines(&self, color: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+        for check in &self.checks {
+            let marker = match (check.status, color) {
+                (CheckStatus::Pass, false) => "[ OK ]".to_string(),
+                (CheckStatus::Warn, false) => "[WARN]".to_string(),
+                (CheckStatus::Fail, false) => "[FAIL]".to_string(),
+                (CheckStatus::Pass, true) => format!("[{}]", " OK ".green()),
+                (CheckStatus::Warn, true) => format!("[{}]", "WARN".yellow()),
+                (CheckStatus::Fail, true) => format!("[{}]", "FAIL".red().bold()),
+            };
+            lines.push(format!("{marker} {}", check.message));
+            for detail in &check.details {
+                lines.push(format!("       {detail}"));
+            }
+        }
+        lines
+    }
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf)
+}
+
+fn check_home() -> Diagnostic {
+    match env::var_os("HOME").filter(|home| !home.is_empty()) {
+        Some(home) if Path::new(&home).is_dir() => Diagnostic::new(
+            "home",
+            CheckStatus::Pass,
+            format!("HOME is set ({})", Path::new(&home).display()),
+        ),
+        Some(home) => Diagnostic::new(
+            "home",
+            CheckStatus::Fail,
+            format!(
+                "HOME points to {}, which is not a directory",
+                Path::new(&home).display()
+            ),
+        ),
+        None => Diagnostic::new("home", CheckStatus::Fail, "HOME is not set"),
+    }
+}
+
+fn check_interpreters(tools: &Tools) -> Diagnostic {
+    let mut users: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for tool in tools.iter() {
+        users
+            .entry(tool.interpreter.as_str())
+            .or_default()
+            .push(tool.id.as_str());
+    }
+
+    let missing = users
+        .iter()
+        .filter(|(interpreter, _)| find_program(interpreter).is_none())
+        .map(|(interpreter, ids)| format!("{interpreter} (used by {})", ids.join(", ")))
+        .collect::<Vec<_>>();
+    let names = users.keys().copied().collect::<Vec<_>>().join(", ");
+    if missing.is_empty() {
+        Diagnostic::new(
+            "interpreters",
+            CheckStatus::Pass,
+            if names.is_empty() {
+                "No interpreters configured".to_string()
+            } else {
+                format!("Interpreters found on PATH: {names}")
+            },
+        )
+    } else {
+        Diagnostic::new(
+            "interpreters",
+            CheckStatus::Fail,
+            format!("{} interpreter(s) not found on PATH", missing.len()),
+        )
+        .with_details(missing)
+    }
+}
+
+/// Resolves `program` the way a shell would: paths as given, bare names through `PATH`.
+fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        let path = config::expand_home_path(program);
+        return is_executable_file(&path).then_some(path);
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| is_executable_file(candidate))
+}
+
+fn is_executable_file(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && is_executable(&metadata))
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    true
+}
+
+fn check_root_writable(root: &Path) -> Diagnostic {
+    if !root.is_dir() {
+        return Diagnostic::new(
+            "root",
+            CheckStatus::Fail,
+            format!("Root {} does not exist", root.display()),
+        );
+    }
+    match tempfile::Builder::new()
+        .prefix(".dotfiles-doctor")
+        .tempfile_in(root)
+    {
+        Ok(_) => Diagnostic::new(
+            "root",
+            CheckStatus::Pass,
+            format!("Root {} is writable", root.display()),
+        ),
+        Err(error) => Diagnostic::new(
+            "root",
+            CheckStatus::Fail,
+            format!("Root {} is not writable: {error}", root.display()),
+        ),
+    }
+}
+
+fn check_scripts(tools: &Tools) -> Diagnostic {
+    let mut unreadable = Vec::new();
+    let mut not_executable = Vec::new();
+    for tool in tools.iter() {
+        let path = tools.tool_path(tool);
+        match fs::File::open(&path).and_then(|file| file.metadata()) {
+            Ok(metadata) if metadata.is_file() => {
+                if !is_executable(&metadata) {
+                    not_executable.push(format!("{} ({})", path.display(), tool.id));
+                }
+            }
+            Ok(_) => unreadable.push(format!("{} ({}): not a file", path.display(), tool.id)),
+            Err(error) => unreadable.push(format!("{} ({}): {error}", path.display(), tool.id)),
+        }
+    }
+
+    if !unreadable.is_empty() {
+        Diagnostic::new(
+            "scripts",
+            CheckStatus::Fail,
+            format!("{} script(s) cannot be read", unreadable.len()),
+        )
+        .with_details(unreadable)
+    } else if !not_executable.is_empty() {
+        Diagnostic::new(
+            "scripts",
+            CheckStatus::Warn,
+            format!(
+                "{} script(s) are not executable (chmod +x)",
+                not_executable.len()
+            ),
+        )
+        .with_details(not_executable)
+    } else {
+        Diagnostic::new(
+            "scripts",
+            CheckStatus::Pass,
+            format!(
+                "{} script(s) are readable and executable",
+                tools.iter().count()
+            ),
+        )
+    }
+}
+
+/// `install` replaces the original config directory with a symlink to the new root; when the
+/// config directory is such a symlink it has to lead to `SystemPreferences.Root`.
+fn check_root_symlink(config_path: &Path, root: &Path) -> Diagnostic {
+    let link = parent_dir(config_path);
+    let is_symlink = fs::symlink_metadata(&link).is_ok_and(|metadata| metadata.is_symlink());
+    if !is_symlink {
+        return Diagnostic::new(
+            "symlink",
+            CheckStatus::Pass,
+            format!("{} is not a symlink", link.display()),
+        );
+    }
+
+    let target = fs::read_link(&link)
+        .map(|target| target.display().to_string())
+        .unwrap_or_else(|_| "?".to_string());
+    match (fs::canonicalize(&link), fs::canonicalize(root)) {
+        (Ok(resolved), Ok(root_resolved)) if resolved == root_resolved => Diagnostic::new(
+            "symlink",
+            CheckStatus::Pass,
+            format!("{} -> {target} matches Root", link.display()),
+        ),
+        (Ok(_), _) => Diagnostic::new(
+            "symlink",
+            CheckStatus::Fail,
+            format!(
+                "{} -> {target}, but Root is {}",
+                link.display(),
+                root.display()
+            ),
+        ),
+        (Err(_), _) => Diagnostic::new(
+            "symlink",
+            CheckStatus::Fail,
+            format!("{} -> {target} is dangling", link.display()),
+        ),
+    }
+}
+
+/// Plaintext files matching `Secrets` should be encrypted with `dotfiles secret encrypt`.
+fn check_secrets(root: &Path, globs: &[String]) -> Diagnostic {
+    let patterns = match SecretPatterns::new(globs) {
+        Ok(patterns) => patterns,
+        Err(error) => return Diagnostic::new("secrets", CheckStatus::Fail, error.to_string()),
+    };
+    if patterns.is_empty() {
+        return Diagnostic::new("secrets", CheckStatus::Pass, "No Secrets configured");
+    }
+    let files = secrets::scan(root, &patterns);
+    let plaintext = files
+        .iter()
+        .filter(|secret| !secret.encrypted)
+        .map(|secret| secret.path.display().to_string())
+        .collect::<Vec<_>>();
+    if plaintext.is_empty() {
+        Diagnostic::new(
+            "secrets",
+            CheckStatus::Pass,
+            format!("{} secret(s), all encrypted", files.len()),
+        )
+    } else {
+        Diagnostic::new(
+            "secrets",
+            CheckStatus::Warn,
+            format!(
+                "{} secret(s) stored in plaintext (run `dotfiles secret encrypt`)",
+                plaintext.len()
+            ),
+        )
+        .with_details(plaintext)
+    }
+}
+
+/// Looks for backups in each directory, descending at most `max_depth` levels.
+fn check_backups(dirs: &[(PathBuf, usize)]) -> Diagnostic {
+    let mut backups = dirs
+        .iter()
+        .filter(|(dir, _)| dir.is_dir())
+        .flat_map(|(dir, max_depth)| {
+            WalkDir::new(dir)
+                .max_depth(*max_depth)
+                .into_iter()
+                .filter_entry(|entry| entry.file_name() != ".git")
+                .filter_map(Result::ok)
+                .filter(|entry| {
+                    let name = entry.file_name().to_string_lossy();
+                    BACKUP_MARKERS.iter().any(|marker| name.contains(marker))
+                })
+                .map(|entry| entry.into_path())
+        })
+        .collect::<Vec<_>>();
+    backups.sort();
+    backups.dedup();
+
+    if backups.is_empty() {
+        Diagnostic::new("backups", CheckStatus::Pass, "No stale backups")
+    } else {
+        Diagnostic::new(
+            "backups",
+            CheckStatus::Warn,
+            format!("{} backup(s) left by install or migrate", backups.len()),
+        )
+        .with_details(
+            backups
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn status_of(report: &DoctorReport, name: &str) -> CheckStatus {
+        report
+            .checks
+            .iter()
+            .find(|check| check.name == name)
+            .map(|check| check.status)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_doctor_reports_missing_interpreter_scripts_and_backups() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("brew")).unwrap();
+        fs::write(root.join("brew").join("brew.sh"), "echo brew\n").unwrap();
+        fs::write(root.join("config.yaml.bak.20250101000000"), "old").unwrap();
+        let config_path = root.join("config.yaml");
+        fs::write(
+            &config_path,
+            format!(
+                "Version: 1\nSystemPreferences:\n  Root: {}\nPreferences:\n  ToolsSettings:\n    - Name: brew\n      File: brew.sh\n      Interpreter: no-such-shell-12345\n    - Name: git\n      File: git.sh\n      Interpreter: sh\n",
+                root.display()
+            ),
+        )
+        .unwrap();
+
+        let report = DoctorReport::run(&config_path);
+
+        assert_eq!(status_of(&report, "config"), CheckStatus::Pass);
+        assert_eq!(status_of(&report, "interpreters"), CheckStatus::Fail);
+        assert_eq!(status_of(&report, "root"), CheckStatus::Pass);
+        assert_eq!(status_of(&report, "scripts"), CheckStatus::Fail);
+        assert_eq!(status_of(&report, "secrets"), CheckStatus::Pass);
+        assert_eq!(status_of(&report, "symlink"), CheckStatus::Pass);
+        assert_eq!(status_of(&report, "backups"), CheckStatus::Warn);
+        assert!(report.has_failures());
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        let interpreters = &json["checks"][2];
+        assert_eq!(interpreters["name"], "interpreters");
+        assert_eq!(interpreters["status"], "fail");
+        assert_eq!(
+            interpreters["details"][0],
+            "no-such-shell-12345 (used by brew)"
+        );
+        assert!(
+            report.lines(false)[0].starts_with("[ OK ] HOME")
+                || report.lines(false)[0].starts_with("[FAIL] HOME")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_root_symlink() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        let other = dir.path().join("other");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        let link = dir.path().join(".dotfiles");
+        std::os::unix::fs::symlink(&root, &link).unwrap();
+        let config_path = link.join("config.yaml");
+
+        assert_eq!(
+            check_root_symlink(&config_path, &root).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            check_root_symlink(&config_path, &other).status,
+            CheckStatus::Fail
+        );
+
+        fs::remove_dir(&root).unwrap();
+        let dangling = check_root_symlink(&config_path, &other);
+        assert_eq!(dangling.status, CheckStatus::Fail);
+        assert!(dangling.message.contains("dangling"));
+    }
+
+    #[test]
+    fn test_find_program() {
+        assert!(find_program("sh").is_some());
+        assert!(find_program("no-such-program-12345").is_none());
+        assert!(find_program("/no/such/dir/sh").is_none());
+    }
+}
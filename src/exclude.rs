@@ -0,0 +1,110 @@
+//! Files kept out of exports: `.dotfilesignore` files (gitignore syntax) at the root and in
+//! each tool directory, `SystemPreferences.Exclude`, and `dotfiles export --exclude`.
+
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+pub(crate) const IGNORE_FILE_NAME: &str = ".dotfilesignore";
+
+/// Rules that apply to the whole root: its `.dotfilesignore` plus the configured and
+/// command-line patterns, all relative to the root.
+pub(crate) struct ExcludeRules {
+    global: Gitignore,
+}
+
+impl ExcludeRules {
+    pub(crate) fn new(root: &Path, patterns: &[String]) -> Result<Self, ignore::Error> {
+        let mut builder = GitignoreBuilder::new(root);
+        add_ignore_file(&mut builder, root)?;
+        for pattern in patterns.iter().map(|pattern| pattern.trim()) {
+            if !pattern.is_empty() {
+                builder.add_line(None, pattern)?;
+            }
+        }
+        Ok(Self {
+            global: builder.build()?,
+        })
+    }
+
+    /// Adds the `.dotfilesignore` of `tool_dir`, whose rules take precedence over the root's.
+    pub(crate) fn for_tool(&self, tool_dir: &Path) -> Result<ToolExcludes<'_>, ignore::Error> {
+        let mut builder = GitignoreBuilder::new(tool_dir);
+        add_ignore_file(&mut builder, tool_dir)?;
+        Ok(ToolExcludes {
+            global: &self.global,
+            local: builder.build()?,
+        })
+    }
+}
+
+pub(crate) struct ToolExcludes<'a> {
+    global: &'a Gitignore,
+    local: Gitignore,
+}
+
+impl ToolExcludes<'_> {
+    /// Whether `path` (absolute) or one of its parent directories is excluded.
+    pub(crate) fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        match matched(&self.local, path, is_dir) {
+            Match::Ignore(_) => true,
+            Match::Whitelist(_) => false,
+            Match::None => matched(self.global, path, is_dir).is_ignore(),
+        }
+    }
+}
+
+fn matched<'a>(
+    rules: &'a Gitignore,
+    path: &Path,
+    is_dir: bool,
+) -> Match<&'a ignore::gitignore::Glob> {
+    if path.starts_with(rules.path()) && path != rules.path() {
+        rules.matched_path_or_any_parents(path, is_dir)
+    } else {
+        Match::None
+    }
+}
+
+fn add_ignore_file(builder: &mut GitignoreBuilder, dir: &Path) -> Result<(), ignore::Error> {
+    let ignore_file = dir.join(IGNORE_FILE_NAME);
+    match builder.add(&ignore_file) {
+        Some(error) if ignore_file.exists() => Err(error),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_root_tool_and_configured_rules() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let tool_dir = root.join("node");
+        fs::create_dir_all(tool_dir.join("node_modules/left-pad")).unwrap();
+        fs::write(
+            root.join(IGNORE_FILE_NAME),
+            ".DS_Store\n*.swp\nnode_modules/\n",
+        )
+        .unwrap();
+        fs::write(tool_dir.join(IGNORE_FILE_NAME), "cache/\n!keep.swp\n").unwrap();
+
+        let rules = ExcludeRules::new(root, &["*.log".to_string()]).unwrap();
+        let excludes = rules.for_tool(&tool_dir).unwrap();
+
+        assert!(excludes.is_excluded(&tool_dir.join(".DS_Store"), false));
+        assert!(excludes.is_excluded(&tool_dir.join(".init.lua.swp"), false));
+        assert!(!excludes.is_excluded(&tool_dir.join("keep.swp"), false));
+        assert!(excludes.is_excluded(&tool_dir.join("node_modules"), true));
+        assert!(excludes.is_excluded(&tool_dir.join("node_modules/left-pad/index.js"), false));
+        assert!(excludes.is_excluded(&tool_dir.join("cache/data"), false));
+        assert!(excludes.is_excluded(&tool_dir.join("debug.log"), false));
+        assert!(!excludes.is_excluded(&tool_dir.join("node.sh"), false));
+        assert!(!excludes.is_excluded(Path::new("/elsewhere/debug.txt"), false));
+        assert!(ExcludeRules::new(root, &["[z-a]".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! In-process expansion of environment variables and `~` in configured paths.
+//!
+//! Supports `$VAR`, `${VAR}`, `${VAR:-default}`, `${VAR:?message}`, `~` and `~user`.
+//! Nothing is ever executed: `$(...)` and backticks are left as literal text.
+
+use std::env;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// How to treat a variable that is not set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Undefined {
+    /// Substitute an empty string, like the shell does.
+    Empty,
+    /// Fail with [`ExpandError::Undefined`].
+    Error,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub(crate) enum ExpandError {
+    #[error("environment variable '{0}' is not set")]
+    Undefined(String),
+    #[error("{name}: {message}")]
+    Required { name: String, message: String },
+    #[error("missing '}}' in '{0}'")]
+    Unterminated(String),
+    #[error("unsupported expansion '${{{0}}}'")]
+    Unsupported(String),
+    #[error("unknown user '{0}'")]
+    UnknownUser(String),
+}
+
+/// Expands variables in `input`, then a leading `~` or `~user`.
+pub(crate) fn expand_path(input: &str, undefined: Undefined) -> Result<PathBuf, ExpandError> {
+    let expanded = expand(input, undefined)?;
+    expand_tilde(&expanded, undefined).map(PathBuf::from)
+}
+
+/// Expands environment variables in `input`.
+pub(crate) fn expand(input: &str, undefined: Undefined) -> Result<String, ExpandError> {
+    expand_with(input, undefined, &|name| env::var(name).ok())
+}
+
+fn expand_with(
+    input: &str,
+    undefined: Undefined,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, ExpandError> {
+    if !input.contains('$') && !input.contains('\\') {
+        return Ok(input.to_string());
+    }
+
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '\\' if rest.starts_with('$') => {
+                output.push('$');
+                rest = &rest[1..];
+            }
+            '$' if rest.starts_with('{') => {
+                let end = closing_brace(rest)
+                    .ok_or_else(|| ExpandError::Unterminated(input.to_string()))?;
+                output.push_str(&expand_braced(&rest[1..end], undefined, lookup)?);
+                rest = &rest[end + 1..];
+            }
+            '$' => {
+                let name_len = name_length(rest);
+                if name_len == 0 {
+                    output.push('$');
+                } else {
+                    let name = &rest[..name_len];
+                    output.push_str(&resolve(name, undefined, lookup)?);
+                    rest = &rest[name_len..];
+                }
+            }
+            c => output.push(c),
+        }
+    }
+    Ok(output)
+}
+
+/// Expands the inside of `${...}`.
+fn expand_braced(
+    body: &str,
+    undefined: Undefined,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, ExpandError> {
+    let name_len = name_length(body);
+    let (name, operator) = body.split_at(name_len);
+    if name.is_empty() {
+        return Err(ExpandError::Unsupported(body.to_string()));
+    }
+
+    let value = lookup(name).filter(|value| !value.is_empty());
+    if operator.is_empty() {
+        return resolve(name, undefined, lookup);
+    }
+    if let Some(default) = operator.strip_prefix(":-") {
+        return match value {
+            Some(value) => Ok(value),
+            None => expand_with(default, undefined, lookup),
+        };
+    }
+    if let Some(message) = operator.strip_prefix(":?") {
+        return match value {
+            Some(value) => Ok(value),
+            None => Err(ExpandError::Required {
+                name: name.to_string(),
+                message: if message.is_empty() {
+                    "parameter null or not set".to_string()
+                } else {
+                    expand_with(message, undefined, lookup)?
+                },
+            }),
+        };
+    }
+    Err(ExpandError::Unsupported(body.to_string()))
+}
+
+fn resolve(
+    name: &str,
+    undefined: Undefined,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, ExpandError> {
+    match (lookup(name), undefined) {
+        (Some(value), _) => Ok(value),
+        (None, Undefined::Empty) => Ok(String::new()),
+        (None, Undefined::Error) => Err(ExpandError::Undefined(name.to_string())),
+    }
+}
+
+/// Length of the variable name at the start of `input` (`[A-Za-z_][A-Za-z0-9_]*`).
+fn name_length(input: &str) -> usize {
+    let mut chars = input.char_indices();
+    match chars.next() {
+        Some((_, c)) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return 0,
+    }
+    chars
+        .find(|(_, c)| !(c.is_ascii_alphanumeric() || *c == '_'))
+        .map_or(input.len(), |(index, _)| index)
+}
+
+/// Index of the `}` closing the `{` at the start of `input`, allowing nested `${...}`.
+fn closing_brace(input: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (index, c) in input.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn expand_tilde(input: &str, undefined: Undefined) -> Result<String, ExpandError> {
+    let Some(after) = input.strip_prefix('~') else {
+        return Ok(input.to_string());
+    };
+    let (user, rest) = after.split_at(after.find('/').unwrap_or(after.len()));
+
+    let home = if user.is_empty() {
+        match (env::var("HOME"), undefined) {
+            (Ok(home), _) => home,
+            (Err(_), Undefined::Empty) => return Ok(input.to_string()),
+            (Err(_), Undefined::Error) => return Err(ExpandError::Undefined("HOME".to_string())),
+        }
+    } else {
+        match (user_home(user), undefined) {
+            (Some(home), _) => home,
+            (None, Undefined::Empty) => return Ok(input.to_string()),
+            (None, Undefined::Error) => return Err(ExpandError::UnknownUser(user.to_string())),
+        }
+    };
+    Ok(format!("{home}{rest}"))
+}
+
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<String> {
+    nix::unistd::User::from_name(user)
+        .ok()
+        .flatten()
+        .map(|user| user.dir.to_string_lossy().into_owned())
+}
+
+/// Other users' home directories are only looked up on Unix.
+#[cfg(not(unix))]
+fn user_home(_user: &str) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_test(input: &str, undefined: Undefined) -> Result<String, ExpandError> {
+        let lookup = |name: &str| match name {
+            "ROOT" => Some("/opt/dotfiles".to_string()),
+            "USER" => Some("alice".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        expand_with(input, undefined, &lookup)
+    }
+
+    #[test]
+    fn test_expand_plain_and_braced_variables() {
+        assert_eq!(
+            expand_test("$ROOT/${USER}_x/$USER.d", Undefined::Error).unwrap(),
+            "/opt/dotfiles/alice_x/alice.d"
+        );
+        assert_eq!(expand_test("no vars", Undefined::Error).unwrap(), "no vars");
+    }
+
+    #[test]
+    fn test_expand_undefined_modes() {
+        assert_eq!(
+            expand_test("${MISSING}/path", Undefined::Empty).unwrap(),
+            "/path"
+        );
+        assert_eq!(
+            expand_test("$MISSING/path", Undefined::Error),
+            Err(ExpandError::Undefined("MISSING".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_expand_default_and_required() {
+        assert_eq!(
+            expand_test("${MISSING:-$ROOT/fallback}", Undefined::Error).unwrap(),
+            "/opt/dotfiles/fallback"
+        );
+        assert_eq!(
+            expand_test("${EMPTY:-dflt}", Undefined::Error).unwrap(),
+            "dflt"
+        );
+        assert_eq!(
+            expand_test("${USER:-dflt}", Undefined::Error).unwrap(),
+            "alice"
+        );
+        assert_eq!(
+            expand_test("${MISSING:?set MISSING first}", Undefined::Empty),
+            Err(ExpandError::Required {
+                name: "MISSING".to_string(),
+                message: "set MISSING first".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_expand_never_executes_commands() {
+        assert_eq!(
+            expand_test("$(rm -rf /)/`id`", Undefined::Error).unwrap(),
+            "$(rm -rf /)/`id`"
+        );
+        assert_eq!(
+            expand_test(r"\$ROOT costs $5", Undefined::Error).unwrap(),
+            "$ROOT costs $5"
+        );
+    }
+
+    #[test]
+    fn test_expand_rejects_malformed_braces() {
+        assert!(matches!(
+            expand_test("${ROOT", Undefined::Empty),
+            Err(ExpandError::Unterminated(_))
+        ));
+        assert_eq!(
+            expand_test("${ROOT/x/y}", Undefined::Empty),
+            Err(ExpandError::Unsupported("ROOT/x/y".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_expand_tilde_for_current_and_other_users() {
+        if let Ok(home) = env::var("HOME") {
+            assert_eq!(
+                expand_tilde("~/x", Undefined::Error).unwrap(),
+                format!("{home}/x")
+            );
+            assert_eq!(expand_tilde("~", Undefined::Error).unwrap(), home);
+        }
+        assert_eq!(expand_tilde("a/~/b", Undefined::Error).unwrap(), "a/~/b");
+        if let Some(root_home) = user_home("root") {
+            assert_eq!(
+                expand_tilde("~root/.dotfiles", Undefined::Error).unwrap(),
+                format!("{root_home}/.dotfiles")
+            );
+        }
+        assert_eq!(
+            expand_tilde("~no-such-user-12345/x", Undefined::Empty).unwrap(),
+            "~no-such-user-12345/x"
+        );
+        assert_eq!(
+            expand_tilde("~no-such-user-12345/x", Undefined::Error),
+            Err(ExpandError::UnknownUser("no-such-user-12345".to_string()))
+        );
+    }
+}
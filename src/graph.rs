@@ -0,0 +1,404 @@
+//! Renders the tool graph for `dotfiles graph` as Graphviz DOT, Mermaid, JSON, or text.
+
+use crate::tools::{ToolError, ToolItem, Tools};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+    Text,
+}
+
+/// How a tool relates to the highlighted tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Highlight {
+    Selected,
+    Ancestor,
+    Descendant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum EdgeKind {
+    Required,
+    Optional,
+    Conflict,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+struct Edge {
+    from: String,
+    to: String,
+    kind: EdgeKind,
+}
+
+#[derive(Debug, Serialize)]
+struct Node<'a> {
+    id: &'a str,
+    name: &'a str,
+    stage: usize,
+    tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    highlight: Option<Highlight>,
+}
+
+#[derive(Debug, Serialize)]
+struct GraphDocument<'a> {
+    stages: Vec<Vec<&'a str>>,
+    tools: Vec<Node<'a>>,
+    edges: Vec<Edge>,
+}
+
+/// Renders every tool, grouped by execution stage. When `highlight` names a tool, it and its
+/// ancestors and descendants are marked.
+pub(crate) fn render(
+    tools: &Tools,
+    format: GraphFormat,
+    highlight: Option<&str>,
+) -> Result<String, ToolError> {
+    let graph = Graph::new(tools, highlight)?;
+    Ok(match format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Mermaid => graph.to_mermaid(),
+        GraphFormat::Json => graph.to_json(),
+        GraphFormat::Text => graph.to_text(tools, highlight),
+    })
+}
+
+struct Graph<'a> {
+    stages: Vec<Vec<&'a ToolItem>>,
+    edges: Vec<Edge>,
+    highlight: Option<&'a str>,
+    ancestors: BTreeSet<String>,
+    descendants: BTreeSet<String>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(tools: &'a Tools, highlight: Option<&'a str>) -> Result<Self, ToolError> {
+        if let Some(id) = highlight
+            && tools.get(id).is_none()
+        {
+            return Err(ToolError::UnknownTool(id.to_string()));
+        }
+
+        let stages = tools
+            .execution_stages()
+            .iter()
+            .map(|stage| {
+                stage
+                    .iter()
+                    .filter_map(|tool| tools.get(&tool.id))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let mut edges = BTreeSet::new();
+        for tool in tools.iter() {
+            for dependency in &tool.dependencies {
+                edges.insert(Edge::new(dependency, &tool.id, EdgeKind::Required));
+            }
+            for dependency in &tool.optional_dependencies {
+                edges.insert(Edge::new(dependency, &tool.id, EdgeKind::Optional));
+            }
+            for conflict in &tool.conflicts {
+                // Conflicts are symmetric; only draw them between configured tools, once.
+                if tools.get(conflict).is_some() {
+                    let (from, to) = if tool.id < *conflict {
+                        (&tool.id, conflict)
+                    } else {
+                        (conflict, &tool.id)
+                    };
+                    edges.insert(Edge::new(from, to, EdgeKind::Conflict));
+                }
+            }
+        }
+
+        Ok(Self {
+            stages,
+            edges: edges.into_iter().collect(),
+            highlight,
+            ancestors: highlight.map(|id| tools.ancestors(id)).unwrap_or_default(),
+            descendants: highlight
+                .map(|id| tools.descendants(id))
+                .unwrap_or_default(),
+        })
+    }
+
+    fn highlight_of(&self, id: &str) -> Option<Highlight> {
+        if self.highlight == Some(id) {
+            Some(Highlight::Selected)
+        } else if self.ancestors.contains(id) {
+            Some(Highlight::Ancestor)
+        } else if self.descendants.contains(id) {
+            Some(Highlight::Descendant)
+        } else {
+            None
+        }
+    }
+
+    fn stage_tools(&self) -> impl Iterator<Item = (usize, &'a ToolItem)> + '_ {
+        self.stages
+            .iter()
+            .enumerate()
+            .flat_map(|(index, stage)| stage.iter().map(move |tool| (index + 1, *tool)))
+    }
+
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph dotfiles {\n  rankdir=LR;\n  node [shape=box];\n");
+        for (index, stage) in self.stages.iter().enumerate() {
+            let _ = writeln!(out, "  subgraph cluster_stage_{} {{", index + 1);
+            let _ = writeln!(out, "    label=\"Stage {}\";", index + 1);
+            for tool in stage {
+                let mut label = format!("{}\\n({})", tool.name, tool.id);
+                if !tool.tags.is_empty() {
+                    let _ = write!(label, "\\n[{}]", tool.tags.join(", "));
+                }
+                let mut attributes = format!("label={}", dot_quote(&label));
+                if let Some(color) = self.highlight_of(&tool.id).map(|h| match h {
+                    Highlight::Selected => "gold",
+                    Highlight::Ancestor => "lightblue",
+                    Highlight::Descendant => "palegreen",
+                }) {
+                    let _ = write!(attributes, ", style=filled, fillcolor={color}");
+                }
+                let _ = writeln!(out, "    {} [{attributes}];", dot_quote(&tool.id));
+            }
+            out.push_str("  }\n");
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Required => "",
+                EdgeKind::Optional => " [style=dashed]",
+                EdgeKind::Conflict => {
+                    " [style=dotted, color=red, dir=none, constraint=false, label=\"conflicts\"]"
+                }
+            };
+            let _ = writeln!(
+                out,
+                "  {} -> {}{style};",
+                dot_quote(&edge.from),
+                dot_quote(&edge.to)
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for (index, stage) in self.stages.iter().enumerate() {
+            let _ = writeln!(out, "  subgraph stage_{0}[\"Stage {0}\"]", index + 1);
+            for tool in stage {
+                let mut label = format!("{} ({})", tool.name, tool.id);
+                if !tool.tags.is_empty() {
+                    let _ = write!(label, "<br/>[{}]", tool.tags.join(", "));
+                }
+                let _ = writeln!(
+                    out,
+                    "    {}[\"{}\"]",
+                    mermaid_id(&tool.id),
+                    label.replace('"', "#quot;")
+                );
+            }
+            out.push_str("  end\n");
+        }
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                EdgeKind::Required => "-->",
+                EdgeKind::Optional => "-.->",
+                EdgeKind::Conflict => "x--x",
+            };
+            let _ = writeln!(
+                out,
+                "  {} {arrow} {}",
+                mermaid_id(&edge.from),
+                mermaid_id(&edge.to)
+            );
+        }
+        if self.highlight.is_some() {
+            out.push_str("  classDef selected fill:#ffd700,stroke:#333\n");
+            out.push_str("  classDef ancestor fill:#add8e6\n");
+            out.push_str("  classDef descendant fill:#98fb98\n");
+            for (_, tool) in self.stage_tools() {
+                let class = match self.highlight_of(&tool.id) {
+                    Some(Highlight::Selected) => "selected",
+                    Some(Highlight::Ancestor) => "ancestor",
+                    Some(Highlight::Descendant) => "descendant",
+                    None => continue,
+                };
+                let _ = writeln!(out, "  class {} {class}", mermaid_id(&tool.id));
+            }
+        }
+        out
+    }
+
+    fn to_json(&self) -> String {
+        let document = GraphDocument {
+            stages: self
+                .stages
+                .iter()
+                .map(|stage| stage.iter().map(|tool| tool.id.as_str()).collect())
+                .collect(),
+            tools: self
+                .stage_tools()
+                .map(|(stage, tool)| Node {
+                    id: &tool.id,
+                    name: &tool.name,
+                    stage,
+                    tags: &tool.tags,
+                    highlight: self.highlight_of(&tool.id),
+                })
+                .collect(),
+            edges: self
+                .edges
+                .iter()
+                .map(|edge| Edge::new(&edge.from, &edge.to, edge.kind))
+                .collect(),
+        };
+        let mut json = serde_json::to_string_pretty(&document).expect("graph serializes to JSON");
+        json.push('\n');
+        json
+    }
+
+    fn to_text(&self, tools: &Tools, highlight: Option<&str>) -> String {
+        let mut out = String::new();
+        for (index, stage) in self.stages.iter().enumerate() {
+            let _ = writeln!(out, "Stage {}", index + 1);
+            for tool in stage {
+                let marker = match self.highlight_of(&tool.id) {
+                    Some(Highlight::Selected) => "*",
+                    Some(Highlight::Ancestor) => "^",
+                    Some(Highlight::Descendant) => "v",
+                    None => "-",
+                };
+                let _ = write!(out, "  {marker} {}", tool.display_name());
+                if !tool.tags.is_empty() {
+                    let _ = write!(out, " [{}]", tool.tags.join(", "));
+                }
+                out.push('\n');
+            }
+        }
+        let map = tools.dependency_map_lines(highlight);
+        if !map.is_empty() {
+            out.push('\n');
+            for line in map {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+impl Edge {
+    fn new(from: &str, to: &str, kind: EdgeKind) -> Self {
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            kind,
+        }
+    }
+}
+
+fn dot_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\\\""))
+}
+
+/// Mermaid node ids may only contain letters, digits, and underscores, so every other byte
+/// (underscores included) is written as `_` plus two hex digits to keep distinct ids distinct.
+fn mermaid_id(id: &str) -> String {
+    let mut escaped = String::from("t_");
+    for byte in id.bytes() {
+        if byte.is_ascii_alphanumeric() {
+            escaped.push(char::from(byte));
+        } else {
+            let _ = write!(escaped, "_{byte:02x}");
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_dot_groups_stages_and_highlights() {
+        let tools = Tools::new_with_test_data();
+
+        let dot = render(&tools, GraphFormat::Dot, Some("helm")).unwrap();
+
+        assert!(dot.starts_with("digraph dotfiles {"));
+        assert!(dot.contains("subgraph cluster_stage_3 {"));
+        assert!(dot.contains("\"brew\" -> \"helm\";"));
+        assert!(dot.contains("\"helm\" [label=\"Helm\\n(helm)\", style=filled, fillcolor=gold];"));
+        assert!(
+            dot.contains("\"brew\" [label=\"Brew\\n(brew)\", style=filled, fillcolor=lightblue];")
+        );
+        assert!(
+            dot.contains("\"zsh\" [label=\"Zsh\\n(zsh)\", style=filled, fillcolor=palegreen];")
+        );
+        assert!(dot.contains("\"gcloud\" [label=\"Gcloud\\n(gcloud)\"];"));
+    }
+
+    #[test]
+    fn test_render_mermaid() {
+        let tools = Tools::new_with_test_data();
+
+        let mermaid = render(&tools, GraphFormat::Mermaid, None).unwrap();
+
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(
+            mermaid
+                .contains("  subgraph stage_1[\"Stage 1\"]\n    t_brew[\"Brew (brew)\"]\n  end\n")
+        );
+        assert!(mermaid.contains("  t_rust --> t_zsh\n"));
+        assert!(!mermaid.contains("classDef"));
+    }
+
+    #[test]
+    fn test_mermaid_ids_stay_distinct() {
+        assert_eq!(mermaid_id("a-b"), "t_a_2db");
+        assert_eq!(mermaid_id("a_b"), "t_a_5fb");
+        assert_ne!(mermaid_id("a-b"), mermaid_id("a_b"));
+        assert_ne!(mermaid_id("a.b"), mermaid_id("a_2eb"));
+    }
+
+    #[test]
+    fn test_render_json_is_stable() {
+        let tools = Tools::new_with_test_data();
+
+        let json = render(&tools, GraphFormat::Json, Some("zsh")).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["stages"][0][0], "brew");
+        assert_eq!(value["stages"][2][0], "zsh");
+        assert_eq!(value["tools"][5]["highlight"], "selected");
+        assert_eq!(value["tools"][0]["highlight"], "ancestor");
+        assert_eq!(value["edges"].as_array().unwrap().len(), 8);
+        assert_eq!(value["edges"][0]["kind"], "required");
+        assert_eq!(
+            json,
+            render(&tools, GraphFormat::Json, Some("zsh")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_render_text_and_unknown_highlight() {
+        let tools = Tools::new_with_test_data();
+
+        let text = render(&tools, GraphFormat::Text, Some("gcloud")).unwrap();
+        assert!(text.starts_with("Stage 1\n  ^ Brew (brew)\nStage 2\n  * Gcloud (gcloud)\n"));
+        assert!(text.contains("  v Zsh (zsh)\n"));
+
+        assert!(matches!(
+            render(&tools, GraphFormat::Text, Some("nope")),
+            Err(ToolError::UnknownTool(_))
+        ));
+    }
+}
@@ -0,0 +1,946 @@
+//! Files in tool directories placed where they belong under `$HOME` by `dotfiles link` and
+//! removed by `dotfiles unlink`. Sources ending in `.tmpl` are rendered with the machine's
+//! variables and sources ending in `.enc` are decrypted; both are written as plain files, and
+//! everything else is symlinked.
+
+use crate::config;
+use crate::package::{self, PackageError};
+use crate::run_record::{self, RunRecord};
+use crate::secrets::{self, Passphrase, SecretError};
+use crate::tools::{ToolError, ToolItem, Tools};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Suffix that marks a `Links` source as a template.
+pub(crate) const TEMPLATE_SUFFIX: &str = ".tmpl";
+
+#[derive(Debug, Error)]
+pub(crate) enum LinkError {
+    #[error(transparent)]
+    Tools(#[from] ToolError),
+    #[error("{}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("Failed to back up {}: {source}", path.display())]
+    Backup { path: PathBuf, source: PackageError },
+    #[error("Tools '{first}' and '{second}' both link {}", target.display())]
+    DuplicateTarget {
+        target: PathBuf,
+        first: String,
+        second: String,
+    },
+    #[error("Tool '{tool_id}' links {}, which does not exist", source_path.display())]
+    SourceMissing {
+        tool_id: String,
+        source_path: PathBuf,
+    },
+    #[error("Failed to render {}: {message}", path.display())]
+    Render { path: PathBuf, message: String },
+    #[error(transparent)]
+    Secret(#[from] SecretError),
+    #[error(
+        "{} target(s) already exist: {} (rerun with --backup to move them aside)",
+        .0.len(),
+        display_paths(.0)
+    )]
+    Conflicts(Vec<PathBuf>),
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// What `link` does with targets that already exist and are not the expected symlink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Conflicts {
+    Fail,
+    Backup,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LinkState {
+    Linked,
+    Unlinked,
+    /// Something other than the expected symlink or rendered file occupies the target.
+    Conflict,
+    SourceMissing,
+    /// A rendered template or decrypted secret no longer matches its source or the variables.
+    Outdated,
+    /// A rendered template or decrypted secret was changed by hand after `link` wrote it.
+    Edited,
+}
+
+impl LinkState {
+    /// Whether `link` has to move the target aside before placing the link.
+    fn needs_backup(self) -> bool {
+        matches!(self, LinkState::Conflict | LinkState::Edited)
+    }
+}
+
+impl fmt::Display for LinkState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkState::Linked => write!(f, "linked"),
+            LinkState::Unlinked => write!(f, "not linked"),
+            LinkState::Conflict => write!(f, "conflict"),
+            LinkState::SourceMissing => write!(f, "source missing"),
+            LinkState::Outdated => write!(f, "out of date"),
+            LinkState::Edited => write!(f, "edited by hand"),
+        }
+    }
+}
+
+/// How a link's source is placed at its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LinkKind {
+    Symlink,
+    /// A `.tmpl` source, rendered and written.
+    Template,
+    /// An `.enc` source, decrypted and written readable by the owner only.
+    Secret,
+}
+
+impl LinkKind {
+    pub(crate) fn of(source: &str) -> Self {
+        if is_template(source) {
+            LinkKind::Template
+        } else if secrets::is_encrypted_path(Path::new(source)) {
+            LinkKind::Secret
+        } else {
+            LinkKind::Symlink
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct LinkStatus {
+    pub tool_id: String,
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub kind: LinkKind,
+    pub state: LinkState,
+}
+
+/// Counts shown next to each tool in the Dotfiles tab.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct LinkSummary {
+    pub linked: usize,
+    pub total: usize,
+    pub conflicts: usize,
+    pub edited: usize,
+}
+
+impl LinkSummary {
+    pub(crate) fn of(statuses: &[LinkStatus]) -> Self {
+        let count = |state| {
+            statuses
+                .iter()
+                .filter(|status| status.state == state)
+                .count()
+        };
+        Self {
+            linked: count(LinkState::Linked),
+            total: statuses.len(),
+            conflicts: count(LinkState::Conflict),
+            edited: count(LinkState::Edited),
+        }
+    }
+
+    /// Compact form for the tool list, e.g. `2/3` or `0/1!` when a target needs attention.
+    pub(crate) fn short(&self) -> String {
+        let marker = if self.conflicts + self.edited > 0 {
+            "!"
+        } else {
+            ""
+        };
+        format!("{}/{}{marker}", self.linked, self.total)
+    }
+}
+
+impl fmt::Display for LinkSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} linked", self.linked, self.total)?;
+        if self.conflicts > 0 {
+            write!(f, ", {} conflict(s)", self.conflicts)?;
+        }
+        if self.edited > 0 {
+            write!(f, ", {} edited by hand", self.edited)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LinkAction {
+    Created,
+    AlreadyLinked,
+    /// The existing target was moved to `backup` before the link was placed.
+    Replaced {
+        backup: PathBuf,
+    },
+    Removed,
+    /// `unlink` left the target alone because it is not a link this tool created.
+    Skipped(LinkState),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct LinkChange {
+    pub tool_id: String,
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub kind: LinkKind,
+    pub action: LinkAction,
+}
+
+impl fmt::Display for LinkChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let target = self.target.display();
+        let source = self.source.display();
+        let (placed, arrow) = match self.kind {
+            LinkKind::Symlink => ("linked  ", "->"),
+            LinkKind::Template => ("rendered", "<-"),
+            LinkKind::Secret => ("decrypted", "<-"),
+        };
+        write!(f, "{}: ", self.tool_id)?;
+        match &self.action {
+            LinkAction::Created => write!(f, "{placed}   {target} {arrow} {source}"),
+            LinkAction::AlreadyLinked => write!(f, "unchanged  {target} {arrow} {source}"),
+            LinkAction::Replaced { backup } => write!(
+                f,
+                "{placed}   {target} {arrow} {source} (backup: {})",
+                backup.display()
+            ),
+            LinkAction::Removed => write!(f, "unlinked   {target}"),
+            LinkAction::Skipped(state) => write!(f, "skipped    {target} ({state})"),
+        }
+    }
+}
+
+/// Where each of the tool's `Links` stands on this machine. `record` holds what `link`
+/// last wrote for templates and secrets.
+pub(crate) fn statuses(tools: &Tools, tool: &ToolItem, record: &RunRecord) -> Vec<LinkStatus> {
+    let dir = tools.tool_dir(tool);
+    tool.links
+        .iter()
+        .map(|(source, target)| {
+            let kind = LinkKind::of(source);
+            let source = dir.join(source);
+            let target = target_path(target);
+            let state = match kind {
+                LinkKind::Symlink => symlink_state(&source, &target),
+                LinkKind::Template => template_state(tools, &source, &target, record),
+                LinkKind::Secret => secret_state(&source, &target, record),
+            };
+            LinkStatus {
+                tool_id: tool.id.clone(),
+                source,
+                target,
+                kind,
+                state,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn is_template(source: &str) -> bool {
+    source.ends_with(TEMPLATE_SUFFIX)
+}
+
+/// Renders the template at `source` with the tools' variables and machine facts.
+pub(crate) fn render(tools: &Tools, source: &Path) -> Result<String, LinkError> {
+    let contents = fs::read_to_string(source).map_err(|error| LinkError::Io {
+        path: source.to_path_buf(),
+        source: error,
+    })?;
+    tools
+        .variables()
+        .interpolate(&contents)
+        .map_err(|error| LinkError::Render {
+            path: source.to_path_buf(),
+            message: error.to_string(),
+        })
+}
+
+/// Expands `~` and variables; relative targets are taken relative to `$HOME`.
+fn target_path(target: &str) -> PathBuf {
+    let path = config::expand_home_path(target);
+    if path.is_absolute() {
+        path
+    } else {
+        config::expand_home_path("~").join(path)
+    }
+}
+
+fn symlink_state(source: &Path, target: &Path) -> LinkState {
+    if fs::symlink_metadata(source).is_err() {
+        return LinkState::SourceMissing;
+    }
+    match fs::symlink_metadata(target) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => LinkState::Unlinked,
+        Ok(metadata) if metadata.is_symlink() && points_to(target, source) => LinkState::Linked,
+        _ => LinkState::Conflict,
+    }
+}
+
+fn points_to(link: &Path, source: &Path) -> bool {
+    match (fs::canonicalize(link), fs::canonicalize(source)) {
+        (Ok(resolved), Ok(source)) => resolved == source,
+        _ => false,
+    }
+}
+
+/// Digest of the written file at `target`, or the state to report when there is none.
+fn written_digest(source: &Path, target: &Path) -> Result<String, LinkState> {
+    if !source.is_file() {
+        return Err(LinkState::SourceMissing);
+    }
+    match fs::symlink_metadata(target) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Err(LinkState::Unlinked),
+        Ok(metadata) if metadata.is_file() => {}
+        _ => return Err(LinkState::Conflict),
+    }
+    run_record::script_digest(target).map_err(|_| LinkState::Conflict)
+}
+
+/// Compares the file on disk with what `link` last wrote and with a fresh rendering.
+fn template_state(tools: &Tools, source: &Path, target: &Path, record: &RunRecord) -> LinkState {
+    let on_disk = match written_digest(source, target) {
+        Ok(digest) => digest,
+        Err(state) => return state,
+    };
+    let rendered = render(tools, source)
+        .ok()
+        .map(|contents| run_record::content_digest(&contents));
+    let up_to_date = rendered.as_ref() == Some(&on_disk);
+    match record.template(target) {
+        Some(written) if written.sha256 != on_disk => LinkState::Edited,
+        _ if up_to_date => LinkState::Linked,
+        Some(_) => LinkState::Outdated,
+        None => LinkState::Conflict,
+    }
+}
+
+/// Compares the file on disk with what `link` last wrote, and the encrypted source with the
+/// one it was decrypted from, so no passphrase is needed.
+fn secret_state(source: &Path, target: &Path, record: &RunRecord) -> LinkState {
+    let on_disk = match written_digest(source, target) {
+        Ok(digest) => digest,
+        Err(state) => return state,
+    };
+    let source_digest = run_record::script_digest(source).ok();
+    match record.template(target) {
+        Some(written) if written.sha256 != on_disk => LinkState::Edited,
+        Some(written) if written.source_sha256 == source_digest => LinkState::Linked,
+        Some(_) => LinkState::Outdated,
+        None => LinkState::Conflict,
+    }
+}
+
+/// Statuses for the tools in `tool_ids` (every tool when empty), in run order.
+fn selected_statuses(
+    tools: &Tools,
+    tool_ids: &[String],
+    record: &RunRecord,
+) -> Result<Vec<LinkStatus>, LinkError> {
+    if let Some(unknown) = tool_ids.iter().find(|id| tools.get(id).is_none()) {
+        return Err(ToolError::UnknownTool(unknown.clone()).into());
+    }
+    Ok(tools
+        .iter()
+        .filter(|tool| tool_ids.is_empty() || tool_ids.contains(&tool.id))
+        .flat_map(|tool| statuses(tools, tool, record))
+        .collect())
+}
+
+/// Two configured tools must never claim the same target, whichever tools are being linked.
+fn check_duplicate_targets(tools: &Tools) -> Result<(), LinkError> {
+    let mut owners: BTreeMap<PathBuf, &str> = BTreeMap::new();
+    for tool in tools.iter() {
+        for target in tool.links.values() {
+            let target = target_path(target);
+            if let Some(first) = owners.get(&target) {
+                return Err(LinkError::DuplicateTarget {
+                    target,
+                    first: first.to_string(),
+                    second: tool.id.clone(),
+                });
+            }
+            owners.insert(target, &tool.id);
+        }
+    }
+    Ok(())
+}
+
+/// Places the links for the tools in `tool_ids` (every tool when empty) and notes rendered
+/// templates and decrypted secrets in `record`. Nothing is touched unless every link can be
+/// placed: conflicts are checked up front, and if placing one fails the links created and
+/// targets backed up so far are put back. `passphrase` is only asked for when a secret has
+/// to be decrypted.
+pub(crate) fn link(
+    tools: &Tools,
+    tool_ids: &[String],
+    conflicts: Conflicts,
+    passphrase: &mut Passphrase,
+    record: &mut RunRecord,
+) -> Result<Vec<LinkChange>, LinkError> {
+    check_duplicate_targets(tools)?;
+    let statuses = selected_statuses(tools, tool_ids, record)?;
+
+    if let Some(missing) = statuses
+        .iter()
+        .find(|status| status.state == LinkState::SourceMissing)
+    {
+        return Err(LinkError::SourceMissing {
+            tool_id: missing.tool_id.clone(),
+            source_path: missing.source.clone(),
+        });
+    }
+    let mut rendered = BTreeMap::new();
+    for status in &statuses {
+        match status.kind {
+            LinkKind::Symlink => {}
+            LinkKind::Template => {
+                rendered.insert(
+                    status.target.clone(),
+                    render(tools, &status.source)?.into_bytes(),
+                );
+            }
+            LinkKind::Secret if status.state != LinkState::Linked => {
+                let contents = secrets::decrypt_file(&status.source, passphrase.get(false)?)?;
+                rendered.insert(status.target.clone(), contents);
+            }
+            LinkKind::Secret => {}
+        }
+    }
+    let conflicting = statuses
+        .iter()
+        .filter(|status| status.state.needs_backup())
+        .map(|status| status.target.clone())
+        .collect::<Vec<_>>();
+    if conflicts == Conflicts::Fail && !conflicting.is_empty() {
+        return Err(LinkError::Conflicts(conflicting));
+    }
+
+    let mut changes = Vec::new();
+    let mut undo = Vec::new();
+    for status in statuses {
+        match place_link(&status, &rendered, record, &mut undo) {
+            Ok(action) => changes.push(LinkChange {
+                tool_id: status.tool_id,
+                source: status.source,
+                target: status.target,
+                kind: status.kind,
+                action,
+            }),
+            Err(error) => {
+                roll_back(undo);
+                return Err(error);
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// A change `link` made on disk, kept so a later failure can take it back.
+enum Placed {
+    Created(PathBuf),
+    MovedAside { target: PathBuf, backup: PathBuf },
+}
+
+fn place_link(
+    status: &LinkStatus,
+    rendered: &BTreeMap<PathBuf, Vec<u8>>,
+    record: &mut RunRecord,
+    undo: &mut Vec<Placed>,
+) -> Result<LinkAction, LinkError> {
+    let place = |record: &mut RunRecord| match rendered.get(&status.target) {
+        Some(contents) if status.kind == LinkKind::Secret => {
+            secrets::write_private(&status.target, contents)?;
+            let source_digest =
+                run_record::script_digest(&status.source).map_err(|source| LinkError::Io {
+                    path: status.source.clone(),
+                    source,
+                })?;
+            record.record_secret(&status.target, contents, source_digest);
+            Ok(())
+        }
+        Some(contents) => {
+            write_rendered(&status.source, &status.target, contents)?;
+            record.record_template(&status.target, contents);
+            Ok(())
+        }
+        None => create_symlink(&status.source, &status.target),
+    };
+    let action = match status.state {
+        LinkState::Linked => return Ok(LinkAction::AlreadyLinked),
+        LinkState::Conflict | LinkState::Edited => {
+            let backup =
+                package::backup_existing(&status.target).map_err(|source| LinkError::Backup {
+                    path: status.target.clone(),
+                    source,
+                })?;
+            undo.push(Placed::MovedAside {
+                target: status.target.clone(),
+                backup: backup.clone(),
+            });
+            place(record)?;
+            LinkAction::Replaced { backup }
+        }
+        LinkState::Unlinked | LinkState::Outdated | LinkState::SourceMissing => {
+            // An outdated rendered file is ours either way, so only new targets are undone.
+            if fs::symlink_metadata(&status.target).is_err() {
+                undo.push(Placed::Created(status.target.clone()));
+            }
+            place(record)?;
+            LinkAction::Created
+        }
+    };
+    Ok(action)
+}
+
+/// Best effort: removes what was placed and moves backed-up targets back, newest first.
+fn roll_back(undo: Vec<Placed>) {
+    for step in undo.into_iter().rev() {
+        match step {
+            Placed::Created(target) => {
+                let _ = fs::remove_file(target);
+            }
+            Placed::MovedAside { target, backup } => {
+                let _ = fs::remove_file(&target);
+                let _ = fs::rename(backup, target);
+            }
+        }
+    }
+}
+
+/// Removes what `link` placed for the tools in `tool_ids` (every tool when empty). Targets
+/// that are anything else, including rendered files edited by hand, are left in place.
+pub(crate) fn unlink(
+    tools: &Tools,
+    tool_ids: &[String],
+    record: &mut RunRecord,
+) -> Result<Vec<LinkChange>, LinkError> {
+    selected_statuses(tools, tool_ids, record)?
+        .into_iter()
+        .map(|status| {
+            let owned = match status.state {
+                LinkState::Linked => true,
+                LinkState::Outdated => status.kind != LinkKind::Symlink,
+                _ => false,
+            };
+            let action = if owned {
+                fs::remove_file(&status.target).map_err(|source| LinkError::Io {
+                    path: status.target.clone(),
+                    source,
+                })?;
+                record.forget_template(&status.target);
+                LinkAction::Removed
+            } else {
+                LinkAction::Skipped(status.state)
+            };
+            Ok(LinkChange {
+                tool_id: status.tool_id,
+                source: status.source,
+                target: status.target,
+                kind: status.kind,
+                action,
+            })
+        })
+        .collect()
+}
+
+fn create_symlink(source: &Path, target: &Path) -> Result<(), LinkError> {
+    let io_error = |source| LinkError::Io {
+        path: target.to_path_buf(),
+        source,
+    };
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    symlink(source, target).map_err(io_error)
+}
+
+/// Writes a rendered template with the permissions of its source.
+fn write_rendered(source: &Path, target: &Path, contents: &[u8]) -> Result<(), LinkError> {
+    let io_error = |source| LinkError::Io {
+        path: target.to_path_buf(),
+        source,
+    };
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    fs::write(target, contents).map_err(io_error)?;
+    let permissions = fs::metadata(source).map_err(io_error)?.permissions();
+    fs::set_permissions(target, permissions).map_err(io_error)
+}
+
+#[cfg(unix)]
+pub(crate) fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn symlink(_source: &Path, _target: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symlinks are only supported on Unix",
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use tempfile::tempdir;
+
+    fn load_tools(root: &Path, tools_yaml: &str) -> Tools {
+        let config_path = root.join("config.yaml");
+        fs::write(
+            &config_path,
+            format!(
+                "Version: 1\nSystemPreferences:\n  Root: {}\nPreferences:\n  ToolsSettings:\n{tools_yaml}",
+                root.display()
+            ),
+        )
+        .unwrap();
+        let config = Config::load_from_file(&config_path.to_string_lossy()).unwrap();
+        Tools::from_config(&config, true).unwrap().0
+    }
+
+    #[test]
+    fn test_link_backs_up_conflicts_and_unlink_removes_only_own_links() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        let home = dir.path().join("home");
+        fs::create_dir_all(root.join("zsh")).unwrap();
+        fs::create_dir_all(root.join("nvim").join("config")).unwrap();
+        fs::create_dir_all(&home).unwrap();
+        fs::write(root.join("zsh").join(".zshrc"), "new").unwrap();
+        fs::write(root.join("nvim").join("config").join("init.lua"), "").unwrap();
+        fs::write(home.join(".zshrc"), "old").unwrap();
+        let mut record = RunRecord::default();
+        let tools = load_tools(
+            &root,
+            &format!(
+                "    - Name: zsh\n      Links:\n        .zshrc: {home}/.zshrc\n    - Name: nvim\n      Links:\n        config: {home}/.config/nvim\n",
+                home = home.display()
+            ),
+        );
+
+        let error = link(
+            &tools,
+            &[],
+            Conflicts::Fail,
+            &mut Passphrase::fixed("pass"),
+            &mut record,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(error, LinkError::Conflicts(ref paths) if paths == &[home.join(".zshrc")])
+        );
+        assert!(fs::symlink_metadata(home.join(".config")).is_err());
+
+        let changes = link(
+            &tools,
+            &[],
+            Conflicts::Backup,
+            &mut Passphrase::fixed("pass"),
+            &mut record,
+        )
+        .unwrap();
+        let action_of = |changes: &[LinkChange], id: &str| {
+            changes
+                .iter()
+                .find(|change| change.tool_id == id)
+                .map(|change| change.action.clone())
+                .unwrap()
+        };
+        let LinkAction::Replaced { backup } = action_of(&changes, "zsh") else {
+            panic!("expected a backup, got {:?}", action_of(&changes, "zsh"));
+        };
+        assert_eq!(fs::read_to_string(backup).unwrap(), "old");
+        assert_eq!(action_of(&changes, "nvim"), LinkAction::Created);
+        assert_eq!(fs::read_to_string(home.join(".zshrc")).unwrap(), "new");
+        assert!(home.join(".config/nvim/init.lua").is_file());
+        let zsh = tools.get("zsh").unwrap();
+        assert_eq!(
+            LinkSummary::of(&statuses(&tools, zsh, &record)),
+            LinkSummary {
+                linked: 1,
+                total: 1,
+                conflicts: 0,
+                edited: 0
+            }
+        );
+
+        let again = link(
+            &tools,
+            &["nvim".to_string()],
+            Conflicts::Fail,
+            &mut Passphrase::fixed("pass"),
+            &mut record,
+        )
+        .unwrap();
+        assert_eq!(again.len(), 1);
+        assert_eq!(again[0].action, LinkAction::AlreadyLinked);
+
+        fs::remove_file(home.join(".zshrc")).unwrap();
+        fs::write(home.join(".zshrc"), "local").unwrap();
+        let changes = unlink(&tools, &[], &mut record).unwrap();
+        assert_eq!(
+            action_of(&changes, "zsh"),
+            LinkAction::Skipped(LinkState::Conflict)
+        );
+        assert_eq!(action_of(&changes, "nvim"), LinkAction::Removed);
+        assert_eq!(fs::read_to_string(home.join(".zshrc")).unwrap(), "local");
+        assert!(fs::symlink_metadata(home.join(".config/nvim")).is_err());
+        assert!(root.join("nvim/config/init.lua").is_file());
+    }
+
+    #[test]
+    fn test_link_puts_back_earlier_links_when_one_fails() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        let home = dir.path().join("home");
+        fs::create_dir_all(root.join("zsh")).unwrap();
+        fs::create_dir_all(root.join("nvim")).unwrap();
+        fs::create_dir_all(&home).unwrap();
+        fs::write(root.join("zsh").join(".zshrc"), "new").unwrap();
+        fs::write(root.join("nvim").join("init.lua"), "").unwrap();
+        fs::write(home.join(".zshrc"), "old").unwrap();
+        // A file where nvim's target directory should be, so placing that link fails.
+        fs::write(home.join(".config"), "").unwrap();
+        let mut record = RunRecord::default();
+        let tools = load_tools(
+            &root,
+            &format!(
+                "    - Name: zsh\n      Links:\n        .zshrc: {home}/.zshrc\n    - Name: nvim\n      Links:\n        init.lua: {home}/.config/nvim/init.lua\n",
+                home = home.display()
+            ),
+        );
+
+        assert!(
+            link(
+                &tools,
+                &[],
+                Conflicts::Backup,
+                &mut Passphrase::fixed("pass"),
+                &mut record,
+            )
+            .is_err()
+        );
+
+        assert!(
+            !fs::symlink_metadata(home.join(".zshrc"))
+                .unwrap()
+                .is_symlink()
+        );
+        assert_eq!(fs::read_to_string(home.join(".zshrc")).unwrap(), "old");
+        let leftovers = fs::read_dir(&home)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.contains(".bak."))
+            .collect::<Vec<_>>();
+        assert!(leftovers.is_empty(), "backups left behind: {leftovers:?}");
+    }
+
+    #[test]
+    fn test_link_rejects_duplicate_targets_and_missing_sources() {
+        let dir = tempdir().unwrap();
+        let home = dir.path().join("home");
+        fs::create_dir_all(dir.path().join("a")).unwrap();
+        fs::write(dir.path().join("a").join("rc"), "").unwrap();
+        let mut record = RunRecord::default();
+        let tools = load_tools(
+            dir.path(),
+            &format!(
+                "    - Name: a\n      Links:\n        rc: {home}/.rc\n    - Name: b\n      Links:\n        rc: {home}/.rc\n",
+                home = home.display()
+            ),
+        );
+        assert!(matches!(
+            link(&tools, &["a".to_string()], Conflicts::Fail, &mut Passphrase::fixed("pass"), &mut record),
+            Err(LinkError::DuplicateTarget { ref first, ref second, .. }) if first != second
+        ));
+
+        let tools = load_tools(
+            dir.path(),
+            &format!(
+                "    - Name: b\n      Links:\n        rc: {home}/.rc\n",
+                home = home.display()
+            ),
+        );
+        assert!(matches!(
+            link(&tools, &[], Conflicts::Fail, &mut Passphrase::fixed("pass"), &mut record),
+            Err(LinkError::SourceMissing { ref tool_id, .. }) if tool_id == "b"
+        ));
+        assert!(matches!(
+            link(
+                &tools,
+                &["zsh".to_string()],
+                Conflicts::Fail,
+                &mut Passphrase::fixed("pass"),
+                &mut record
+            ),
+            Err(LinkError::Tools(ToolError::UnknownTool(_)))
+        ));
+    }
+
+    #[test]
+    fn test_templates_are_rendered_and_hand_edits_detected() {
+        let dir = tempdir().unwrap();
+        let home = dir.path().join("home");
+        fs::create_dir_all(dir.path().join("git")).unwrap();
+        fs::write(
+            dir.path().join("git").join("gitconfig.tmpl"),
+            "[user]\n  email = {{ email }}\n# {{ machine.os }}\n",
+        )
+        .unwrap();
+        let config_path = dir.path().join("config.yaml");
+        let write_config = |email: &str| {
+            fs::write(
+                &config_path,
+                format!(
+                    "Version: 1\nSystemPreferences:\n  Root: {}\nVariables:\n  email: {email}\nPreferences:\n  ToolsSettings:\n    - Name: git\n      Links:\n        gitconfig.tmpl: {}/.gitconfig\n",
+                    dir.path().display(),
+                    home.display()
+                ),
+            )
+            .unwrap();
+            let config = Config::load_from_file(&config_path.to_string_lossy()).unwrap();
+            Tools::from_config(&config, true).unwrap().0
+        };
+        let target = home.join(".gitconfig");
+        let mut record = RunRecord::default();
+        let state = |tools: &Tools, record: &RunRecord| {
+            statuses(tools, tools.get("git").unwrap(), record)[0].state
+        };
+
+        let tools = write_config("me@home");
+        let changes = link(
+            &tools,
+            &[],
+            Conflicts::Fail,
+            &mut Passphrase::fixed("pass"),
+            &mut record,
+        )
+        .unwrap();
+        assert_eq!(changes[0].kind, LinkKind::Template);
+        assert_eq!(changes[0].action, LinkAction::Created);
+        assert!(!fs::symlink_metadata(&target).unwrap().is_symlink());
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            format!("[user]\n  email = me@home\n# {}\n", std::env::consts::OS)
+        );
+        assert_eq!(state(&tools, &record), LinkState::Linked);
+
+        let tools = write_config("me@work");
+        assert_eq!(state(&tools, &record), LinkState::Outdated);
+        link(
+            &tools,
+            &[],
+            Conflicts::Fail,
+            &mut Passphrase::fixed("pass"),
+            &mut record,
+        )
+        .unwrap();
+        assert!(fs::read_to_string(&target).unwrap().contains("me@work"));
+
+        fs::write(&target, "hand edited\n").unwrap();
+        assert_eq!(state(&tools, &record), LinkState::Edited);
+        assert!(matches!(
+            link(
+                &tools,
+                &[],
+                Conflicts::Fail,
+                &mut Passphrase::fixed("pass"),
+                &mut record
+            ),
+            Err(LinkError::Conflicts(_))
+        ));
+        let changes = unlink(&tools, &[], &mut record).unwrap();
+        assert_eq!(changes[0].action, LinkAction::Skipped(LinkState::Edited));
+
+        link(
+            &tools,
+            &[],
+            Conflicts::Backup,
+            &mut Passphrase::fixed("pass"),
+            &mut record,
+        )
+        .unwrap();
+        let changes = unlink(&tools, &[], &mut record).unwrap();
+        assert_eq!(changes[0].action, LinkAction::Removed);
+        assert!(!target.exists());
+        assert!(record.template(&target).is_none());
+    }
+
+    #[test]
+    fn test_secrets_are_decrypted_without_a_passphrase_for_status() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        let home = dir.path().join("home");
+        fs::create_dir_all(root.join("npm")).unwrap();
+        let source = root.join("npm").join("npmrc.enc");
+        fs::write(
+            &source,
+            secrets::encrypt(b"_authToken=abc\n", "pass").unwrap(),
+        )
+        .unwrap();
+        let target = home.join(".npmrc");
+        let tools = load_tools(
+            &root,
+            &format!(
+                "    - Name: npm\n      Links:\n        npmrc.enc: {}\n",
+                target.display()
+            ),
+        );
+        let mut record = RunRecord::default();
+        let state =
+            |record: &RunRecord| statuses(&tools, tools.get("npm").unwrap(), record)[0].state;
+
+        assert!(matches!(
+            link(
+                &tools,
+                &[],
+                Conflicts::Fail,
+                &mut Passphrase::fixed("wrong"),
+                &mut record
+            ),
+            Err(LinkError::Secret(SecretError::Decrypt { .. }))
+        ));
+        let changes = link(
+            &tools,
+            &[],
+            Conflicts::Fail,
+            &mut Passphrase::fixed("pass"),
+            &mut record,
+        )
+        .unwrap();
+        assert_eq!(changes[0].kind, LinkKind::Secret);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "_authToken=abc\n");
+        assert_eq!(
+            fs::metadata(&target).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert_eq!(state(&record), LinkState::Linked);
+
+        fs::write(
+            &source,
+            secrets::encrypt(b"_authToken=def\n", "pass").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(state(&record), LinkState::Outdated);
+        fs::write(&target, "edited").unwrap();
+        assert_eq!(state(&record), LinkState::Edited);
+    }
+}
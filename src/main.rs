@@ -1,11 +1,26 @@
+mod adopt;
 mod app;
+mod archive;
 mod config;
+mod config_editor;
+mod doctor;
+mod exclude;
+mod expand;
+mod graph;
+mod links;
 mod package;
+mod run_record;
+mod secrets;
+mod signing;
+mod status;
 mod tools;
+mod variables;
 
 use clap::{Parser, Subcommand, ValueEnum};
 use color_eyre::Result;
-use std::path::PathBuf;
+use color_eyre::eyre::bail;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(author, version, about = "dotfiles manager", propagate_version = true)]
@@ -26,13 +41,41 @@ enum Commands {
         #[arg(short, long)]
         dest: PathBuf,
 
-        /// Archive format (tar.gz or zip)
+        /// Archive format (tar.gz, tar.zst, tar.xz or zip)
         #[arg(long, value_enum, default_value_t = ExportFormat::TarGz)]
         format: ExportFormat,
+
+        /// Compression level (0-9 for tar.gz, tar.xz and zip; 1-22 for tar.zst)
+        #[arg(long, value_name = "LEVEL")]
+        level: Option<u32>,
+
+        /// Encrypt files matching SystemPreferences.Secrets instead of leaving them out
+        #[arg(long, default_value_t = false)]
+        encrypt_secrets: bool,
+
+        /// Gitignore-style pattern of files to leave out, relative to the root (repeatable)
+        #[arg(long, value_name = "PATTERN")]
+        exclude: Vec<String>,
+
+        /// Export only this tool (repeatable; defaults to every tool)
+        #[arg(long = "tool", value_name = "ID")]
+        tools: Vec<String>,
+
+        /// Export only tools with this tag (repeatable)
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+
+        /// Also export everything the selected tools depend on
+        #[arg(long, default_value_t = false)]
+        with_deps: bool,
+
+        /// Sign the manifest with this secret key (see `dotfiles keygen`)
+        #[arg(long, value_name = "PATH")]
+        sign_key: Option<PathBuf>,
     },
     /// Install configuration and tools from an archive
     Install {
-        /// Source archive path
+        /// Source archive path, or `-` to read the archive from stdin
         #[arg(short, long)]
         src: PathBuf,
 
@@ -43,13 +86,283 @@ enum Commands {
         /// Run without prompts (falls back to manifest root when dest missing)
         #[arg(long, default_value_t = false)]
         non_interactive: bool,
+
+        /// Public key (hex, or a .pub file) to trust besides SystemPreferences.TrustedKeys (repeatable)
+        #[arg(long = "trusted-key", value_name = "KEY")]
+        trusted_keys: Vec<String>,
+
+        /// Refuse archives that are not signed by a trusted key
+        #[arg(long, default_value_t = false)]
+        require_signature: bool,
+    },
+    /// Generate a key pair for signing exports
+    Keygen {
+        /// Secret key file to write; the public key goes to <PATH>.pub
+        path: PathBuf,
+
+        /// Replace existing key files
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
+    /// Check an archive's files against its manifest without installing it
+    Verify {
+        /// Archive path, or `-` to read the archive from stdin
+        archive: PathBuf,
+    },
+    /// Inspect an exported archive without installing it
+    Archive {
+        #[command(subcommand)]
+        command: ArchiveCommands,
+    },
+    /// Inspect or upgrade the configuration file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Add, remove, or edit tools in config.yaml
+    Tool {
+        /// Config file to edit (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long, global = true)]
+        config: Option<PathBuf>,
+
+        #[command(subcommand)]
+        command: ToolCommands,
+    },
+    /// Print the resolved Variables for this machine
+    Vars {
+        /// Config file to read (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Profile to apply instead of $DOTFILES_PROFILE or the hostname match
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    /// Report tools whose state on this machine no longer matches the config
+    Status {
+        /// Config file to read (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Skip the tools' Check scripts
+        #[arg(long, default_value_t = false)]
+        no_checks: bool,
+    },
+    /// Check that this machine meets what the config and scripts assume
+    Doctor {
+        /// Config file to read (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Print the results as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Symlink each tool's Links into place
+    Link {
+        /// Tool ids to link (defaults to every tool)
+        ids: Vec<String>,
+
+        /// Config file to read (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Move existing targets aside to `<name>.bak.<timestamp>` instead of failing
+        #[arg(long, default_value_t = false)]
+        backup: bool,
+    },
+    /// Move an existing file into a tool's directory and link it back
+    Adopt {
+        /// File or directory to adopt (with --undo, the adoption to reverse)
+        path: Option<PathBuf>,
+
+        /// Tool whose directory receives the file
+        #[arg(long, required_unless_present = "undo", requires = "path")]
+        tool: Option<String>,
+
+        /// Reverse the most recent adoption, or the one of PATH
+        #[arg(long, default_value_t = false, conflicts_with = "tool")]
+        undo: bool,
+
+        /// Config file to edit (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+    /// Remove the symlinks created by `link`
+    Unlink {
+        /// Tool ids to unlink (defaults to every tool)
+        ids: Vec<String>,
+
+        /// Config file to read (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+    /// Encrypt, decrypt, or list files matching SystemPreferences.Secrets
+    Secret {
+        /// Config file to read (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long, global = true)]
+        config: Option<PathBuf>,
+
+        #[command(subcommand)]
+        command: SecretCommands,
+    },
+    /// Query the tool dependency graph
+    Deps {
+        /// Config file to read (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long, global = true)]
+        config: Option<PathBuf>,
+
+        #[command(subcommand)]
+        command: DepsCommands,
+    },
+    /// Print the tool dependency graph
+    Graph {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = GraphFormat::Text)]
+        format: GraphFormat,
+
+        /// Tool id whose ancestors and descendants are highlighted
+        #[arg(long)]
+        highlight: Option<String>,
+
+        /// Config file to read (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ToolCommands {
+    /// Add a tool and scaffold its script
+    Add {
+        /// Display name
+        #[arg(long)]
+        name: String,
+        /// Explicit id (generated from the name when omitted)
+        #[arg(long)]
+        id: Option<String>,
+        /// Directory under the root (defaults to the lowercase name)
+        #[arg(long)]
+        root: Option<String>,
+        /// Script file name (defaults to <name>-settings.zsh)
+        #[arg(long)]
+        file: Option<String>,
+        /// Program used to run the script (defaults to zsh)
+        #[arg(long)]
+        interpreter: Option<String>,
+        /// Id of a tool that must run first (repeatable)
+        #[arg(long = "dep")]
+        dependencies: Vec<String>,
+    },
+    /// Remove a tool
+    Remove {
+        /// Tool id
+        id: String,
+        /// Also delete the tool's directory
+        #[arg(long, default_value_t = false)]
+        delete_dir: bool,
+    },
+    /// Change fields or dependencies of a tool
+    Edit {
+        /// Tool id
+        id: String,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        root: Option<String>,
+        #[arg(long)]
+        file: Option<String>,
+        #[arg(long)]
+        interpreter: Option<String>,
+        /// Dependency to add (repeatable)
+        #[arg(long = "add-dep")]
+        add_dependencies: Vec<String>,
+        /// Dependency to remove (repeatable)
+        #[arg(long = "remove-dep")]
+        remove_dependencies: Vec<String>,
     },
 }
 
+#[derive(Subcommand)]
+enum SecretCommands {
+    /// Replace plaintext secrets with `.enc` files (every matching file when none are given)
+    Encrypt {
+        /// Files to encrypt
+        paths: Vec<PathBuf>,
+    },
+    /// Replace `.enc` files with their plaintext
+    Decrypt {
+        /// Files to decrypt
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// List secrets under the dotfiles root and whether they are encrypted
+    List,
+}
+
+#[derive(Subcommand)]
+enum DepsCommands {
+    /// Show the shortest dependency chain from one tool to another
+    Why {
+        /// Tool that waits
+        id: String,
+        /// Tool it may be waiting for
+        dependency: String,
+    },
+    /// List every tool that depends on a tool, directly or transitively
+    Rdeps {
+        /// Tool id
+        id: String,
+    },
+    /// List the tools that re-run when a tool's script changes
+    Impact {
+        /// Tool id
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ArchiveCommands {
+    /// Print every file in the archive with its mode, size, and hash
+    List {
+        /// Archive path, or `-` to read the archive from stdin
+        archive: PathBuf,
+
+        /// Print the files as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Print when and where the archive was made, its tools with dependencies, and totals
+    Show {
+        /// Archive path, or `-` to read the archive from stdin
+        archive: PathBuf,
+
+        /// Print the summary, including every file, as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Upgrade config.yaml to the current schema version (the original is backed up)
+    Migrate {
+        /// Config file to migrate (defaults to ~/.dotfiles/config.yaml)
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+    /// Print the JSON Schema for config.yaml
+    Schema,
+}
+
 #[derive(Clone, Copy, Debug, ValueEnum)]
 enum ExportFormat {
     #[value(alias = "tgz", alias = "tar", alias = "tar.gz")]
     TarGz,
+    #[value(alias = "tzst", alias = "zst", alias = "tar.zst")]
+    TarZst,
+    #[value(alias = "txz", alias = "xz", alias = "tar.xz")]
+    TarXz,
     #[value(alias = "zip")]
     Zip,
 }
@@ -58,36 +371,107 @@ impl ExportFormat {
     fn as_archive_format(&self) -> package::ArchiveFormat {
         match self {
             ExportFormat::TarGz => package::ArchiveFormat::TarGz,
+            ExportFormat::TarZst => package::ArchiveFormat::TarZst,
+            ExportFormat::TarXz => package::ArchiveFormat::TarXz,
             ExportFormat::Zip => package::ArchiveFormat::Zip,
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+    Text,
+}
+
+impl GraphFormat {
+    fn as_graph_format(&self) -> graph::GraphFormat {
+        match self {
+            GraphFormat::Dot => graph::GraphFormat::Dot,
+            GraphFormat::Mermaid => graph::GraphFormat::Mermaid,
+            GraphFormat::Json => graph::GraphFormat::Json,
+            GraphFormat::Text => graph::GraphFormat::Text,
+        }
+    }
+}
+
 fn main() -> Result<()> {
     color_eyre::install()?;
 
     let cli = Cli::parse();
     match cli.command {
-        Some(Commands::Export { dest, format }) => {
+        Some(Commands::Export {
+            dest,
+            format,
+            level,
+            encrypt_secrets,
+            exclude,
+            tools,
+            tags,
+            with_deps,
+            sign_key,
+        }) => {
             let options = package::ExportOptions {
                 destination: dest,
                 format: format.as_archive_format(),
+                compression_level: level,
+                encrypt_secrets,
+                exclude,
+                tools,
+                tags,
+                with_dependencies: with_deps,
+                sign_key,
             };
-            let archive_path = package::export_archive(&options)?;
-            println!("Created archive at {}", archive_path.display());
+            let report = package::export_archive(&options)?;
+            for path in &report.withheld_secrets {
+                eprintln!(
+                    "Warning: left secret {path} out of the archive (use --encrypt-secrets to include it)"
+                );
+            }
+            if !report.skipped.is_empty() {
+                println!("Skipped {} excluded path(s):", report.skipped.len());
+                for path in &report.skipped {
+                    println!("  {path}");
+                }
+            }
+            println!("Created archive at {}", report.archive_path.display());
+            if let Some(key) = report.signed_by {
+                println!("Signed with key {key}");
+            }
             Ok(())
         }
         Some(Commands::Install {
             src,
             dest,
             non_interactive,
+            trusted_keys,
+            require_signature,
         }) => {
+            let trusted_keys = load_trusted_keys(&trusted_keys)?;
+            if require_signature && trusted_keys.is_empty() {
+                bail!(
+                    "--require-signature needs a trusted key: set SystemPreferences.TrustedKeys or pass --trusted-key"
+                );
+            }
             let options = package::InstallOptions {
                 archive_path: src,
                 destination_root: dest,
                 non_interactive,
+                trusted_keys,
+                require_signature,
             };
             let report = package::install_archive(&options)?;
+            match &report.signature {
+                package::SignatureStatus::Trusted(key) => {
+                    println!("Signature verified (trusted key {key})");
+                }
+                package::SignatureStatus::Untrusted(key) => {
+                    eprintln!("Warning: archive is signed by {key}, which is not a trusted key");
+                }
+                package::SignatureStatus::Unsigned => {}
+            }
             println!(
                 "Installed dotfiles into {}",
                 report.destination_root.display()
@@ -102,12 +486,471 @@ fn main() -> Result<()> {
             for path in report.installed_files {
                 println!("  {}", path.display());
             }
+            if !report.decrypted_secrets.is_empty() {
+                println!("Decrypted secrets:");
+                for path in report.decrypted_secrets {
+                    println!("  {}", path.display());
+                }
+            }
             Ok(())
         }
+        Some(Commands::Keygen { path, force }) => {
+            let public_path = signing::generate_key_pair(&path, force)?;
+            println!("Wrote secret key to {}", path.display());
+            println!("Wrote public key to {}", public_path.display());
+            println!(
+                "Sign exports with `dotfiles export --sign-key {}` and trust the public key on other machines",
+                path.display()
+            );
+            Ok(())
+        }
+        Some(Commands::Verify { archive }) => {
+            let report = package::verify_archive(&archive)?;
+            if report.problems.is_empty() {
+                println!(
+                    "Verified {} entries in {} ({})",
+                    report.checked,
+                    archive.display(),
+                    report.format.extension()
+                );
+                if let Some(key) = report.signed_by {
+                    println!("Manifest signed by {key}");
+                }
+                return Ok(());
+            }
+            eprintln!(
+                "Found {} problem(s) in {}:",
+                report.problems.len(),
+                archive.display()
+            );
+            for problem in &report.problems {
+                eprintln!("  {problem}");
+            }
+            std::process::exit(1);
+        }
+        Some(Commands::Archive { command }) => run_archive_command(command),
+        Some(Commands::Config { command }) => run_config_command(command),
+        Some(Commands::Tool { config, command }) => {
+            let config_path =
+                config.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            run_tool_command(&config_path, command)
+        }
+        Some(Commands::Vars { config, profile }) => {
+            let config_path =
+                config.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            print_variables(&config_path, profile.as_deref())
+        }
+        Some(Commands::Status { config, no_checks }) => {
+            let config_path =
+                config.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            let checks = if no_checks {
+                status::Checks::Skip
+            } else {
+                status::Checks::Run
+            };
+            if !print_status(&config_path, checks)? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Commands::Doctor { config, json }) => {
+            let config_path =
+                config.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            let report = doctor::DoctorReport::run(&config_path);
+            if json {
+                println!("{}", report.to_json());
+            } else {
+                for line in report.lines(std::io::stdout().is_terminal()) {
+                    println!("{line}");
+                }
+            }
+            if report.has_failures() {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Commands::Link {
+            ids,
+            config,
+            backup,
+        }) => {
+            let config_path =
+                config.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            let conflicts = if backup {
+                links::Conflicts::Backup
+            } else {
+                links::Conflicts::Fail
+            };
+            let mut passphrase = secrets::Passphrase::from_env_or_prompt(true);
+            update_links(&config_path, |tools, record| {
+                links::link(tools, &ids, conflicts, &mut passphrase, record)
+            })
+        }
+        Some(Commands::Adopt {
+            path,
+            tool,
+            undo,
+            config,
+        }) => {
+            let config_path =
+                config.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            let record_path = run_record::default_path();
+            let mut record = run_record::RunRecord::load(&record_path)?;
+            if undo {
+                let adoption = adopt::undo(&config_path, path.as_deref(), &mut record)?;
+                record.save(&record_path)?;
+                println!(
+                    "Moved {} back to {} and removed it from '{}'",
+                    adoption.stored.display(),
+                    adoption.original.display(),
+                    adoption.tool_id
+                );
+            } else if let (Some(path), Some(tool)) = (path, tool) {
+                let adoption = adopt::adopt(&config_path, &path, &tool, &mut record)?;
+                record.save(&record_path)?;
+                println!(
+                    "Moved {} to {} and linked it back (undo with `dotfiles adopt --undo`)",
+                    adoption.original.display(),
+                    adoption.stored.display()
+                );
+            }
+            Ok(())
+        }
+        Some(Commands::Unlink { ids, config }) => {
+            let config_path =
+                config.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            update_links(&config_path, |tools, record| {
+                links::unlink(tools, &ids, record)
+            })
+        }
+        Some(Commands::Secret { config, command }) => {
+            let config_path =
+                config.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            run_secret_command(&config_path, command)
+        }
+        Some(Commands::Deps { config, command }) => {
+            let config_path =
+                config.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            run_deps_command(&config_path, command)
+        }
+        Some(Commands::Graph {
+            format,
+            highlight,
+            config,
+        }) => {
+            let config_path =
+                config.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            print_graph(&config_path, format, highlight.as_deref())
+        }
         None => run_tui(),
     }
 }
 
+/// Keys from `SystemPreferences.TrustedKeys` of this machine's config plus `extra`.
+fn load_trusted_keys(extra: &[String]) -> Result<Vec<ed25519_dalek::VerifyingKey>> {
+    let mut keys = Vec::new();
+    if config::expand_home_path(config::DEFAULT_CONFIG_PATH).exists() {
+        match config::Config::load() {
+            Ok(config) => keys.extend(config.trusted_keys().iter().cloned()),
+            Err(error) => eprintln!(
+                "Warning: ignoring SystemPreferences.TrustedKeys, {} does not load: {error}",
+                config::DEFAULT_CONFIG_PATH
+            ),
+        }
+    }
+    keys.extend(extra.iter().cloned());
+    keys.iter()
+        .map(|key| signing::load_public_key(key).map_err(Into::into))
+        .collect()
+}
+
+fn run_archive_command(command: ArchiveCommands) -> Result<()> {
+    match command {
+        ArchiveCommands::List { archive, json } => {
+            let summary = archive::ArchiveSummary::read(&archive)?;
+            if json {
+                println!("{}", summary.files_to_json());
+            } else {
+                for line in summary.list_lines() {
+                    println!("{line}");
+                }
+            }
+        }
+        ArchiveCommands::Show { archive, json } => {
+            let summary = archive::ArchiveSummary::read(&archive)?;
+            if json {
+                println!("{}", summary.to_json());
+            } else {
+                for line in summary.show_lines() {
+                    println!("{line}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_config_command(command: ConfigCommands) -> Result<()> {
+    match command {
+        ConfigCommands::Migrate { path } => {
+            let path =
+                path.unwrap_or_else(|| config::expand_home_path(config::DEFAULT_CONFIG_PATH));
+            let report = config::migrate_file(&path)?;
+            match report.backup {
+                Some(backup) => {
+                    println!(
+                        "Migrated {} from version {} to {}",
+                        report.path.display(),
+                        report.from_version,
+                        report.to_version
+                    );
+                    println!("Backed up original to {}", backup.display());
+                }
+                None => println!(
+                    "{} is already at version {}",
+                    report.path.display(),
+                    report.to_version
+                ),
+            }
+            Ok(())
+        }
+        ConfigCommands::Schema => {
+            println!("{}", config::json_schema()?);
+            Ok(())
+        }
+    }
+}
+
+fn print_variables(config_path: &Path, profile: Option<&str>) -> Result<()> {
+    let config =
+        config::Config::load_from_file_with_profile(&config_path.to_string_lossy(), profile)?;
+    let variables = config.variables();
+    println!("Profile: {}", variables.profile().unwrap_or("(none)"));
+    for (name, variable) in variables.iter() {
+        match variable.profile.as_deref() {
+            Some(profile) => println!("{name} = {}  ({profile})", variable.value),
+            None => println!("{name} = {}", variable.value),
+        }
+    }
+    Ok(())
+}
+
+fn load_tools(config_path: &Path) -> Result<tools::Tools> {
+    let config = config::Config::load_from_file(&config_path.to_string_lossy())?;
+    let (tools, _) = tools::Tools::from_config(&config, true)?;
+    Ok(tools)
+}
+
+/// Prints the drift report and returns whether everything matched.
+fn print_status(config_path: &Path, checks: status::Checks) -> Result<bool> {
+    let tools = load_tools(config_path)?;
+    let record_path = run_record::default_path();
+    let record = run_record::RunRecord::load(&record_path)?;
+    let report = status::StatusReport::collect(&tools, &record, checks);
+    println!("Run record: {}", record_path.display());
+    for line in report.lines() {
+        println!("{line}");
+    }
+    Ok(report.is_clean())
+}
+
+fn print_graph(config_path: &Path, format: GraphFormat, highlight: Option<&str>) -> Result<()> {
+    let tools = load_tools(config_path)?;
+    print!(
+        "{}",
+        graph::render(&tools, format.as_graph_format(), highlight)?
+    );
+    Ok(())
+}
+
+/// Runs `link` or `unlink` and saves the rendered templates to the run record, even when
+/// the command stopped partway.
+fn update_links(
+    config_path: &Path,
+    update: impl FnOnce(
+        &tools::Tools,
+        &mut run_record::RunRecord,
+    ) -> Result<Vec<links::LinkChange>, links::LinkError>,
+) -> Result<()> {
+    let tools = load_tools(config_path)?;
+    let record_path = run_record::default_path();
+    let mut record = run_record::RunRecord::load(&record_path)?;
+    let result = update(&tools, &mut record);
+    record.save(&record_path)?;
+    let changes = result?;
+    if changes.is_empty() {
+        println!("No tools have Links configured.");
+    }
+    for change in changes {
+        println!("{change}");
+    }
+    Ok(())
+}
+
+fn run_secret_command(config_path: &Path, command: SecretCommands) -> Result<()> {
+    let tools = load_tools(config_path)?;
+    let root = config::expand_home_path(tools.root());
+    let patterns = secrets::SecretPatterns::new(tools.secrets())?;
+    let mut passphrase = secrets::Passphrase::from_env_or_prompt(true);
+    match command {
+        SecretCommands::Encrypt { paths } => {
+            let paths = if paths.is_empty() {
+                secrets::scan(&root, &patterns)
+                    .into_iter()
+                    .filter(|secret| !secret.encrypted)
+                    .map(|secret| root.join(secret.path))
+                    .collect()
+            } else {
+                paths
+            };
+            if paths.is_empty() {
+                println!("No plaintext secrets to encrypt.");
+            }
+            for path in paths {
+                let path = std::path::absolute(&path)?;
+                let encrypted = secrets::encrypt_in_place(&path, passphrase.get(true)?)?;
+                println!("encrypted  {}", encrypted.display());
+                relink_secret(config_path, &tools, &path, &encrypted)?;
+            }
+        }
+        SecretCommands::Decrypt { paths } => {
+            for path in paths {
+                let path = std::path::absolute(&path)?;
+                let decrypted = secrets::decrypt_in_place(&path, passphrase.get(false)?)?;
+                println!("decrypted  {}", decrypted.display());
+                relink_secret(config_path, &tools, &path, &decrypted)?;
+            }
+        }
+        SecretCommands::List => {
+            let files = secrets::scan(&root, &patterns);
+            if files.is_empty() {
+                println!("No secrets under {}", root.display());
+            }
+            for secret in files {
+                let state = if secret.encrypted {
+                    "encrypted"
+                } else {
+                    "plaintext"
+                };
+                println!("{state:<10} {}", secret.path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Keeps `Links` entries pointing at a secret that was just encrypted or decrypted.
+fn relink_secret(config_path: &Path, tools: &tools::Tools, from: &Path, to: &Path) -> Result<()> {
+    for id in config_editor::rename_link_source(config_path, tools, from, to)? {
+        println!("relinked   {} in '{id}'", to.display());
+    }
+    Ok(())
+}
+
+fn run_deps_command(config_path: &Path, command: DepsCommands) -> Result<()> {
+    let tools = load_tools(config_path)?;
+    let lookup = |id: &str| {
+        tools
+            .get(id)
+            .ok_or_else(|| tools::ToolError::UnknownTool(id.to_string()))
+    };
+    match command {
+        DepsCommands::Why { id, dependency } => {
+            let tool = lookup(&id)?;
+            let target = lookup(&dependency)?;
+            match tools.dependency_path(&id, &dependency) {
+                Some(path) => {
+                    let chain = path
+                        .iter()
+                        .filter_map(|id| tools.get(id))
+                        .map(tools::ToolItem::display_name)
+                        .collect::<Vec<_>>();
+                    println!("{}", chain.join(" -> "));
+                }
+                None => println!(
+                    "{} does not depend on {}",
+                    tool.display_name(),
+                    target.display_name()
+                ),
+            }
+        }
+        DepsCommands::Rdeps { id } => {
+            let tool = lookup(&id)?;
+            let dependents = tools.in_run_order(&tools.descendants(&id));
+            if dependents.is_empty() {
+                println!("No tools depend on {}", tool.display_name());
+            }
+            for dependent in dependents {
+                println!("{}", dependent.display_name());
+            }
+        }
+        DepsCommands::Impact { id } => {
+            lookup(&id)?;
+            let affected = tools.impact(&id);
+            println!("Changing '{id}' re-runs {} tool(s):", affected.len());
+            for tool in affected {
+                let stage = tools.execution_stage_index(&tool.id).unwrap_or_default() + 1;
+                println!("  Stage {stage}: {}", tool.display_name());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_tool_command(config_path: &Path, command: ToolCommands) -> Result<()> {
+    match command {
+        ToolCommands::Add {
+            name,
+            id,
+            root,
+            file,
+            interpreter,
+            dependencies,
+        } => {
+            let spec = config_editor::ToolSpec {
+                name,
+                id,
+                root,
+                file,
+                interpreter,
+                dependencies,
+            };
+            let id = config_editor::add_tool(config_path, &spec)?;
+            println!("Added tool '{id}' to {}", config_path.display());
+        }
+        ToolCommands::Remove { id, delete_dir } => {
+            let removed = config_editor::remove_tool(config_path, &id, delete_dir)?;
+            println!("Removed tool '{id}' from {}", removed.source.display());
+            if let Some(dir) = removed.deleted_dir {
+                println!("Deleted {}", dir.display());
+            }
+        }
+        ToolCommands::Edit {
+            id,
+            name,
+            root,
+            file,
+            interpreter,
+            add_dependencies,
+            remove_dependencies,
+        } => {
+            let changes = config_editor::ToolChanges {
+                name,
+                root,
+                file,
+                interpreter,
+                dependencies: None,
+                add_dependencies,
+                remove_dependencies,
+                ..Default::default()
+            };
+            let source = config_editor::edit_tool(config_path, &id, &changes)?;
+            println!("Updated tool '{id}' in {}", source.display());
+        }
+    }
+    Ok(())
+}
+
 fn run_tui() -> Result<()> {
     let terminal = ratatui::init();
     let result = app::App::new().run(terminal);
@@ -125,6 +968,14 @@ mod tests {
             ExportFormat::TarGz.as_archive_format(),
             package::ArchiveFormat::TarGz
         ));
+        assert!(matches!(
+            ExportFormat::TarZst.as_archive_format(),
+            package::ArchiveFormat::TarZst
+        ));
+        assert!(matches!(
+            ExportFormat::TarXz.as_archive_format(),
+            package::ArchiveFormat::TarXz
+        ));
         assert!(matches!(
             ExportFormat::Zip.as_archive_format(),
             package::ArchiveFormat::Zip
@@ -1,27 +1,36 @@
 use chrono::Utc;
 use dialoguer::Input;
+use ed25519_dalek::{SigningKey, VerifyingKey};
 use flate2::Compression;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
+use liblzma::read::XzDecoder;
+use liblzma::write::XzEncoder;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs::{self, File};
 use std::io::{self, Cursor, Read, Write};
+use std::ops::RangeInclusive;
 #[cfg(unix)]
 use std::os::unix::fs::symlink;
 use std::path::{Component, Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tar::{Archive as TarArchive, Builder as TarBuilder, EntryType, Header as TarHeader};
-use tempfile::TempDir;
+use tempfile::{NamedTempFile, TempDir};
 use walkdir::WalkDir;
 use zip::CompressionMethod;
 use zip::ZipWriter;
 use zip::read::ZipArchive;
+use zip::result::ZipError;
 use zip::write::FileOptions as ZipFileOptions;
 
 use crate::config;
-use crate::tools::Tools;
+use crate::config_editor;
+use crate::exclude::ExcludeRules;
+use crate::secrets::{self, Passphrase, SecretError, SecretPatterns};
+use crate::signing::{self, ManifestSignature, SIGNATURE_FILE_NAME, SigningError};
+use crate::tools::{ToolError, Tools};
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
@@ -31,6 +40,12 @@ const MANIFEST_FILE_NAME: &str = "manifest.json";
 
 const DEFAULT_CONFIG_NAME: &str = "config.yaml";
 
+/// Bytes read to recognise an archive; a tar header's `ustar` magic ends at offset 262.
+const MAGIC_LEN: usize = 262;
+
+/// `InstallOptions::archive_path` that reads the archive from stdin.
+pub const STDIN_PATH: &str = "-";
+
 #[derive(Debug, thiserror::Error)]
 pub enum PackageError {
     #[error("Failed to load tools: {0}")]
@@ -45,6 +60,13 @@ pub enum PackageError {
     Zip(#[from] zip::result::ZipError),
     #[error("Unsupported archive format")]
     UnsupportedArchive,
+    #[error("Compression level {level} is not supported for {format} (use {min}-{max})")]
+    InvalidCompressionLevel {
+        level: u32,
+        format: &'static str,
+        min: u32,
+        max: u32,
+    },
     #[error("Missing manifest in archive")]
     MissingManifest,
     #[error("Manifest version {found} is not supported (expected {expected})")]
@@ -55,12 +77,43 @@ pub enum PackageError {
     HashMismatch { path: String },
     #[error("Required file '{path}' not found in archive")]
     MissingFile { path: String },
+    #[error("Size mismatch for {path}: manifest says {expected} bytes, archive has {actual}")]
+    SizeMismatch {
+        path: String,
+        expected: u64,
+        actual: u64,
+    },
+    #[error("Mode mismatch for {path}: manifest says {expected:04o}, archive has {actual:04o}")]
+    ModeMismatch {
+        path: String,
+        expected: u32,
+        actual: u32,
+    },
+    #[error("'{path}' is in the archive but not in the manifest")]
+    UnlistedFile { path: String },
     #[error("Interactive prompt required but disabled (provide --dest)")]
     PromptUnavailable,
     #[error("Archive contains duplicate entry '{path}'")]
     DuplicatePath { path: String },
     #[error("Prompt error: {0}")]
     Prompt(#[from] dialoguer::Error),
+    #[error(transparent)]
+    Secret(#[from] SecretError),
+    #[error(transparent)]
+    Signing(#[from] SigningError),
+    #[error("Archive is not signed (required by --require-signature)")]
+    UnsignedArchive,
+    #[error("Archive is signed by {key}, which is not a trusted key")]
+    UntrustedSignature { key: String },
+    #[error(
+        "Tool '{tool_id}' depends on '{dependency_id}', which is not selected (add it or pass --with-deps)"
+    )]
+    UnselectedDependency {
+        tool_id: String,
+        dependency_id: String,
+    },
+    #[error("Invalid exclude pattern: {0}")]
+    Exclude(#[from] ignore::Error),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -69,6 +122,9 @@ pub struct Manifest {
     pub generated_at: String,
     pub original_root: String,
     pub config: ManifestFile,
+    /// Config files pulled in through `Include` that live outside tool directories.
+    #[serde(default)]
+    pub includes: Vec<ManifestFile>,
     pub tools: Vec<ManifestToolEntry>,
 }
 
@@ -78,6 +134,10 @@ pub struct ManifestFile {
     pub sha256: String,
     pub mode: u32,
     pub size: u64,
+    /// A secret encrypted for the archive; `path` ends in `.enc` and install decrypts it to
+    /// the path without that suffix.
+    #[serde(default)]
+    pub encrypted: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,6 +147,10 @@ pub struct ManifestToolEntry {
     pub root: String,
     pub file: String,
     pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub optional_dependencies: Vec<String>,
+    #[serde(default)]
+    pub conflicts: Vec<String>,
     pub artifact: ManifestFile,
     #[serde(default)]
     pub related_files: Vec<ManifestFile>,
@@ -96,6 +160,31 @@ pub struct ManifestToolEntry {
 pub struct ExportOptions {
     pub destination: PathBuf,
     pub format: ArchiveFormat,
+    /// Compression level for `format`; `None` uses the format's default.
+    pub compression_level: Option<u32>,
+    /// Encrypt plaintext files matching `SystemPreferences.Secrets` instead of leaving them out.
+    pub encrypt_secrets: bool,
+    /// Gitignore-style patterns, relative to the root, added to `SystemPreferences.Exclude`.
+    pub exclude: Vec<String>,
+    /// Tool ids to export; together with `tags` empty, every tool is exported.
+    pub tools: Vec<String>,
+    /// Tags whose tools are exported.
+    pub tags: Vec<String>,
+    /// Add everything the selected tools depend on, directly or transitively.
+    pub with_dependencies: bool,
+    /// Secret key file (from `dotfiles keygen`) used to sign the manifest.
+    pub sign_key: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct ExportReport {
+    pub archive_path: PathBuf,
+    /// Files and directories (with a trailing `/`) left out by exclude rules.
+    pub skipped: Vec<String>,
+    /// Plaintext secrets left out because `encrypt_secrets` was off.
+    pub withheld_secrets: Vec<String>,
+    /// Hex public key of the key that signed the manifest.
+    pub signed_by: Option<String>,
 }
 
 #[derive(Debug)]
@@ -103,6 +192,10 @@ pub struct InstallOptions {
     pub archive_path: PathBuf,
     pub destination_root: Option<PathBuf>,
     pub non_interactive: bool,
+    /// Keys whose signatures are trusted.
+    pub trusted_keys: Vec<VerifyingKey>,
+    /// Refuse archives that are not signed by one of `trusted_keys`.
+    pub require_signature: bool,
 }
 
 #[derive(Debug)]
@@ -110,24 +203,119 @@ pub struct InstallReport {
     pub destination_root: PathBuf,
     pub installed_files: Vec<PathBuf>,
     pub backups: Vec<PathBuf>,
+    /// Secrets decrypted from the archive.
+    pub decrypted_secrets: Vec<PathBuf>,
+    pub signature: SignatureStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Unsigned,
+    /// Signed by one of `InstallOptions::trusted_keys` (hex public key).
+    Trusted(String),
+    /// Validly signed, but by a key that is not trusted.
+    Untrusted(String),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Debug)]
+pub struct VerifyReport {
+    pub format: ArchiveFormat,
+    /// Manifest entries checked, directories included.
+    pub checked: usize,
+    /// Hex public key of a valid manifest signature; whether it is trusted is not checked.
+    pub signed_by: Option<String>,
+    pub problems: Vec<PackageError>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ArchiveFormat {
     TarGz,
+    TarZst,
+    TarXz,
     Zip,
+    /// Uncompressed tarball, accepted by `install` but not offered by `export`.
+    Tar,
 }
 
 impl ArchiveFormat {
+    const ALL: [ArchiveFormat; 5] = [
+        ArchiveFormat::TarGz,
+        ArchiveFormat::TarZst,
+        ArchiveFormat::TarXz,
+        ArchiveFormat::Zip,
+        ArchiveFormat::Tar,
+    ];
+
     pub fn extension(&self) -> &'static str {
         match self {
             ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::TarZst => "tar.zst",
+            ArchiveFormat::TarXz => "tar.xz",
             ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+        }
+    }
+
+    /// File name suffixes recognised by `detect_archive_format`, besides `extension`.
+    fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            ArchiveFormat::TarGz => &["tgz", "gz"],
+            ArchiveFormat::TarZst => &["tzst", "zst"],
+            ArchiveFormat::TarXz => &["txz", "xz"],
+            ArchiveFormat::Zip | ArchiveFormat::Tar => &[],
+        }
+    }
+
+    /// Recognises an archive from its first `MAGIC_LEN` bytes.
+    fn from_magic(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Some(ArchiveFormat::TarGz)
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(ArchiveFormat::TarZst)
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(ArchiveFormat::TarXz)
+        } else if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(ArchiveFormat::Zip)
+        } else if header.get(257..262) == Some(b"ustar") {
+            Some(ArchiveFormat::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// Accepted compression levels and the one used when none is given.
+    fn compression_levels(&self) -> (RangeInclusive<u32>, u32) {
+        match self {
+            ArchiveFormat::TarGz | ArchiveFormat::TarXz | ArchiveFormat::Zip => (0..=9, 6),
+            ArchiveFormat::TarZst => (1..=22, zstd::DEFAULT_COMPRESSION_LEVEL as u32),
+            ArchiveFormat::Tar => (0..=0, 0),
+        }
+    }
+
+    fn compression_level(&self, requested: Option<u32>) -> Result<u32, PackageError> {
+        let (levels, default) = self.compression_levels();
+        match requested {
+            None => Ok(default),
+            Some(level) if levels.contains(&level) => Ok(level),
+            Some(level) => Err(PackageError::InvalidCompressionLevel {
+                level,
+                format: self.extension(),
+                min: *levels.start(),
+                max: *levels.end(),
+            }),
         }
     }
 }
 
-pub fn export_archive(options: &ExportOptions) -> Result<PathBuf, PackageError> {
+pub fn export_archive(options: &ExportOptions) -> Result<ExportReport, PackageError> {
+    let compression_level = options
+        .format
+        .compression_level(options.compression_level)?;
+    let signing_key = options
+        .sign_key
+        .as_deref()
+        .map(signing::load_signing_key)
+        .transpose()?;
     ensure_destination_parent(&options.destination)?;
 
     let (tools, warnings) = Tools::new_relaxed()?;
@@ -135,11 +323,6 @@ pub fn export_archive(options: &ExportOptions) -> Result<PathBuf, PackageError>
         eprintln!("Warning: {warning}");
     }
 
-    let expanded_root = config::expand_home_path(tools.root().trim());
-    if !expanded_root.exists() {
-        fs::create_dir_all(&expanded_root)?;
-    }
-
     let config_path = config::expand_home_path(config::DEFAULT_CONFIG_PATH);
     if !config_path.exists() {
         return Err(PackageError::MissingFile {
@@ -147,18 +330,74 @@ pub fn export_archive(options: &ExportOptions) -> Result<PathBuf, PackageError>
         });
     }
 
-    let config_metadata = fs::metadata(&config_path)?;
-    let config_relative = relative_path(&config_path, &expanded_root)
+    let config = config::Config::load_from_file(&config_path.to_string_lossy())
+        .map_err(|error| ToolError::ConfigLoad(error.to_string()))?;
+    export_config(
+        &tools,
+        &config,
+        &config_path,
+        compression_level,
+        signing_key,
+        options,
+    )
+}
+
+/// Writes the archive for `config`, loaded from `config_path`, and its `tools`.
+fn export_config(
+    tools: &Tools,
+    config: &config::Config,
+    config_path: &Path,
+    compression_level: u32,
+    signing_key: Option<SigningKey>,
+    options: &ExportOptions,
+) -> Result<ExportReport, PackageError> {
+    let expanded_root = config::expand_home_path(tools.root().trim());
+    if !expanded_root.exists() {
+        fs::create_dir_all(&expanded_root)?;
+    }
+
+    let selected = select_tools(tools, options)?;
+    let mut sources = BTreeMap::new();
+    for (path, contents) in match &selected {
+        Some(selected) => config_editor::pruned_sources(config, selected)
+            .map_err(|error| ToolError::ConfigLoad(error.to_string()))?,
+        None => Vec::new(),
+    } {
+        sources.insert(path, contents);
+    }
+
+    let patterns = [tools.exclude(), options.exclude.as_slice()].concat();
+    let mut filter = ExportFilter {
+        rules: ExcludeRules::new(&expanded_root, &patterns)?,
+        secrets: SecretPacker::new(tools.secrets(), options.encrypt_secrets)?,
+        staging: Staging::new()?,
+        skipped: Vec::new(),
+    };
+
+    let config_metadata = fs::metadata(config_path)?;
+    let config_relative = relative_path(config_path, &expanded_root)
         .unwrap_or_else(|_| PathBuf::from(DEFAULT_CONFIG_NAME));
-    let config_manifest = ManifestFile {
-        path: path_to_string(&config_relative),
-        sha256: compute_sha256_path(&config_path)?,
-        mode: file_mode(&config_metadata),
-        size: config_metadata.len(),
+    let config_manifest = match sources.get(config_path) {
+        Some(Some(pruned)) => filter.staging.stage(
+            &path_to_string(&config_relative),
+            pruned.as_bytes(),
+            file_mode(&config_metadata),
+            false,
+        )?,
+        _ => ManifestFile {
+            path: path_to_string(&config_relative),
+            sha256: compute_sha256_path(config_path)?,
+            mode: file_mode(&config_metadata),
+            size: config_metadata.len(),
+            encrypted: false,
+        },
     };
 
     let mut manifest_tools = Vec::new();
-    for tool in tools.iter() {
+    for tool in tools
+        .iter()
+        .filter(|tool| selected.as_ref().is_none_or(|ids| ids.contains(&tool.id)))
+    {
         let script_path = tools.tool_path(tool);
         if !script_path.exists() {
             eprintln!(
@@ -177,6 +416,8 @@ pub fn export_archive(options: &ExportOptions) -> Result<PathBuf, PackageError>
             root: tool.root.clone(),
             file: tool.file.clone(),
             dependencies: tool.dependencies.clone(),
+            optional_dependencies: tool.optional_dependencies.clone(),
+            conflicts: tool.conflicts.clone(),
             artifact: ManifestFile {
                 path: path_to_string(&relative),
                 sha256: if script_metadata.is_dir() {
@@ -190,51 +431,107 @@ pub fn export_archive(options: &ExportOptions) -> Result<PathBuf, PackageError>
                 } else {
                     script_metadata.len()
                 },
+                encrypted: false,
             },
-            related_files: collect_related_files(&expanded_root, &relative)?,
+            related_files: collect_related_files(&expanded_root, &relative, &mut filter)?,
         });
     }
 
+    let includes = collect_included_configs(
+        config,
+        &expanded_root,
+        &manifest_tools,
+        &sources,
+        &mut filter.staging,
+    )?;
+
     let manifest = Manifest {
         version: MANIFEST_VERSION,
         generated_at: Utc::now().to_rfc3339(),
         original_root: tools.root().to_string(),
         config: config_manifest,
+        includes,
         tools: manifest_tools,
     };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    let signature = signing_key.map(|key| ManifestSignature::sign(&key, &manifest_json));
+    let mut metadata = vec![(MANIFEST_FILE_NAME, manifest_json)];
+    if let Some(signature) = &signature {
+        metadata.push((SIGNATURE_FILE_NAME, serde_json::to_vec_pretty(signature)?));
+    }
 
     let package_path = finalize_destination(&options.destination, options.format);
     ensure_destination_parent(&package_path)?;
 
-    match options.format {
-        ArchiveFormat::TarGz => create_tar_gz(&package_path, &manifest, &expanded_root)?,
-        ArchiveFormat::Zip => create_zip(&package_path, &manifest, &expanded_root)?,
-    }
+    let archive_sources = ArchiveSources {
+        root: &expanded_root,
+        staging: &filter.staging,
+    };
+    write_archive(
+        &package_path,
+        options.format,
+        compression_level,
+        &metadata,
+        &manifest,
+        &archive_sources,
+    )?;
 
-    Ok(package_path)
+    filter.skipped.sort();
+    Ok(ExportReport {
+        archive_path: package_path,
+        skipped: filter.skipped,
+        withheld_secrets: filter.secrets.excluded,
+        signed_by: signature.map(|signature| signature.public_key),
+    })
 }
 
-pub fn install_archive(options: &InstallOptions) -> Result<InstallReport, PackageError> {
-    let format = detect_archive_format(&options.archive_path)?;
-    let temp_dir = extract_archive(&options.archive_path, format)?;
-    let manifest_path = temp_dir.path().join(MANIFEST_FILE_NAME);
-    if !manifest_path.exists() {
-        return Err(PackageError::MissingManifest);
+/// The tools chosen with `--tool` and `--tag`, or `None` to export every tool. Hard dependencies
+/// left out of the selection are an error because the exported config would not load.
+fn select_tools(
+    tools: &Tools,
+    options: &ExportOptions,
+) -> Result<Option<BTreeSet<String>>, PackageError> {
+    if options.tools.is_empty() && options.tags.is_empty() {
+        return Ok(None);
     }
+    let selected = tools.select(&options.tools, &options.tags, options.with_dependencies)?;
+    for tool in tools.in_run_order(&selected) {
+        if let Some(dependency) = tool
+            .dependencies
+            .iter()
+            .find(|dependency| !selected.contains(*dependency))
+        {
+            return Err(PackageError::UnselectedDependency {
+                tool_id: tool.id.clone(),
+                dependency_id: dependency.clone(),
+            });
+        }
+    }
+    Ok(Some(selected))
+}
 
-    let manifest_file = File::open(&manifest_path)?;
-    let manifest: Manifest = serde_json::from_reader(manifest_file)?;
-
-    if manifest.version != MANIFEST_VERSION {
-        return Err(PackageError::ManifestVersionMismatch {
-            found: manifest.version,
-            expected: MANIFEST_VERSION,
-        });
+pub fn install_archive(options: &InstallOptions) -> Result<InstallReport, PackageError> {
+    let source = ArchiveSource::open(&options.archive_path)?;
+    // Stdin carries the archive, so there is nothing left to answer prompts with.
+    let interactive = !options.non_interactive && !source.is_stdin();
+    let format = detect_archive_format(source.path())?;
+    let temp_dir = extract_archive(source.path(), format)?;
+    let manifest = read_extracted_manifest(temp_dir.path())?;
+    let signature = check_signature(
+        temp_dir.path(),
+        &options.trusted_keys,
+        options.require_signature,
+    )?;
+    if let Some(warning) = missing_signature_warning(&signature, &options.trusted_keys) {
+        eprintln!("Warning: {warning}");
     }
 
     validate_manifest_paths(&manifest)?;
 
     verify_manifest_entry(temp_dir.path(), &manifest.config)?;
+    for include in &manifest.includes {
+        verify_manifest_entry(temp_dir.path(), include)?;
+    }
     for entry in &manifest.tools {
         verify_manifest_entry(temp_dir.path(), &entry.artifact)?;
         for related in &entry.related_files {
@@ -242,13 +539,28 @@ pub fn install_archive(options: &InstallOptions) -> Result<InstallReport, Packag
         }
     }
 
-    let destination_root = resolve_destination_root(&manifest, options)?;
+    // Decrypt secrets next to their `.enc` files first so a wrong passphrase installs nothing.
+    let mut passphrase = Passphrase::from_env_or_prompt(interactive);
+    for related in manifest
+        .tools
+        .iter()
+        .flat_map(|entry| &entry.related_files)
+        .filter(|related| related.encrypted)
+    {
+        let sealed = temp_dir.path().join(&related.path);
+        let plaintext = secrets::decrypt_file(&sealed, passphrase.get(false)?)?;
+        secrets::write_private(&secrets::plaintext_path(&sealed), &plaintext)?;
+    }
+
+    let destination_root = resolve_destination_root(&manifest, options, interactive)?;
     fs::create_dir_all(&destination_root)?;
 
     let mut report = InstallReport {
         destination_root: destination_root.clone(),
         installed_files: Vec::new(),
         backups: Vec::new(),
+        decrypted_secrets: Vec::new(),
+        signature,
     };
 
     let config_source = temp_dir.path().join(&manifest.config.path);
@@ -262,6 +574,12 @@ pub fn install_archive(options: &InstallOptions) -> Result<InstallReport, Packag
 
     rewrite_config_root(&config_target, &destination_root)?;
 
+    for include in &manifest.includes {
+        let include_source = temp_dir.path().join(&include.path);
+        let include_target = destination_root.join(&include.path);
+        install_file(&include_source, &include_target, include.mode, &mut report)?;
+    }
+
     for entry in &manifest.tools {
         let script_source = temp_dir.path().join(&entry.artifact.path);
         let script_target = destination_root.join(&entry.artifact.path);
@@ -273,8 +591,13 @@ pub fn install_archive(options: &InstallOptions) -> Result<InstallReport, Packag
         )?;
 
         for related in &entry.related_files {
-            let related_source = temp_dir.path().join(&related.path);
-            let related_target = destination_root.join(&related.path);
+            let mut related_source = temp_dir.path().join(&related.path);
+            let mut related_target = destination_root.join(&related.path);
+            if related.encrypted {
+                related_source = secrets::plaintext_path(&related_source);
+                related_target = secrets::plaintext_path(&related_target);
+                report.decrypted_secrets.push(related_target.clone());
+            }
             install_file(&related_source, &related_target, related.mode, &mut report)?;
         }
     }
@@ -310,8 +633,25 @@ fn finalize_destination(candidate: &Path, format: ArchiveFormat) -> PathBuf {
         return final_path;
     }
 
-    match final_path.extension().and_then(|ext| ext.to_str()) {
-        Some(ext) if ext.eq_ignore_ascii_case(format.extension()) => final_path,
+    // Match whole suffixes so "backup.tar.gz" is kept as is, or becomes "backup.tar.zst",
+    // rather than "backup.tar.tar.gz".
+    let file_name = final_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_string();
+    if let Some(known) = ArchiveFormat::ALL
+        .into_iter()
+        .find(|known| has_suffix(&file_name, known.extension()))
+    {
+        if known != format {
+            let stem = &file_name[..file_name.len() - known.extension().len() - 1];
+            final_path.set_file_name(format!("{stem}.{}", format.extension()));
+        }
+        return final_path;
+    }
+
+    match final_path.extension() {
         Some(_) => {
             final_path.set_extension(format.extension());
             final_path
@@ -325,6 +665,13 @@ fn finalize_destination(candidate: &Path, format: ArchiveFormat) -> PathBuf {
     }
 }
 
+/// Whether `file_name` ends with `.{suffix}`, ignoring ASCII case.
+fn has_suffix(file_name: &str, suffix: &str) -> bool {
+    file_name
+        .to_ascii_lowercase()
+        .ends_with(&format!(".{suffix}"))
+}
+
 fn default_archive_name(extension: &str) -> PathBuf {
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -333,47 +680,158 @@ fn default_archive_name(extension: &str) -> PathBuf {
     PathBuf::from(format!("dotfiles-export-{timestamp}.{extension}"))
 }
 
-fn create_tar_gz(destination: &Path, manifest: &Manifest, root: &Path) -> Result<(), PackageError> {
+/// Where the builders read archive entries from.
+struct ArchiveSources<'a> {
+    root: &'a Path,
+    staging: &'a Staging,
+}
+
+impl ArchiveSources<'_> {
+    fn root_of(&self, entry: &ManifestFile) -> &Path {
+        if self.staging.paths.contains(&entry.path) {
+            self.staging.dir.path()
+        } else {
+            self.root
+        }
+    }
+}
+
+/// Files written for this archive only (encrypted secrets, pruned configs), stored at their
+/// manifest path under a temporary directory.
+struct Staging {
+    dir: TempDir,
+    paths: HashSet<String>,
+}
+
+impl Staging {
+    fn new() -> Result<Self, PackageError> {
+        Ok(Self {
+            dir: TempDir::new()?,
+            paths: HashSet::new(),
+        })
+    }
+
+    fn stage(
+        &mut self,
+        relative: &str,
+        contents: &[u8],
+        mode: u32,
+        encrypted: bool,
+    ) -> Result<ManifestFile, PackageError> {
+        secrets::write_private(&self.dir.path().join(relative), contents)?;
+        self.paths.insert(relative.to_string());
+        Ok(ManifestFile {
+            path: relative.to_string(),
+            sha256: compute_sha256_reader(contents)?,
+            mode,
+            size: contents.len() as u64,
+            encrypted,
+        })
+    }
+}
+
+/// `metadata` holds the files describing the archive, written before the manifest's entries:
+/// `manifest.json` and, when signed, `manifest.sig`.
+fn write_archive(
+    destination: &Path,
+    format: ArchiveFormat,
+    compression_level: u32,
+    metadata: &[(&str, Vec<u8>)],
+    manifest: &Manifest,
+    sources: &ArchiveSources,
+) -> Result<(), PackageError> {
     let file = File::create(destination)?;
-    let encoder = GzEncoder::new(file, Compression::default());
-    let mut builder = TarBuilder::new(encoder);
+    match format {
+        ArchiveFormat::TarGz => {
+            let encoder = GzEncoder::new(file, Compression::new(compression_level));
+            create_tar(encoder, metadata, manifest, sources)?.finish()?;
+        }
+        ArchiveFormat::TarZst => {
+            let encoder = zstd::Encoder::new(file, compression_level as i32)?;
+            create_tar(encoder, metadata, manifest, sources)?.finish()?;
+        }
+        ArchiveFormat::TarXz => {
+            let encoder = XzEncoder::new(file, compression_level);
+            create_tar(encoder, metadata, manifest, sources)?.finish()?;
+        }
+        ArchiveFormat::Zip => create_zip(file, compression_level, metadata, manifest, sources)?,
+        ArchiveFormat::Tar => {
+            create_tar(file, metadata, manifest, sources)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the tarball into `writer` and hands it back so the caller can finish the compressor.
+fn create_tar<W: Write>(
+    writer: W,
+    metadata: &[(&str, Vec<u8>)],
+    manifest: &Manifest,
+    sources: &ArchiveSources,
+) -> Result<W, PackageError> {
+    let mut builder = TarBuilder::new(writer);
+
+    for (name, bytes) in metadata {
+        append_bytes_to_tar(&mut builder, name, bytes)?;
+    }
 
-    append_bytes_to_tar(
+    append_file_to_tar(
         &mut builder,
-        MANIFEST_FILE_NAME,
-        &serde_json::to_vec_pretty(manifest)?,
+        sources.root_of(&manifest.config),
+        &manifest.config,
     )?;
-
-    append_file_to_tar(&mut builder, root, &manifest.config)?;
+    for include in &manifest.includes {
+        append_file_to_tar(&mut builder, sources.root_of(include), include)?;
+    }
     for entry in &manifest.tools {
-        append_file_to_tar(&mut builder, root, &entry.artifact)?;
+        append_file_to_tar(
+            &mut builder,
+            sources.root_of(&entry.artifact),
+            &entry.artifact,
+        )?;
         for related in &entry.related_files {
-            append_file_to_tar(&mut builder, root, related)?;
+            append_file_to_tar(&mut builder, sources.root_of(related), related)?;
         }
     }
 
-    builder.finish()?;
-    Ok(())
+    Ok(builder.into_inner()?)
 }
 
-fn create_zip(destination: &Path, manifest: &Manifest, root: &Path) -> Result<(), PackageError> {
-    let file = File::create(destination)?;
+fn create_zip(
+    file: File,
+    compression_level: u32,
+    metadata: &[(&str, Vec<u8>)],
+    manifest: &Manifest,
+    sources: &ArchiveSources,
+) -> Result<(), PackageError> {
     let mut writer = ZipWriter::new(file);
+    let options = ZipFileOptions::<()>::default()
+        .compression_method(CompressionMethod::Deflated)
+        .compression_level(Some(i64::from(compression_level)));
 
-    let manifest_bytes = serde_json::to_vec_pretty(manifest)?;
-    writer.start_file::<_, ()>(
-        MANIFEST_FILE_NAME,
-        ZipFileOptions::default()
-            .compression_method(CompressionMethod::Deflated)
-            .unix_permissions(0o644),
-    )?;
-    writer.write_all(&manifest_bytes)?;
+    for (name, bytes) in metadata {
+        writer.start_file(*name, options.unix_permissions(0o644))?;
+        writer.write_all(bytes)?;
+    }
 
-    add_file_to_zip(&mut writer, root, &manifest.config)?;
+    add_file_to_zip(
+        &mut writer,
+        options,
+        sources.root_of(&manifest.config),
+        &manifest.config,
+    )?;
+    for include in &manifest.includes {
+        add_file_to_zip(&mut writer, options, sources.root_of(include), include)?;
+    }
     for entry in &manifest.tools {
-        add_file_to_zip(&mut writer, root, &entry.artifact)?;
+        add_file_to_zip(
+            &mut writer,
+            options,
+            sources.root_of(&entry.artifact),
+            &entry.artifact,
+        )?;
         for related in &entry.related_files {
-            add_file_to_zip(&mut writer, root, related)?;
+            add_file_to_zip(&mut writer, options, sources.root_of(related), related)?;
         }
     }
 
@@ -477,6 +935,7 @@ fn append_file_to_tar<W: Write>(
 
 fn add_file_to_zip(
     writer: &mut ZipWriter<File>,
+    options: ZipFileOptions<()>,
     root: &Path,
     entry: &ManifestFile,
 ) -> Result<(), PackageError> {
@@ -493,59 +952,269 @@ fn add_file_to_zip(
     }
 
     let mut file = File::open(&full_path)?;
-    writer.start_file::<_, ()>(
+    writer.start_file(
         path_to_string(Path::new(trimmed)),
-        ZipFileOptions::default()
-            .compression_method(CompressionMethod::Deflated)
-            .unix_permissions(entry.mode),
+        options.unix_permissions(entry.mode),
     )?;
     io::copy(&mut file, writer)?;
     Ok(())
 }
 
+/// Picks the format from the file's magic bytes, falling back to its name for files that
+/// cannot be read or are not recognised.
 fn detect_archive_format(path: &Path) -> Result<ArchiveFormat, PackageError> {
-    if let Some(name) = path.file_name().and_then(|name| name.to_str())
-        && (name.ends_with(".tar.gz") || name.ends_with(".tgz"))
-    {
-        return Ok(ArchiveFormat::TarGz);
+    let mut header = Vec::with_capacity(MAGIC_LEN);
+    let sniffed = File::open(path)
+        .and_then(|file| file.take(MAGIC_LEN as u64).read_to_end(&mut header))
+        .ok()
+        .and_then(|_| ArchiveFormat::from_magic(&header));
+    if let Some(format) = sniffed {
+        return Ok(format);
     }
 
-    let lowercase = path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.to_ascii_lowercase())
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
         .unwrap_or_default();
+    ArchiveFormat::ALL
+        .into_iter()
+        .find(|format| {
+            std::iter::once(format.extension())
+                .chain(format.aliases().iter().copied())
+                .any(|suffix| has_suffix(name, suffix))
+        })
+        .ok_or(PackageError::UnsupportedArchive)
+}
+
+/// An archive path, or stdin (`-`) copied into a temporary file since zip archives have to be
+/// seekable.
+struct ArchiveSource {
+    path: PathBuf,
+    spooled: Option<NamedTempFile>,
+}
+
+impl ArchiveSource {
+    fn open(path: &Path) -> Result<Self, PackageError> {
+        if path != Path::new(STDIN_PATH) {
+            return Ok(Self {
+                path: path.to_path_buf(),
+                spooled: None,
+            });
+        }
+        let mut spooled = NamedTempFile::new()?;
+        io::copy(&mut io::stdin().lock(), &mut spooled)?;
+        spooled.flush()?;
+        Ok(Self {
+            path: spooled.path().to_path_buf(),
+            spooled: Some(spooled),
+        })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn is_stdin(&self) -> bool {
+        self.spooled.is_some()
+    }
+}
+
+/// Reads the manifest straight from the archive stream, without extracting anything.
+pub fn read_manifest(path: &Path) -> Result<(ArchiveFormat, Manifest), PackageError> {
+    let source = ArchiveSource::open(path)?;
+    let format = detect_archive_format(source.path())?;
+    let file = File::open(source.path())?;
+
+    let manifest: Manifest = if format == ArchiveFormat::Zip {
+        let mut archive = ZipArchive::new(file)?;
+        match archive.by_name(MANIFEST_FILE_NAME) {
+            Ok(entry) => serde_json::from_reader(entry)?,
+            Err(ZipError::FileNotFound) => return Err(PackageError::MissingManifest),
+            Err(error) => return Err(error.into()),
+        }
+    } else {
+        let mut archive = TarArchive::new(tar_stream(format, file)?);
+        let mut manifest = None;
+        for entry in archive.entries()? {
+            let entry = entry?;
+            if entry.path()? == Path::new(MANIFEST_FILE_NAME) {
+                manifest = Some(serde_json::from_reader(entry)?);
+                break;
+            }
+        }
+        manifest.ok_or(PackageError::MissingManifest)?
+    };
+
+    check_manifest_version(&manifest)?;
+    Ok((format, manifest))
+}
+
+/// Checks every manifest entry of the archive at `path` (or stdin for `-`) without installing
+/// it. Problems with individual entries are collected in the report; only an archive that
+/// cannot be read or has no usable manifest is an error.
+pub fn verify_archive(path: &Path) -> Result<VerifyReport, PackageError> {
+    let source = ArchiveSource::open(path)?;
+    let format = detect_archive_format(source.path())?;
+    let temp_dir = extract_archive(source.path(), format)?;
+    let root = temp_dir.path();
+    let manifest = read_extracted_manifest(root)?;
+
+    let mut problems = Vec::new();
+    let signed_by = match read_signature(root) {
+        Ok(key) => key.map(|key| hex::encode(key.as_bytes())),
+        Err(error) => {
+            problems.push(error);
+            None
+        }
+    };
+    if let Err(error) = validate_manifest_paths(&manifest) {
+        problems.push(error);
+    }
+
+    let entries = std::iter::once(&manifest.config)
+        .chain(&manifest.includes)
+        .chain(
+            manifest
+                .tools
+                .iter()
+                .flat_map(|entry| std::iter::once(&entry.artifact).chain(&entry.related_files)),
+        )
+        .collect::<Vec<_>>();
+    for entry in &entries {
+        // Escaping paths were reported by `validate_manifest_paths` and are never read.
+        if validate_relative_path(&entry.path).is_err() {
+            continue;
+        }
+        match verify_manifest_entry(root, entry) {
+            Err(error @ PackageError::MissingFile { .. }) => {
+                problems.push(error);
+                continue;
+            }
+            Err(error) => problems.push(error),
+            Ok(()) => {}
+        }
+        problems.extend(check_size_and_mode(root, entry)?);
+    }
+
+    let listed = entries
+        .iter()
+        .map(|entry| entry.path.trim_end_matches('/'))
+        .collect::<HashSet<_>>();
+    for file in WalkDir::new(root).min_depth(1) {
+        let file = file.map_err(io::Error::from)?;
+        if file.file_type().is_dir() {
+            continue;
+        }
+        let relative = path_to_string(file.path().strip_prefix(root).unwrap_or(file.path()));
+        let metadata = [MANIFEST_FILE_NAME, SIGNATURE_FILE_NAME];
+        if !metadata.contains(&relative.as_str()) && !listed.contains(relative.as_str()) {
+            problems.push(PackageError::UnlistedFile { path: relative });
+        }
+    }
+
+    Ok(VerifyReport {
+        format,
+        checked: entries.len(),
+        signed_by,
+        problems,
+    })
+}
+
+/// A signature that does not match the manifest is always an error; `require_signature` also
+/// rejects unsigned archives and keys that are not trusted.
+fn check_signature(
+    root: &Path,
+    trusted_keys: &[VerifyingKey],
+    require_signature: bool,
+) -> Result<SignatureStatus, PackageError> {
+    match read_signature(root)? {
+        None if require_signature => Err(PackageError::UnsignedArchive),
+        None => Ok(SignatureStatus::Unsigned),
+        Some(key) if trusted_keys.contains(&key) => {
+            Ok(SignatureStatus::Trusted(hex::encode(key.as_bytes())))
+        }
+        Some(key) if require_signature => Err(PackageError::UntrustedSignature {
+            key: hex::encode(key.as_bytes()),
+        }),
+        Some(key) => Ok(SignatureStatus::Untrusted(hex::encode(key.as_bytes()))),
+    }
+}
+
+/// Trusted keys are configured but the archive is unsigned, and `--require-signature` was not
+/// given, so the install goes ahead.
+fn missing_signature_warning(
+    status: &SignatureStatus,
+    trusted_keys: &[VerifyingKey],
+) -> Option<String> {
+    (*status == SignatureStatus::Unsigned && !trusted_keys.is_empty()).then(|| {
+        "archive is not signed although trusted keys are configured \
+         (pass --require-signature to refuse unsigned archives)"
+            .to_string()
+    })
+}
+
+/// The key that signed the extracted manifest, or `None` for an unsigned archive.
+fn read_signature(root: &Path) -> Result<Option<VerifyingKey>, PackageError> {
+    let signature_path = root.join(SIGNATURE_FILE_NAME);
+    if !signature_path.exists() {
+        return Ok(None);
+    }
+    let signature: ManifestSignature = serde_json::from_slice(&fs::read(signature_path)?)?;
+    let key = signature.verify(&fs::read(root.join(MANIFEST_FILE_NAME))?)?;
+    Ok(Some(key))
+}
 
-    match lowercase.as_str() {
-        "zip" => Ok(ArchiveFormat::Zip),
-        "gz" => Ok(ArchiveFormat::TarGz),
-        _ => Err(PackageError::UnsupportedArchive),
+fn read_extracted_manifest(root: &Path) -> Result<Manifest, PackageError> {
+    let manifest_path = root.join(MANIFEST_FILE_NAME);
+    if !manifest_path.exists() {
+        return Err(PackageError::MissingManifest);
     }
+
+    let manifest_file = File::open(&manifest_path)?;
+    let manifest: Manifest = serde_json::from_reader(manifest_file)?;
+    check_manifest_version(&manifest)?;
+    Ok(manifest)
+}
+
+fn check_manifest_version(manifest: &Manifest) -> Result<(), PackageError> {
+    if manifest.version != MANIFEST_VERSION {
+        return Err(PackageError::ManifestVersionMismatch {
+            found: manifest.version,
+            expected: MANIFEST_VERSION,
+        });
+    }
+    Ok(())
 }
 
 fn extract_archive(path: &Path, format: ArchiveFormat) -> Result<TempDir, PackageError> {
     let temp_dir = TempDir::new()?;
+    let file = File::open(path)?;
 
     match format {
-        ArchiveFormat::TarGz => {
-            let file = File::open(path)?;
-            let decoder = GzDecoder::new(file);
-            let mut archive = TarArchive::new(decoder);
-            archive.unpack(temp_dir.path())?;
-        }
-        ArchiveFormat::Zip => {
-            let file = File::open(path)?;
-            let mut archive = ZipArchive::new(file)?;
-            archive.extract(temp_dir.path())?;
-        }
+        ArchiveFormat::Zip => ZipArchive::new(file)?.extract(temp_dir.path())?,
+        _ => TarArchive::new(tar_stream(format, file)?).unpack(temp_dir.path())?,
     }
 
     Ok(temp_dir)
 }
 
+/// The decompressed tar stream of a tarball.
+fn tar_stream(format: ArchiveFormat, file: File) -> Result<Box<dyn Read>, PackageError> {
+    Ok(match format {
+        ArchiveFormat::TarGz => Box::new(GzDecoder::new(file)),
+        ArchiveFormat::TarZst => Box::new(zstd::Decoder::new(file)?),
+        ArchiveFormat::TarXz => Box::new(XzDecoder::new(file)),
+        ArchiveFormat::Tar => Box::new(file),
+        ArchiveFormat::Zip => return Err(PackageError::UnsupportedArchive),
+    })
+}
+
 fn validate_manifest_paths(manifest: &Manifest) -> Result<(), PackageError> {
     let mut seen = HashSet::new();
     check_path(&mut seen, &manifest.config.path)?;
+    for include in &manifest.includes {
+        check_path(&mut seen, &include.path)?;
+    }
     for entry in &manifest.tools {
         check_path(&mut seen, &entry.artifact.path)?;
         for related in &entry.related_files {
@@ -569,7 +1238,7 @@ fn verify_manifest_entry(root: &Path, entry: &ManifestFile) -> Result<(), Packag
     let source = root.join(&entry.path);
     if !source.exists() {
         return Err(PackageError::MissingFile {
-            path: source.to_string_lossy().into_owned(),
+            path: entry.path.clone(),
         });
     }
 
@@ -587,9 +1256,35 @@ fn verify_manifest_entry(root: &Path, entry: &ManifestFile) -> Result<(), Packag
     Ok(())
 }
 
+/// Compares an extracted entry's size and permission bits with the manifest.
+fn check_size_and_mode(
+    root: &Path,
+    entry: &ManifestFile,
+) -> Result<Vec<PackageError>, PackageError> {
+    let metadata = fs::metadata(root.join(&entry.path))?;
+    let mut problems = Vec::new();
+    if metadata.is_file() && metadata.len() != entry.size {
+        problems.push(PackageError::SizeMismatch {
+            path: entry.path.clone(),
+            expected: entry.size,
+            actual: metadata.len(),
+        });
+    }
+    let (expected, actual) = (entry.mode & 0o777, file_mode(&metadata) & 0o777);
+    if expected != actual {
+        problems.push(PackageError::ModeMismatch {
+            path: entry.path.clone(),
+            expected,
+            actual,
+        });
+    }
+    Ok(problems)
+}
+
 fn resolve_destination_root(
     manifest: &Manifest,
     options: &InstallOptions,
+    interactive: bool,
 ) -> Result<PathBuf, PackageError> {
     if let Some(root) = &options.destination_root {
         return Ok(root.clone());
@@ -599,6 +1294,10 @@ fn resolve_destination_root(
         return Ok(PathBuf::from(&manifest.original_root));
     }
 
+    if !interactive {
+        return Err(PackageError::PromptUnavailable);
+    }
+
     let default_value = manifest.original_root.clone();
     let input: String = Input::new()
         .with_prompt("Destination root")
@@ -645,7 +1344,9 @@ fn install_file(
     Ok(())
 }
 
-fn backup_existing(path: &Path) -> Result<PathBuf, PackageError> {
+/// Moves `path` aside to `<name>.bak.<timestamp>` in the same directory and returns the new
+/// location. Directories and symlinks are moved as a whole.
+pub(crate) fn backup_existing(path: &Path) -> Result<PathBuf, PackageError> {
     let timestamp = Utc::now().format("%Y%m%d%H%M%S");
     let file_name = path
         .file_name()
@@ -657,12 +1358,7 @@ fn backup_existing(path: &Path) -> Result<PathBuf, PackageError> {
         .map(|parent| parent.join(file_name.clone()))
         .unwrap_or_else(|| PathBuf::from(file_name.clone()));
 
-    if path.is_dir() {
-        fs::create_dir_all(&backup_path)?;
-    } else {
-        fs::rename(path, &backup_path)?;
-    }
-
+    fs::rename(path, &backup_path)?;
     Ok(backup_path)
 }
 
@@ -742,6 +1438,7 @@ fn file_mode(metadata: &fs::Metadata) -> u32 {
 fn collect_related_files(
     root: &Path,
     script_relative: &Path,
+    filter: &mut ExportFilter,
 ) -> Result<Vec<ManifestFile>, PackageError> {
     let script_dir = script_relative.parent().map(Path::to_path_buf);
     let mut files = Vec::new();
@@ -750,10 +1447,12 @@ fn collect_related_files(
     if let Some(dir) = script_dir {
         let absolute_dir = root.join(&dir);
         if absolute_dir.is_dir() {
-            for entry in WalkDir::new(&absolute_dir)
-                .into_iter()
-                .filter_map(Result::ok)
-            {
+            let excludes = filter.rules.for_tool(&absolute_dir)?;
+            let mut walker = WalkDir::new(&absolute_dir).into_iter();
+            while let Some(entry) = walker.next() {
+                let Ok(entry) = entry else {
+                    continue;
+                };
                 let path = entry.path();
                 if path == root.join(script_relative) {
                     continue;
@@ -765,6 +1464,17 @@ fn collect_related_files(
                     continue;
                 }
 
+                let is_dir = entry.file_type().is_dir();
+                if path != absolute_dir && excludes.is_excluded(path, is_dir) {
+                    if is_dir {
+                        filter.skipped.push(format!("{relative_string}/"));
+                        walker.skip_current_dir();
+                    } else {
+                        filter.skipped.push(relative_string);
+                    }
+                    continue;
+                }
+
                 let metadata = fs::metadata(path)?;
 
                 if path.is_dir() {
@@ -773,13 +1483,21 @@ fn collect_related_files(
                         sha256: String::new(),
                         mode: file_mode(&metadata),
                         size: 0,
+                        encrypted: false,
                     });
+                } else if path.is_file() && filter.secrets.patterns.matches(&relative) {
+                    if let Some(encrypted) =
+                        filter.secrets.pack(&mut filter.staging, path, &relative)?
+                    {
+                        files.push(encrypted);
+                    }
                 } else if path.is_file() {
                     files.push(ManifestFile {
                         path: relative_string,
                         sha256: compute_sha256_path(path)?,
                         mode: file_mode(&metadata),
                         size: metadata.len(),
+                        encrypted: false,
                     });
                 }
             }
@@ -790,6 +1508,106 @@ fn collect_related_files(
     Ok(files)
 }
 
+/// What `collect_related_files` leaves out of, or encrypts for, an export.
+struct ExportFilter {
+    rules: ExcludeRules,
+    secrets: SecretPacker,
+    staging: Staging,
+    /// Paths left out by exclude rules; an excluded directory is listed once, with a trailing `/`.
+    skipped: Vec<String>,
+}
+
+/// Keeps plaintext secrets out of exports: each one is either left out and noted in
+/// `excluded`, or encrypted, staged, and packed as `<path>.enc`.
+struct SecretPacker {
+    patterns: SecretPatterns,
+    passphrase: Option<Passphrase>,
+    excluded: Vec<String>,
+}
+
+impl SecretPacker {
+    fn new(globs: &[String], encrypt: bool) -> Result<Self, PackageError> {
+        Ok(Self {
+            patterns: SecretPatterns::new(globs)?,
+            passphrase: encrypt.then(|| Passphrase::from_env_or_prompt(true)),
+            excluded: Vec::new(),
+        })
+    }
+
+    fn pack(
+        &mut self,
+        staging: &mut Staging,
+        path: &Path,
+        relative: &Path,
+    ) -> Result<Option<ManifestFile>, PackageError> {
+        let Some(passphrase) = self.passphrase.as_mut() else {
+            self.excluded.push(path_to_string(relative));
+            return Ok(None);
+        };
+        let sealed = secrets::encrypt(&fs::read(path)?, passphrase.get(true)?)?;
+        let packed = path_to_string(&secrets::encrypted_path(relative));
+        staging.stage(&packed, &sealed, 0o600, true).map(Some)
+    }
+}
+
+/// Config files pulled in through `Include` or discovered as `tool.yaml`. With a selection,
+/// `pruned` holds their contents without the other tools (`None` drops the file).
+fn collect_included_configs(
+    config: &config::Config,
+    root: &Path,
+    manifest_tools: &[ManifestToolEntry],
+    pruned: &BTreeMap<PathBuf, Option<String>>,
+    staging: &mut Staging,
+) -> Result<Vec<ManifestFile>, PackageError> {
+    let packaged: HashSet<&str> = manifest_tools
+        .iter()
+        .flat_map(|entry| {
+            std::iter::once(entry.artifact.path.as_str())
+                .chain(entry.related_files.iter().map(|file| file.path.as_str()))
+        })
+        .collect();
+
+    let mut files = Vec::new();
+    for included in config.loaded_files().iter().skip(1) {
+        let Ok(relative) = relative_path(included, root) else {
+            eprintln!(
+                "Warning: included config {} is outside {} and will not be exported",
+                included.display(),
+                root.display()
+            );
+            continue;
+        };
+        let relative_string = path_to_string(&relative);
+        if packaged.contains(relative_string.as_str()) {
+            continue;
+        }
+
+        let metadata = fs::metadata(included)?;
+        match pruned.get(included) {
+            Some(None) => continue,
+            Some(Some(contents)) => {
+                files.push(staging.stage(
+                    &relative_string,
+                    contents.as_bytes(),
+                    file_mode(&metadata),
+                    false,
+                )?);
+                continue;
+            }
+            None => {}
+        }
+        files.push(ManifestFile {
+            path: relative_string,
+            sha256: compute_sha256_path(included)?,
+            mode: file_mode(&metadata),
+            size: metadata.len(),
+            encrypted: false,
+        });
+    }
+
+    Ok(files)
+}
+
 fn rewrite_config_root(config_path: &Path, new_root: &Path) -> Result<(), PackageError> {
     use std::fmt::Write as FmtWrite;
 
@@ -885,7 +1703,185 @@ mod tests {
     #[test]
     fn test_archive_format_extension() {
         assert_eq!(ArchiveFormat::TarGz.extension(), "tar.gz");
+        assert_eq!(ArchiveFormat::TarZst.extension(), "tar.zst");
+        assert_eq!(ArchiveFormat::TarXz.extension(), "tar.xz");
         assert_eq!(ArchiveFormat::Zip.extension(), "zip");
+        assert_eq!(ArchiveFormat::Tar.extension(), "tar");
+    }
+
+    #[test]
+    fn test_compression_level() {
+        assert_eq!(ArchiveFormat::TarGz.compression_level(None).unwrap(), 6);
+        assert_eq!(ArchiveFormat::TarZst.compression_level(None).unwrap(), 3);
+        assert_eq!(
+            ArchiveFormat::TarZst.compression_level(Some(19)).unwrap(),
+            19
+        );
+        assert_eq!(ArchiveFormat::TarXz.compression_level(Some(9)).unwrap(), 9);
+        assert!(matches!(
+            ArchiveFormat::Zip.compression_level(Some(12)),
+            Err(PackageError::InvalidCompressionLevel { min: 0, max: 9, .. })
+        ));
+        assert!(ArchiveFormat::TarZst.compression_level(Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_verify_archive_reports_every_problem() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |path: &str, contents: &str, size: u64| ManifestFile {
+            path: path.to_string(),
+            sha256: compute_sha256_reader(contents.as_bytes()).unwrap(),
+            mode: 0o100644,
+            size,
+            encrypted: false,
+        };
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            generated_at: "2024-01-01T00:00:00Z".to_string(),
+            original_root: "/test".to_string(),
+            config: file("config.yaml", "Version: 1\n", 11),
+            includes: vec![
+                file("tools/a.yaml", "listed\n", 7),
+                file("tools/b.yaml", "gone\n", 5),
+            ],
+            tools: vec![],
+        };
+
+        let path = dir.path().join("backup.tar");
+        let mut builder = TarBuilder::new(File::create(&path).unwrap());
+        let manifest_json = serde_json::to_vec(&manifest).unwrap();
+        append_bytes_to_tar(&mut builder, MANIFEST_FILE_NAME, &manifest_json).unwrap();
+        append_bytes_to_tar(&mut builder, "config.yaml", b"Version: 1\n").unwrap();
+        append_bytes_to_tar(&mut builder, "tools/a.yaml", b"edited!!\n").unwrap();
+        append_bytes_to_tar(&mut builder, "stray.txt", b"stray\n").unwrap();
+        builder.into_inner().unwrap();
+
+        let report = verify_archive(&path).unwrap();
+        assert_eq!(report.format, ArchiveFormat::Tar);
+        assert_eq!(report.checked, 3);
+        let problems = report
+            .problems
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            vec![
+                "Hash mismatch for tools/a.yaml",
+                "Size mismatch for tools/a.yaml: manifest says 7 bytes, archive has 9",
+                "Required file 'tools/b.yaml' not found in archive",
+                "'stray.txt' is in the archive but not in the manifest",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let trusted = [key.verifying_key()];
+        fs::write(root.join(MANIFEST_FILE_NAME), b"{}").unwrap();
+
+        assert_eq!(
+            check_signature(root, &trusted, false).unwrap(),
+            SignatureStatus::Unsigned
+        );
+        assert!(matches!(
+            check_signature(root, &trusted, true),
+            Err(PackageError::UnsignedArchive)
+        ));
+
+        let signature = ManifestSignature::sign(&key, b"{}");
+        let hex_key = signature.public_key.clone();
+        fs::write(
+            root.join(SIGNATURE_FILE_NAME),
+            serde_json::to_vec(&signature).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            check_signature(root, &trusted, true).unwrap(),
+            SignatureStatus::Trusted(hex_key.clone())
+        );
+        assert_eq!(
+            check_signature(root, &[], false).unwrap(),
+            SignatureStatus::Untrusted(hex_key)
+        );
+        assert!(matches!(
+            check_signature(root, &[], true),
+            Err(PackageError::UntrustedSignature { .. })
+        ));
+
+        fs::write(root.join(MANIFEST_FILE_NAME), b"{\"tampered\":true}").unwrap();
+        assert!(matches!(
+            check_signature(root, &trusted, false),
+            Err(PackageError::Signing(SigningError::InvalidSignature))
+        ));
+    }
+
+    #[test]
+    fn test_missing_signature_warning() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]).verifying_key();
+
+        assert!(
+            missing_signature_warning(&SignatureStatus::Unsigned, &[key])
+                .is_some_and(|warning| warning.contains("--require-signature"))
+        );
+        assert_eq!(
+            missing_signature_warning(&SignatureStatus::Unsigned, &[]),
+            None
+        );
+        assert_eq!(
+            missing_signature_warning(
+                &SignatureStatus::Trusted(hex::encode(key.as_bytes())),
+                &[key]
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_archive_formats_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut staging = Staging::new().unwrap();
+        let config = staging
+            .stage("config.yaml", b"Version: 1\n", 0o644, false)
+            .unwrap();
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            generated_at: "2024-01-01T00:00:00Z".to_string(),
+            original_root: "/test".to_string(),
+            config,
+            includes: vec![],
+            tools: vec![],
+        };
+        let sources = ArchiveSources {
+            root: dir.path(),
+            staging: &staging,
+        };
+
+        let metadata = [(
+            MANIFEST_FILE_NAME,
+            serde_json::to_vec_pretty(&manifest).unwrap(),
+        )];
+
+        for format in ArchiveFormat::ALL {
+            let level = format.compression_level(None).unwrap();
+            // No extension, so the format has to come from the magic bytes.
+            let path = dir.path().join(format!("{format:?}"));
+            write_archive(&path, format, level, &metadata, &manifest, &sources).unwrap();
+
+            assert_eq!(detect_archive_format(&path).unwrap(), format);
+            let (read_format, read) = read_manifest(&path).unwrap();
+            assert_eq!(read_format, format);
+            assert_eq!(read.config.path, "config.yaml");
+            let extracted = extract_archive(&path, format).unwrap();
+            assert_eq!(
+                fs::read_to_string(extracted.path().join("config.yaml")).unwrap(),
+                "Version: 1\n"
+            );
+            assert!(extracted.path().join(MANIFEST_FILE_NAME).exists());
+        }
     }
 
     #[test]
@@ -898,9 +1894,46 @@ mod tests {
             detect_archive_format(Path::new("test.zip")),
             Ok(ArchiveFormat::Zip)
         ));
+        assert!(matches!(
+            detect_archive_format(Path::new("test.tar.zst")),
+            Ok(ArchiveFormat::TarZst)
+        ));
+        assert!(matches!(
+            detect_archive_format(Path::new("TEST.TXZ")),
+            Ok(ArchiveFormat::TarXz)
+        ));
         assert!(detect_archive_format(Path::new("test.txt")).is_err());
     }
 
+    #[test]
+    fn test_detect_archive_format_prefers_magic_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let renamed = dir.path().join("export.zip");
+        fs::write(&renamed, [0x1f, 0x8b, 0x08, 0x00]).unwrap();
+        assert_eq!(
+            detect_archive_format(&renamed).unwrap(),
+            ArchiveFormat::TarGz
+        );
+
+        let mut tar_header = vec![0; MAGIC_LEN];
+        tar_header[257..262].copy_from_slice(b"ustar");
+        let unnamed = dir.path().join("dotfiles-export");
+        fs::write(&unnamed, &tar_header).unwrap();
+        assert_eq!(detect_archive_format(&unnamed).unwrap(), ArchiveFormat::Tar);
+
+        let unknown = dir.path().join("notes.tgz");
+        fs::write(&unknown, "plain text").unwrap();
+        assert_eq!(
+            detect_archive_format(&unknown).unwrap(),
+            ArchiveFormat::TarGz
+        );
+        fs::write(&unnamed, "plain text").unwrap();
+        assert!(matches!(
+            detect_archive_format(&unnamed),
+            Err(PackageError::UnsupportedArchive)
+        ));
+    }
+
     #[test]
     fn test_validate_relative_path() {
         // Safe paths
@@ -947,6 +1980,13 @@ mod tests {
         let path = Path::new("myfile.txt");
         let result = finalize_destination(path, ArchiveFormat::Zip);
         assert_eq!(result.to_string_lossy(), "myfile.zip");
+
+        // Multi-part archive suffixes are matched or replaced as a whole
+        let path = Path::new("backup.tar.gz");
+        let result = finalize_destination(path, ArchiveFormat::TarGz);
+        assert_eq!(result.to_string_lossy(), "backup.tar.gz");
+        let result = finalize_destination(path, ArchiveFormat::TarZst);
+        assert_eq!(result.to_string_lossy(), "backup.tar.zst");
     }
 
     #[test]
@@ -1054,6 +2094,35 @@ Preferences:
         }
     }
 
+    #[test]
+    fn test_install_file_moves_existing_directory_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.conf");
+        fs::write(&source, "installed").unwrap();
+        // The archive has a file where the destination has a directory.
+        let target = dir.path().join("dest").join("app.conf");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("local.conf"), "local").unwrap();
+        let mut report = InstallReport {
+            destination_root: dir.path().join("dest"),
+            installed_files: Vec::new(),
+            backups: Vec::new(),
+            decrypted_secrets: Vec::new(),
+            signature: SignatureStatus::Unsigned,
+        };
+
+        install_file(&source, &target, 0o100644, &mut report).unwrap();
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "installed");
+        assert_eq!(report.backups.len(), 1);
+        let backup = &report.backups[0];
+        assert!(backup.is_dir());
+        assert_eq!(
+            fs::read_to_string(backup.join("local.conf")).unwrap(),
+            "local"
+        );
+    }
+
     #[test]
     fn test_ensure_destination_parent() {
         use tempfile::TempDir;
@@ -1093,6 +2162,7 @@ Preferences:
             sha256: "abc123".to_string(),
             mode: 0o644,
             size: 1024,
+            encrypted: false,
         };
 
         assert_eq!(manifest_file.path, "test/file.txt");
@@ -1109,11 +2179,14 @@ Preferences:
             root: "tool1".to_string(),
             file: "tool1.sh".to_string(),
             dependencies: vec!["tool2".to_string()],
+            optional_dependencies: vec![],
+            conflicts: vec![],
             artifact: ManifestFile {
                 path: "tool1/tool1.sh".to_string(),
                 sha256: "def456".to_string(),
                 mode: 0o755,
                 size: 2048,
+                encrypted: false,
             },
             related_files: vec![],
         };
@@ -1134,18 +2207,23 @@ Preferences:
                 sha256: "hash1".to_string(),
                 mode: 0o644,
                 size: 100,
+                encrypted: false,
             },
+            includes: vec![],
             tools: vec![ManifestToolEntry {
                 id: "tool1".to_string(),
                 name: "Tool 1".to_string(),
                 root: "tool1".to_string(),
                 file: "tool1.sh".to_string(),
                 dependencies: vec![],
+                optional_dependencies: vec![],
+                conflicts: vec![],
                 artifact: ManifestFile {
                     path: "config.yaml".to_string(), // Duplicate!
                     sha256: "hash2".to_string(),
                     mode: 0o755,
                     size: 200,
+                    encrypted: false,
                 },
                 related_files: vec![],
             }],
@@ -1156,6 +2234,176 @@ Preferences:
         assert!(matches!(result, Err(PackageError::DuplicatePath { .. })));
     }
 
+    #[test]
+    fn test_collect_related_files_keeps_secrets_out_of_plaintext() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("npm")).unwrap();
+        fs::write(root.join("npm/npm.sh"), "npm i").unwrap();
+        fs::write(root.join("npm/.npmrc"), "_authToken=abc").unwrap();
+        fs::write(root.join("npm/aliases"), "alias n=npm").unwrap();
+        let script = Path::new("npm/npm.sh");
+        let secrets = vec![".npmrc".to_string()];
+
+        let filter = |secrets: SecretPacker| ExportFilter {
+            rules: ExcludeRules::new(root, &[]).unwrap(),
+            secrets,
+            staging: Staging::new().unwrap(),
+            skipped: Vec::new(),
+        };
+
+        let mut excluding = filter(SecretPacker::new(&secrets, false).unwrap());
+        let files = collect_related_files(root, script, &mut excluding).unwrap();
+        let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["npm/", "npm/aliases"]);
+        assert_eq!(excluding.secrets.excluded, ["npm/.npmrc"]);
+
+        let mut encrypting = filter(SecretPacker {
+            passphrase: Some(Passphrase::fixed("pass")),
+            ..SecretPacker::new(&secrets, false).unwrap()
+        });
+        let files = collect_related_files(root, script, &mut encrypting).unwrap();
+        let sealed = files.iter().find(|file| file.encrypted).unwrap();
+        assert_eq!(sealed.path, "npm/.npmrc.enc");
+        let staging = encrypting.staging.dir.path();
+        verify_manifest_entry(staging, sealed).unwrap();
+        assert_eq!(
+            secrets::decrypt_file(&staging.join(&sealed.path), "pass").unwrap(),
+            b"_authToken=abc"
+        );
+        assert!(encrypting.secrets.excluded.is_empty());
+    }
+
+    #[test]
+    fn test_collect_related_files_skips_excluded_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("node/node_modules/left-pad")).unwrap();
+        fs::write(root.join("node/node.sh"), "npm i").unwrap();
+        fs::write(root.join("node/.DS_Store"), "").unwrap();
+        fs::write(root.join("node/init.lua.swp"), "").unwrap();
+        fs::write(root.join("node/node_modules/left-pad/index.js"), "").unwrap();
+        fs::write(root.join("node/.dotfilesignore"), "node_modules/\n").unwrap();
+        fs::write(root.join(".dotfilesignore"), ".DS_Store\n").unwrap();
+        let mut filter = ExportFilter {
+            rules: ExcludeRules::new(root, &["*.swp".to_string()]).unwrap(),
+            secrets: SecretPacker::new(&[], false).unwrap(),
+            staging: Staging::new().unwrap(),
+            skipped: Vec::new(),
+        };
+
+        let files = collect_related_files(root, Path::new("node/node.sh"), &mut filter).unwrap();
+
+        let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["node/", "node/.dotfilesignore"]);
+        filter.skipped.sort();
+        assert_eq!(
+            filter.skipped,
+            ["node/.DS_Store", "node/init.lua.swp", "node/node_modules/"]
+        );
+    }
+
+    /// A root with `brew`, `helm` (depending on `brew`), and `zsh`, each with a script.
+    fn selection_fixture() -> (tempfile::TempDir, PathBuf, config::Config, Tools) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        for id in ["brew", "helm", "zsh"] {
+            fs::create_dir_all(root.join(id)).unwrap();
+            fs::write(
+                root.join(id).join(format!("{id}-settings.zsh")),
+                "echo ok\n",
+            )
+            .unwrap();
+        }
+        let config_path = root.join("config.yaml");
+        fs::write(
+            &config_path,
+            format!(
+                "Version: 1\nSystemPreferences:\n  Root: {}\nPreferences:\n  ToolsSettings:\n    - Name: brew\n    - Name: helm\n      Dependencies:\n        - brew\n    - Name: zsh\n",
+                root.display()
+            ),
+        )
+        .unwrap();
+        let config = config::Config::load_from_file(&config_path.to_string_lossy()).unwrap();
+        let (tools, _) = Tools::from_config(&config, true).unwrap();
+        (dir, config_path, config, tools)
+    }
+
+    fn selection_options(destination: PathBuf, tools: &[&str], with_deps: bool) -> ExportOptions {
+        ExportOptions {
+            destination,
+            format: ArchiveFormat::TarGz,
+            compression_level: None,
+            encrypt_secrets: false,
+            exclude: vec![],
+            tools: tools.iter().map(|id| id.to_string()).collect(),
+            tags: vec![],
+            with_dependencies: with_deps,
+            sign_key: None,
+        }
+    }
+
+    #[test]
+    fn test_select_tools_requires_hard_dependencies() {
+        let (dir, _, _, tools) = selection_fixture();
+        let destination = dir.path().join("out");
+
+        let error = select_tools(
+            &tools,
+            &selection_options(destination.clone(), &["helm"], false),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            PackageError::UnselectedDependency { ref tool_id, ref dependency_id }
+                if tool_id == "helm" && dependency_id == "brew"
+        ));
+
+        let selected = select_tools(
+            &tools,
+            &selection_options(destination.clone(), &["helm"], true),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(selected.into_iter().collect::<Vec<_>>(), ["brew", "helm"]);
+        assert!(
+            select_tools(&tools, &selection_options(destination, &[], false))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_export_with_selection_stages_pruned_config() {
+        let (dir, config_path, config, tools) = selection_fixture();
+        let options = selection_options(dir.path().join("out"), &["helm"], true);
+        let level = options.format.compression_level(None).unwrap();
+
+        let report = export_config(&tools, &config, &config_path, level, None, &options).unwrap();
+
+        let (format, manifest) = read_manifest(&report.archive_path).unwrap();
+        let ids: Vec<&str> = manifest.tools.iter().map(|tool| tool.id.as_str()).collect();
+        assert_eq!(ids, ["brew", "helm"]);
+        let extracted = extract_archive(&report.archive_path, format).unwrap();
+        assert!(!extracted.path().join("zsh").exists());
+
+        let staged = extracted.path().join(&manifest.config.path);
+        let contents = fs::read_to_string(&staged).unwrap();
+        assert!(contents.contains("Name: brew"));
+        assert!(contents.contains("Name: helm"));
+        assert!(!contents.contains("zsh"));
+        assert_eq!(
+            compute_sha256_path(&staged).unwrap(),
+            manifest.config.sha256
+        );
+        // The original config is untouched.
+        assert!(
+            fs::read_to_string(&config_path)
+                .unwrap()
+                .contains("Name: zsh")
+        );
+    }
+
     #[test]
     fn test_path_to_string_with_backslashes() {
         // Test that backslashes are converted to forward slashes
@@ -0,0 +1,232 @@
+//! Per-machine record of tool runs, rendered templates, and adopted files, used to detect
+//! drift between the config and this machine and to undo `dotfiles adopt`.
+//!
+//! The record lives outside the dotfiles root (which is usually shared between machines) in
+//! `$XDG_STATE_HOME/dotfiles/runs.json`, falling back to `~/.local/state/dotfiles/runs.json`.
+
+use crate::config;
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use color_eyre::eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const RECORD_FILE_NAME: &str = "runs.json";
+const FALLBACK_STATE_DIR: &str = "~/.local/state";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct RunRecord {
+    #[serde(default)]
+    pub tools: BTreeMap<String, ToolRecord>,
+    /// Templates rendered and secrets decrypted by `dotfiles link`, keyed by target path.
+    #[serde(default)]
+    pub templates: BTreeMap<PathBuf, TemplateRecord>,
+    /// Files moved into the dotfiles root by `dotfiles adopt`, oldest first.
+    #[serde(default)]
+    pub adoptions: Vec<AdoptionRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ToolRecord {
+    pub last_run: RunEntry,
+    /// Most recent run that succeeded; kept when a later run fails.
+    #[serde(default)]
+    pub last_success: Option<RunEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RunEntry {
+    pub finished_at: DateTime<Utc>,
+    pub success: bool,
+    /// SHA-256 of the script as it was when the run started, if it could be read.
+    #[serde(default)]
+    pub script_sha256: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct TemplateRecord {
+    pub rendered_at: DateTime<Utc>,
+    /// SHA-256 of the output as written; a different digest on disk means it was edited.
+    pub sha256: String,
+    /// SHA-256 of the encrypted source a secret was decrypted from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct AdoptionRecord {
+    pub adopted_at: DateTime<Utc>,
+    pub tool_id: String,
+    /// Where the file was, now a symlink to `stored`.
+    pub original: PathBuf,
+    /// Where the file lives inside the tool directory.
+    pub stored: PathBuf,
+    /// Key of the `Links` entry added for it.
+    pub source: String,
+}
+
+/// Outcome of one tool in a workflow run, as stored in the record.
+#[derive(Debug, Clone)]
+pub(crate) struct RunOutcome {
+    pub id: String,
+    pub script_sha256: Option<String>,
+    pub success: bool,
+    pub reason: Option<String>,
+}
+
+impl RunRecord {
+    /// Reads the record at `path`; a missing file is an empty record.
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .wrap_err_with(|| format!("Failed to parse run record {}", path.display())),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => {
+                Err(error).wrap_err_with(|| format!("Failed to read run record {}", path.display()))
+            }
+        }
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .wrap_err_with(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json + "\n")
+            .wrap_err_with(|| format!("Failed to write run record {}", path.display()))
+    }
+
+    pub(crate) fn record(&mut self, outcome: RunOutcome, finished_at: DateTime<Utc>) {
+        let entry = RunEntry {
+            finished_at,
+            success: outcome.success,
+            script_sha256: outcome.script_sha256,
+            reason: outcome.reason,
+        };
+        let last_success = if entry.success {
+            Some(entry.clone())
+        } else {
+            self.tools
+                .get(&outcome.id)
+                .and_then(|record| record.last_success.clone())
+        };
+        self.tools.insert(
+            outcome.id,
+            ToolRecord {
+                last_run: entry,
+                last_success,
+            },
+        );
+    }
+
+    pub(crate) fn get(&self, tool_id: &str) -> Option<&ToolRecord> {
+        self.tools.get(tool_id)
+    }
+
+    pub(crate) fn template(&self, target: &Path) -> Option<&TemplateRecord> {
+        self.templates.get(target)
+    }
+
+    pub(crate) fn record_template(&mut self, target: &Path, contents: impl AsRef<[u8]>) {
+        self.templates.insert(
+            target.to_path_buf(),
+            TemplateRecord {
+                rendered_at: Utc::now(),
+                sha256: content_digest(contents),
+                source_sha256: None,
+            },
+        );
+    }
+
+    pub(crate) fn record_secret(&mut self, target: &Path, contents: &[u8], source_sha256: String) {
+        self.templates.insert(
+            target.to_path_buf(),
+            TemplateRecord {
+                rendered_at: Utc::now(),
+                sha256: content_digest(contents),
+                source_sha256: Some(source_sha256),
+            },
+        );
+    }
+
+    pub(crate) fn forget_template(&mut self, target: &Path) {
+        self.templates.remove(target);
+    }
+}
+
+/// Adds `outcomes` to the record at `path`, creating it if needed.
+pub(crate) fn append(path: &Path, outcomes: Vec<RunOutcome>) -> Result<()> {
+    let mut record = RunRecord::load(path)?;
+    let finished_at = Utc::now();
+    for outcome in outcomes {
+        record.record(outcome, finished_at);
+    }
+    record.save(path)
+}
+
+/// Location of this machine's run record.
+pub(crate) fn default_path() -> PathBuf {
+    let state_dir = env::var_os("XDG_STATE_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| config::expand_home_path(FALLBACK_STATE_DIR));
+    state_dir.join("dotfiles").join(RECORD_FILE_NAME)
+}
+
+/// Hex SHA-256 of the file at `path`.
+pub(crate) fn script_digest(path: &Path) -> io::Result<String> {
+    let contents = fs::read(path)?;
+    Ok(hex::encode(Sha256::digest(contents)))
+}
+
+/// Hex SHA-256 of `contents`, matching [`script_digest`] of a file holding them.
+pub(crate) fn content_digest(contents: impl AsRef<[u8]>) -> String {
+    hex::encode(Sha256::digest(contents.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn outcome(id: &str, sha: &str, success: bool) -> RunOutcome {
+        RunOutcome {
+            id: id.to_string(),
+            script_sha256: Some(sha.to_string()),
+            success,
+            reason: (!success).then(|| "exit status 1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_failed_run_keeps_last_success() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state").join("runs.json");
+
+        append(&path, vec![outcome("brew", "aaa", true)]).unwrap();
+        append(&path, vec![outcome("brew", "bbb", false)]).unwrap();
+
+        let record = RunRecord::load(&path).unwrap();
+        let brew = record.get("brew").unwrap();
+        assert!(!brew.last_run.success);
+        assert_eq!(brew.last_run.script_sha256.as_deref(), Some("bbb"));
+        let last_success = brew.last_success.as_ref().unwrap();
+        assert_eq!(last_success.script_sha256.as_deref(), Some("aaa"));
+        assert!(record.get("zsh").is_none());
+    }
+
+    #[test]
+    fn test_load_missing_record_is_empty() {
+        let dir = tempdir().unwrap();
+        let record = RunRecord::load(&dir.path().join("runs.json")).unwrap();
+        assert!(record.tools.is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+//! Ed25519 signatures over an export's `manifest.json`. Since the manifest holds the hash of
+//! every file, a valid signature from a trusted key covers the whole archive. Keys are stored
+//! as hex: the 32-byte seed in the secret key file and the public key in `<file>.pub`.
+
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::config;
+use crate::secrets::{self, SecretError};
+
+/// Archive entry next to `manifest.json` that holds its signature.
+pub(crate) const SIGNATURE_FILE_NAME: &str = "manifest.sig";
+const ALGORITHM: &str = "ed25519";
+const PUBLIC_KEY_SUFFIX: &str = ".pub";
+
+#[derive(Debug, Error)]
+pub enum SigningError {
+    #[error("I/O error on {path}: {source}")]
+    Io { path: String, source: io::Error },
+    #[error(transparent)]
+    Write(#[from] SecretError),
+    #[error("{path} does not contain a valid Ed25519 secret key")]
+    InvalidSecretKey { path: String },
+    #[error("'{key}' is neither an Ed25519 public key nor a file containing one")]
+    InvalidPublicKey { key: String },
+    #[error("{path} already exists (pass --force to replace it)")]
+    KeyExists { path: String },
+    #[error("Unsupported signature algorithm '{0}'")]
+    UnsupportedAlgorithm(String),
+    #[error("The manifest signature is invalid; the archive may have been tampered with")]
+    InvalidSignature,
+}
+
+/// Contents of `manifest.sig`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ManifestSignature {
+    pub algorithm: String,
+    /// Hex-encoded public key of the signer.
+    pub public_key: String,
+    /// Hex-encoded signature over the exact bytes of `manifest.json`.
+    pub signature: String,
+}
+
+impl ManifestSignature {
+    pub(crate) fn sign(key: &SigningKey, manifest: &[u8]) -> Self {
+        Self {
+            algorithm: ALGORITHM.to_string(),
+            public_key: hex::encode(key.verifying_key().as_bytes()),
+            signature: hex::encode(key.sign(manifest).to_bytes()),
+        }
+    }
+
+    /// Checks the signature against `manifest` and returns the key that made it.
+    pub(crate) fn verify(&self, manifest: &[u8]) -> Result<VerifyingKey, SigningError> {
+        if self.algorithm != ALGORITHM {
+            return Err(SigningError::UnsupportedAlgorithm(self.algorithm.clone()));
+        }
+        let key = parse_public_key(&self.public_key).ok_or(SigningError::InvalidSignature)?;
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or(SigningError::InvalidSignature)?;
+        key.verify(manifest, &signature)
+            .map_err(|_| SigningError::InvalidSignature)?;
+        Ok(key)
+    }
+}
+
+/// Writes a new key pair to `path` (mode 0600) and `<path>.pub`, returning the public key path.
+pub(crate) fn generate_key_pair(path: &Path, force: bool) -> Result<PathBuf, SigningError> {
+    let public_path = public_key_path(path);
+    if !force
+        && let Some(existing) = [path, public_path.as_path()]
+            .into_iter()
+            .find(|path| path.exists())
+    {
+        return Err(SigningError::KeyExists {
+            path: existing.display().to_string(),
+        });
+    }
+
+    let mut seed = [0u8; ed25519_dalek::SECRET_KEY_LENGTH];
+    OsRng.fill_bytes(&mut seed);
+    let key = SigningKey::from_bytes(&seed);
+
+    secrets::write_private(path, format!("{}\n", hex::encode(seed)).as_bytes())?;
+    fs::write(
+        &public_path,
+        format!("{}\n", hex::encode(key.verifying_key().as_bytes())),
+    )
+    .map_err(|source| SigningError::Io {
+        path: public_path.display().to_string(),
+        source,
+    })?;
+    Ok(public_path)
+}
+
+pub(crate) fn load_signing_key(path: &Path) -> Result<SigningKey, SigningError> {
+    let contents = fs::read_to_string(path).map_err(|source| SigningError::Io {
+        path: path.display().to_string(),
+        source,
+    })?;
+    hex::decode(contents.trim())
+        .ok()
+        .and_then(|bytes| <[u8; ed25519_dalek::SECRET_KEY_LENGTH]>::try_from(bytes).ok())
+        .map(|seed| SigningKey::from_bytes(&seed))
+        .ok_or_else(|| SigningError::InvalidSecretKey {
+            path: path.display().to_string(),
+        })
+}
+
+/// A trusted key given as hex or as the path of a `.pub` file.
+pub(crate) fn load_public_key(key: &str) -> Result<VerifyingKey, SigningError> {
+    parse_public_key(key)
+        .or_else(|| {
+            fs::read_to_string(config::expand_home_path(key))
+                .ok()
+                .and_then(|contents| parse_public_key(&contents))
+        })
+        .ok_or_else(|| SigningError::InvalidPublicKey {
+            key: key.to_string(),
+        })
+}
+
+fn parse_public_key(hex_key: &str) -> Option<VerifyingKey> {
+    let bytes = hex::decode(hex_key.trim()).ok()?;
+    VerifyingKey::from_bytes(&bytes.try_into().ok()?).ok()
+}
+
+fn public_key_path(path: &Path) -> PathBuf {
+    let mut public = path.as_os_str().to_os_string();
+    public.push(PUBLIC_KEY_SUFFIX);
+    PathBuf::from(public)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_signatures_round_trip_and_detect_tampering() {
+        let dir = tempdir().unwrap();
+        let key_path = dir.path().join("signing.key");
+        let public_path = generate_key_pair(&key_path, false).unwrap();
+        assert!(matches!(
+            generate_key_pair(&key_path, false),
+            Err(SigningError::KeyExists { .. })
+        ));
+
+        let key = load_signing_key(&key_path).unwrap();
+        let trusted = load_public_key(&public_path.to_string_lossy()).unwrap();
+        assert_eq!(
+            load_public_key(&hex::encode(trusted.as_bytes())).unwrap(),
+            trusted
+        );
+
+        let signature = ManifestSignature::sign(&key, b"{\"version\":1}");
+        assert_eq!(signature.verify(b"{\"version\":1}").unwrap(), trusted);
+        assert!(matches!(
+            signature.verify(b"{\"version\":2}"),
+            Err(SigningError::InvalidSignature)
+        ));
+        assert!(load_public_key("not-a-key").is_err());
+    }
+}
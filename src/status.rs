@@ -0,0 +1,397 @@
+//! Drift between the configuration and this machine, reported by `dotfiles status` and the TUI.
+
+use crate::config::TOOL_CONFIG_NAME;
+use crate::exclude::{ExcludeRules, IGNORE_FILE_NAME};
+use crate::links::{self, LinkState};
+use crate::run_record::{self, RunRecord};
+use crate::tools::{ToolItem, Tools};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use walkdir::WalkDir;
+
+/// Names skipped when looking for files that no tool references.
+const IGNORED_NAMES: &[&str] = &[".git", ".DS_Store"];
+
+/// Whether to run each tool's `Check` script; they may be slow, so the TUI skips them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Checks {
+    Run,
+    Skip,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Drift {
+    NeverRun,
+    LastRunFailed {
+        reason: Option<String>,
+    },
+    ScriptChanged,
+    ScriptMissing,
+    CheckFailed {
+        reason: String,
+    },
+    /// A file rendered from a `.tmpl` link or decrypted from an `.enc` link was changed by hand.
+    TemplateEdited {
+        target: PathBuf,
+    },
+    /// A rendered or decrypted file no longer matches its source or the variables.
+    TemplateOutdated {
+        target: PathBuf,
+    },
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::NeverRun => write!(f, "never run on this machine"),
+            Drift::LastRunFailed {
+                reason: Some(reason),
+            } => {
+                write!(f, "last run failed ({reason})")
+            }
+            Drift::LastRunFailed { reason: None } => write!(f, "last run failed"),
+            Drift::ScriptChanged => write!(f, "script edited since last successful run"),
+            Drift::ScriptMissing => write!(f, "script is missing"),
+            Drift::CheckFailed { reason } => write!(f, "check failed ({reason})"),
+            Drift::TemplateEdited { target } => {
+                write!(f, "{} edited by hand", target.display())
+            }
+            Drift::TemplateOutdated { target } => {
+                write!(
+                    f,
+                    "{} is out of date (run `dotfiles link`)",
+                    target.display()
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ToolStatus {
+    pub id: String,
+    pub name: String,
+    pub last_success: Option<DateTime<Utc>>,
+    pub drift: Vec<Drift>,
+}
+
+impl ToolStatus {
+    pub(crate) fn is_clean(&self) -> bool {
+        self.drift.is_empty()
+    }
+
+    /// One-line summary such as `up to date` or `never run on this machine`.
+    pub(crate) fn summary(&self) -> String {
+        if self.is_clean() {
+            "up to date".to_string()
+        } else {
+            self.drift
+                .iter()
+                .map(Drift::to_string)
+                .collect::<Vec<_>>()
+                .join("; ")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StatusReport {
+    /// One entry per tool, in run order.
+    pub tools: Vec<ToolStatus>,
+    /// Files in tool directories that no tool references.
+    pub untracked: Vec<PathBuf>,
+}
+
+impl StatusReport {
+    pub(crate) fn collect(tools: &Tools, record: &RunRecord, checks: Checks) -> Self {
+        Self {
+            tools: tools
+                .iter()
+                .map(|tool| tool_status(tools, tool, record, checks))
+                .collect(),
+            untracked: untracked_files(tools),
+        }
+    }
+
+    pub(crate) fn is_clean(&self) -> bool {
+        self.drifted_count() == 0 && self.untracked.is_empty()
+    }
+
+    pub(crate) fn drifted_count(&self) -> usize {
+        self.tools.iter().filter(|tool| !tool.is_clean()).count()
+    }
+
+    pub(crate) fn tool(&self, tool_id: &str) -> Option<&ToolStatus> {
+        self.tools.iter().find(|tool| tool.id == tool_id)
+    }
+
+    /// Report printed by `dotfiles status`.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for tool in &self.tools {
+            let label = format!("{} ({})", tool.name, tool.id);
+            if tool.is_clean() {
+                let applied = tool.last_success.map_or_else(String::new, |time| {
+                    format!(", applied {}", time.format("%Y-%m-%d %H:%M UTC"))
+                });
+                lines.push(format!("  ok     {label}{applied}"));
+            } else {
+                lines.push(format!("  drift  {label}: {}", tool.summary()));
+            }
+        }
+        if !self.untracked.is_empty() {
+            lines.push(String::new());
+            lines.push("Files not referenced by the config:".to_string());
+            for path in &self.untracked {
+                lines.push(format!("  {}", path.display()));
+            }
+        }
+        lines.push(String::new());
+        lines.push(if self.is_clean() {
+            "Everything matches the last successful run.".to_string()
+        } else {
+            format!(
+                "{} of {} tools need attention; {} untracked file(s).",
+                self.drifted_count(),
+                self.tools.len(),
+                self.untracked.len()
+            )
+        });
+        lines
+    }
+}
+
+fn tool_status(tools: &Tools, tool: &ToolItem, record: &RunRecord, checks: Checks) -> ToolStatus {
+    let mut drift = Vec::new();
+    let current = run_record::script_digest(&tools.tool_path(tool)).ok();
+    if current.is_none() {
+        drift.push(Drift::ScriptMissing);
+    }
+
+    let entry = record.get(&tool.id);
+    match entry {
+        None => drift.push(Drift::NeverRun),
+        Some(entry) => {
+            if !entry.last_run.success {
+                drift.push(Drift::LastRunFailed {
+                    reason: entry.last_run.reason.clone(),
+                });
+            }
+            if let (Some(current), Some(applied)) = (&current, &entry.last_success)
+                && applied.script_sha256.as_ref() != Some(current)
+            {
+                drift.push(Drift::ScriptChanged);
+            }
+        }
+    }
+
+    for link in links::statuses(tools, tool, record) {
+        match link.state {
+            LinkState::Edited => drift.push(Drift::TemplateEdited {
+                target: link.target,
+            }),
+            LinkState::Outdated => drift.push(Drift::TemplateOutdated {
+                target: link.target,
+            }),
+            _ => {}
+        }
+    }
+
+    if checks == Checks::Run
+        && let Some(reason) = run_check(tools, tool)
+    {
+        drift.push(Drift::CheckFailed { reason });
+    }
+
+    ToolStatus {
+        id: tool.id.clone(),
+        name: tool.name.clone(),
+        last_success: entry
+            .and_then(|entry| entry.last_success.as_ref())
+            .map(|entry| entry.finished_at),
+        drift,
+    }
+}
+
+/// Runs the tool's `Check` script and returns why it failed, if it did.
+fn run_check(tools: &Tools, tool: &ToolItem) -> Option<String> {
+    let path = tools.check_path(tool)?;
+    if !path.is_file() {
+        return Some(format!("{} not found", path.display()));
+    }
+    match Command::new(&tool.interpreter)
+        .envs(&tool.env)
+        .arg("--")
+        .arg(&path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+    {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(format!("exited with {status}")),
+        Err(error) => Some(format!("failed to start {}: {error}", tool.interpreter)),
+    }
+}
+
+/// Files under each tool directory that are neither a tool's script, its check, a `Links`
+/// source (or inside one), nor a `tool.yaml` or `.dotfilesignore`. Tools that live directly in the root are skipped,
+/// and the walk does not descend into `Links` sources or anything excluded from exports.
+fn untracked_files(tools: &Tools) -> Vec<PathBuf> {
+    let root = crate::config::expand_home_path(&tools.root);
+    let mut referenced = HashSet::new();
+    let mut linked = Vec::new();
+    let mut directories = BTreeSet::new();
+    for tool in tools.iter() {
+        let dir = tools.tool_dir(tool);
+        referenced.insert(tools.tool_path(tool));
+        referenced.extend(tools.check_path(tool));
+        referenced.insert(dir.join(TOOL_CONFIG_NAME));
+        referenced.insert(dir.join(IGNORE_FILE_NAME));
+        linked.extend(tool.links.keys().map(|source| dir.join(source)));
+        if dir != root {
+            directories.insert(dir);
+        }
+    }
+
+    // An invalid pattern is reported by `export`; here it only means fewer files are skipped.
+    let rules = ExcludeRules::new(&root, tools.exclude()).ok();
+    let mut untracked = BTreeSet::new();
+    for dir in directories {
+        let excludes = rules.as_ref().and_then(|rules| rules.for_tool(&dir).ok());
+        let entries = WalkDir::new(&dir)
+            .into_iter()
+            .filter_entry(|entry| {
+                let path = entry.path();
+                !IGNORED_NAMES
+                    .iter()
+                    .any(|name| entry.file_name() == std::ffi::OsStr::new(name))
+                    && !linked.iter().any(|source| path.starts_with(source))
+                    && !excludes.as_ref().is_some_and(|excludes| {
+                        excludes.is_excluded(path, entry.file_type().is_dir())
+                    })
+            })
+            .filter_map(Result::ok)
+            .filter(|entry| !entry.file_type().is_dir());
+        for entry in entries {
+            if !referenced.contains(entry.path()) {
+                untracked.insert(entry.into_path());
+            }
+        }
+    }
+    untracked.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::run_record::RunOutcome;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn load_tools(root: &std::path::Path, tools_yaml: &str) -> Tools {
+        let config_path = root.join("config.yaml");
+        fs::write(
+            &config_path,
+            format!(
+                "Version: 1\nSystemPreferences:\n  Root: {}\nPreferences:\n  ToolsSettings:\n{tools_yaml}",
+                root.display()
+            ),
+        )
+        .unwrap();
+        let config = Config::load_from_file(&config_path.to_string_lossy()).unwrap();
+        Tools::from_config(&config, true).unwrap().0
+    }
+
+    fn outcome(id: &str, script_sha256: Option<String>, success: bool) -> RunOutcome {
+        RunOutcome {
+            id: id.to_string(),
+            script_sha256,
+            success,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn test_collect_reports_each_kind_of_drift() {
+        let dir = tempdir().unwrap();
+        for name in ["brew", "git", "zsh", "vim"] {
+            fs::create_dir_all(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("run.sh"), "echo v1\n").unwrap();
+        }
+        fs::write(dir.path().join("zsh").join("notes.txt"), "stray\n").unwrap();
+        fs::create_dir_all(dir.path().join("vim").join("colors")).unwrap();
+        fs::write(dir.path().join("vim").join("colors").join("dark.vim"), "").unwrap();
+        fs::create_dir_all(dir.path().join("zsh").join(".git")).unwrap();
+        fs::write(dir.path().join("zsh").join(".git").join("HEAD"), "x").unwrap();
+        let tools = load_tools(
+            dir.path(),
+            "    - Name: brew\n      File: run.sh\n    - Name: git\n      File: run.sh\n    - Name: zsh\n      File: run.sh\n    - Name: vim\n      File: run.sh\n      Links:\n        colors: ~/.vim/colors\n",
+        );
+
+        let digest =
+            |name: &str| run_record::script_digest(&dir.path().join(name).join("run.sh")).ok();
+        let mut record = RunRecord::default();
+        record.record(outcome("brew", digest("brew"), true), Utc::now());
+        record.record(outcome("git", digest("git"), true), Utc::now());
+        record.record(outcome("zsh", digest("zsh"), false), Utc::now());
+        fs::write(dir.path().join("git").join("run.sh"), "echo v2\n").unwrap();
+
+        let report = StatusReport::collect(&tools, &record, Checks::Skip);
+
+        assert!(report.tool("brew").unwrap().is_clean());
+        assert_eq!(report.tool("git").unwrap().drift, [Drift::ScriptChanged]);
+        assert_eq!(
+            report.tool("zsh").unwrap().drift,
+            [Drift::LastRunFailed { reason: None }]
+        );
+        assert_eq!(report.tool("vim").unwrap().drift, [Drift::NeverRun]);
+        assert_eq!(report.untracked, [dir.path().join("zsh").join("notes.txt")]);
+        assert_eq!(report.drifted_count(), 3);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_untracked_files_skip_ignored_directories() {
+        let dir = tempdir().unwrap();
+        let tool_dir = dir.path().join("node");
+        fs::create_dir_all(tool_dir.join("node_modules/left-pad")).unwrap();
+        fs::create_dir_all(tool_dir.join("cache")).unwrap();
+        fs::write(tool_dir.join("run.sh"), "").unwrap();
+        fs::write(tool_dir.join("notes.txt"), "").unwrap();
+        fs::write(tool_dir.join("node_modules/left-pad/index.js"), "").unwrap();
+        fs::write(tool_dir.join("cache/data"), "").unwrap();
+        fs::write(dir.path().join(IGNORE_FILE_NAME), "node_modules/\n").unwrap();
+        fs::write(tool_dir.join(IGNORE_FILE_NAME), "cache/\n").unwrap();
+        let tools = load_tools(dir.path(), "    - Name: node\n      File: run.sh\n");
+
+        assert_eq!(untracked_files(&tools), [tool_dir.join("notes.txt")]);
+    }
+
+    #[test]
+    fn test_check_script_failure_is_drift() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("brew")).unwrap();
+        fs::write(dir.path().join("brew").join("run.sh"), "true\n").unwrap();
+        fs::write(dir.path().join("brew").join("check.sh"), "exit 3\n").unwrap();
+        let tools = load_tools(
+            dir.path(),
+            "    - Name: brew\n      File: run.sh\n      Check: check.sh\n      Interpreter: sh\n",
+        );
+        let mut record = RunRecord::default();
+        let digest = run_record::script_digest(&dir.path().join("brew").join("run.sh")).ok();
+        record.record(outcome("brew", digest, true), Utc::now());
+
+        let skipped = StatusReport::collect(&tools, &record, Checks::Skip);
+        assert!(skipped.is_clean());
+
+        let checked = StatusReport::collect(&tools, &record, Checks::Run);
+        let drift = &checked.tool("brew").unwrap().drift;
+        assert!(matches!(drift.as_slice(), [Drift::CheckFailed { .. }]));
+        assert!(checked.lines()[0].starts_with("  drift  brew (brew): check failed"));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::config::{self, Config};
-use std::collections::{HashMap, HashSet, VecDeque};
+use crate::variables::Variables;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::fs;
 use std::path::PathBuf;
@@ -7,6 +8,12 @@ use std::path::PathBuf;
 #[derive(Clone)]
 pub(crate) struct Tools {
     pub root: String,
+    /// Variables resolved for this machine, used to render `.tmpl` links.
+    variables: Variables,
+    /// `SystemPreferences.Secrets` globs.
+    secrets: Vec<String>,
+    /// `SystemPreferences.Exclude` patterns.
+    exclude: Vec<String>,
     ordered_ids: Vec<String>,
     items: HashMap<String, ToolItem>,
 }
@@ -15,6 +22,9 @@ impl Default for Tools {
     fn default() -> Self {
         Self {
             root: "~/.dotfiles".to_string(),
+            variables: Variables::default(),
+            secrets: Vec::new(),
+            exclude: Vec::new(),
             ordered_ids: Vec::new(),
             items: HashMap::new(),
         }
@@ -27,13 +37,31 @@ pub(crate) struct ToolItem {
     pub name: String,
     pub root: String,
     pub file: String,
+    pub interpreter: String,
+    /// Environment variables set when running the script, with variables interpolated.
+    pub env: BTreeMap<String, String>,
     pub dependencies: Vec<String>,
+    /// Order-only dependencies; only ids of configured tools remain after loading.
+    pub optional_dependencies: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub tags: Vec<String>,
+    /// Script next to `file` that exits 0 while the machine still matches it.
+    pub check: Option<String>,
+    /// Files in the tool directory mapped to the (unexpanded) paths they are symlinked to.
+    pub links: BTreeMap<String, String>,
 }
 
 impl ToolItem {
     pub(crate) fn display_name(&self) -> String {
         format!("{} ({})", self.name, self.id)
     }
+
+    /// Every tool that has to finish before this one starts, hard or optional.
+    pub(crate) fn ordering_dependencies(&self) -> impl Iterator<Item = &String> {
+        self.dependencies
+            .iter()
+            .chain(self.optional_dependencies.iter())
+    }
 }
 
 #[derive(Debug)]
@@ -45,6 +73,12 @@ pub enum ToolError {
         dependency_id: String,
     },
     SelfDependency(String),
+    Conflict {
+        tool_id: String,
+        conflict_id: String,
+    },
+    UnknownTool(String),
+    UnknownTag(String),
     CycleDetected,
 }
 
@@ -62,6 +96,9 @@ impl Tools {
     pub(crate) fn new_empty() -> Self {
         Self {
             root: "~/.dotfiles".to_string(),
+            variables: Variables::default(),
+            secrets: Vec::new(),
+            exclude: Vec::new(),
             ordered_ids: Vec::new(),
             items: HashMap::new(),
         }
@@ -79,6 +116,13 @@ impl Tools {
                 name: "Brew".to_string(),
                 root: "brew".to_string(),
                 file: "brew-settings.zsh".to_string(),
+                interpreter: "zsh".to_string(),
+                env: BTreeMap::new(),
+                optional_dependencies: vec![],
+                conflicts: vec![],
+                tags: vec![],
+                check: None,
+                links: BTreeMap::new(),
                 dependencies: vec![],
             },
         );
@@ -91,6 +135,13 @@ impl Tools {
                 name: "Gcloud".to_string(),
                 root: "gcloud".to_string(),
                 file: "gcloud-settings.zsh".to_string(),
+                interpreter: "zsh".to_string(),
+                env: BTreeMap::new(),
+                optional_dependencies: vec![],
+                conflicts: vec![],
+                tags: vec![],
+                check: None,
+                links: BTreeMap::new(),
                 dependencies: vec!["brew".to_string()],
             },
         );
@@ -103,6 +154,13 @@ impl Tools {
                 name: "Helm".to_string(),
                 root: "helm".to_string(),
                 file: "helm-settings.zsh".to_string(),
+                interpreter: "zsh".to_string(),
+                env: BTreeMap::new(),
+                optional_dependencies: vec![],
+                conflicts: vec![],
+                tags: vec![],
+                check: None,
+                links: BTreeMap::new(),
                 dependencies: vec!["brew".to_string()],
             },
         );
@@ -115,6 +173,13 @@ impl Tools {
                 name: "Krew".to_string(),
                 root: "krew".to_string(),
                 file: "krew-settings.zsh".to_string(),
+                interpreter: "zsh".to_string(),
+                env: BTreeMap::new(),
+                optional_dependencies: vec![],
+                conflicts: vec![],
+                tags: vec![],
+                check: None,
+                links: BTreeMap::new(),
                 dependencies: vec!["brew".to_string()],
             },
         );
@@ -127,6 +192,16 @@ impl Tools {
                 name: "Rust".to_string(),
                 root: "rust".to_string(),
                 file: "rust-settings.zsh".to_string(),
+                interpreter: "zsh".to_string(),
+                env: BTreeMap::new(),
+                optional_dependencies: vec![],
+                conflicts: vec![],
+                tags: vec![],
+                check: None,
+                links: BTreeMap::from([(
+                    "cargo-config.toml.tmpl".to_string(),
+                    "~/.cargo/config.toml".to_string(),
+                )]),
                 dependencies: vec!["brew".to_string()],
             },
         );
@@ -139,6 +214,13 @@ impl Tools {
                 name: "Zsh".to_string(),
                 root: "zsh".to_string(),
                 file: "zsh-settings.zsh".to_string(),
+                interpreter: "zsh".to_string(),
+                env: BTreeMap::new(),
+                optional_dependencies: vec![],
+                conflicts: vec![],
+                tags: vec![],
+                check: None,
+                links: BTreeMap::new(),
                 dependencies: vec![
                     "gcloud".to_string(),
                     "helm".to_string(),
@@ -158,8 +240,17 @@ impl Tools {
             "zsh".to_string(),
         ];
 
+        let variables = Variables::resolve(
+            &BTreeMap::from([("cargo_jobs".to_string(), "8".to_string())]),
+            None,
+        )
+        .expect("test variables resolve");
+
         Self {
             root: "tests/assets/dotfiles".to_string(),
+            variables,
+            secrets: Vec::new(),
+            exclude: Vec::new(),
             ordered_ids,
             items,
         }
@@ -167,16 +258,29 @@ impl Tools {
 
     fn load(strict: bool) -> Result<(Self, Vec<String>), ToolError> {
         let config = Self::load_config()?;
+        Self::from_config(&config, strict)
+    }
+
+    /// Builds the tool graph from an already loaded configuration.
+    pub(crate) fn from_config(
+        config: &Config,
+        strict: bool,
+    ) -> Result<(Self, Vec<String>), ToolError> {
         let root = config.root().to_string();
-        let mut items = Self::build_tool_items(&config)?;
-        let (dependency_map, warnings) = Self::sanitize_dependencies(&mut items, strict);
+        let mut items = Self::build_tool_items(config)?;
+        Self::drop_absent_optional_dependencies(&mut items);
+        let (dependency_map, mut warnings) = Self::sanitize_dependencies(&mut items, strict);
 
         Self::validate_dependencies(&items, &dependency_map)?;
+        warnings.extend(Self::check_conflicts(&items, strict)?);
 
         let ordered_ids = Self::topological_order(&items)?;
         Ok((
             Self {
                 root,
+                variables: config.variables().with_machine_facts(),
+                secrets: config.secrets().to_vec(),
+                exclude: config.exclude().to_vec(),
                 ordered_ids,
                 items,
             },
@@ -190,13 +294,17 @@ impl Tools {
 
     fn build_tool_items(config: &Config) -> Result<HashMap<String, ToolItem>, ToolError> {
         let mut items = HashMap::new();
-        let mut name_counts: HashMap<String, usize> = HashMap::new();
+        let ids = resolve_tool_ids(config.tools());
 
-        for tool in config.tools() {
+        for (tool, id) in config.tools().iter().zip(ids) {
             let dependencies = tool.dependencies();
-            let id = generate_tool_id(&mut name_counts, &items, tool);
+            let optional_dependencies = tool.optional_dependencies();
 
-            if dependencies.iter().any(|dependency| dependency == &id) {
+            if dependencies
+                .iter()
+                .chain(&optional_dependencies)
+                .any(|dependency| dependency == &id)
+            {
                 return Err(ToolError::SelfDependency(id));
             }
             if items.contains_key(&id) {
@@ -210,7 +318,14 @@ impl Tools {
                     name: tool.name(),
                     root: tool.root_name(),
                     file: tool.file_name(),
+                    interpreter: tool.interpreter(),
+                    env: tool.env.clone(),
                     dependencies,
+                    optional_dependencies,
+                    conflicts: tool.conflicts(),
+                    tags: tool.tags(),
+                    check: tool.check_file(),
+                    links: tool.links(),
                 },
             );
         }
@@ -218,6 +333,49 @@ impl Tools {
         Ok(items)
     }
 
+    /// Optional dependencies only order tools that are configured; the rest are dropped quietly.
+    fn drop_absent_optional_dependencies(items: &mut HashMap<String, ToolItem>) {
+        let configured_ids: HashSet<String> = items.keys().cloned().collect();
+        for item in items.values_mut() {
+            let hard = item.dependencies.clone();
+            item.optional_dependencies
+                .retain(|id| configured_ids.contains(id) && !hard.contains(id));
+        }
+    }
+
+    /// Reports pairs of configured tools where one lists the other in `Conflicts`.
+    /// Fatal in strict mode, a warning otherwise.
+    fn check_conflicts(
+        items: &HashMap<String, ToolItem>,
+        strict: bool,
+    ) -> Result<Vec<String>, ToolError> {
+        let mut pairs = BTreeSet::new();
+        for item in items.values() {
+            for conflict_id in &item.conflicts {
+                if conflict_id != &item.id && items.contains_key(conflict_id) {
+                    pairs.insert((item.id.clone(), conflict_id.clone()));
+                }
+            }
+        }
+
+        let mut warnings = Vec::new();
+        for (tool_id, conflict_id) in pairs {
+            // Both sides may list each other; report the pair once.
+            if tool_id > conflict_id && items[&conflict_id].conflicts.contains(&tool_id) {
+                continue;
+            }
+            let error = ToolError::Conflict {
+                tool_id,
+                conflict_id,
+            };
+            if strict {
+                return Err(error);
+            }
+            warnings.push(error.to_string());
+        }
+        Ok(warnings)
+    }
+
     fn sanitize_dependencies(
         items: &mut HashMap<String, ToolItem>,
         strict: bool,
@@ -282,7 +440,7 @@ impl Tools {
         for (id, item) in items.iter() {
             let id_str = id.as_str();
             in_degree.entry(id_str).or_insert(0);
-            for dependency in &item.dependencies {
+            for dependency in item.ordering_dependencies() {
                 let dependency_item =
                     items
                         .get(dependency)
@@ -357,7 +515,10 @@ impl Tools {
                 .ordered_ids
                 .iter()
                 .filter_map(|id| remaining.get(id))
-                .filter(|tool| tool.dependencies.iter().all(|dep| processed.contains(dep)))
+                .filter(|tool| {
+                    tool.ordering_dependencies()
+                        .all(|dep| processed.contains(dep))
+                })
                 .cloned()
                 .collect();
 
@@ -388,7 +549,7 @@ impl Tools {
             .ordered_ids
             .iter()
             .filter_map(|id| self.items.get(id))
-            .filter(|tool| tool.dependencies.is_empty())
+            .filter(|tool| tool.ordering_dependencies().next().is_none())
             .collect();
 
         if roots.is_empty() {
@@ -423,6 +584,12 @@ impl Tools {
             self.append_dependents_tree(tool, "", highlight_id, &mut lines, &mut visited);
         }
 
+        let conflict_lines = self.conflict_lines();
+        if !conflict_lines.is_empty() {
+            Self::push_blank_line(&mut lines);
+            lines.extend(conflict_lines);
+        }
+
         if matches!(lines.last(), Some(last) if last.is_empty()) {
             lines.pop();
         }
@@ -430,14 +597,160 @@ impl Tools {
         lines
     }
 
+    /// One `x` line per declared conflict; ids that are not configured are shown as-is.
+    fn conflict_lines(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut lines = Vec::new();
+        for tool in self.iter() {
+            for conflict_id in &tool.conflicts {
+                let pair = if tool.id < *conflict_id {
+                    (tool.id.clone(), conflict_id.clone())
+                } else {
+                    (conflict_id.clone(), tool.id.clone())
+                };
+                if !seen.insert(pair) {
+                    continue;
+                }
+                let other = self
+                    .items
+                    .get(conflict_id)
+                    .map_or_else(|| conflict_id.clone(), ToolItem::display_name);
+                lines.push(format!("x {} conflicts with {other}", tool.display_name()));
+            }
+        }
+        lines
+    }
+
+    /// Ids of every tool `tool_id` waits for, directly or transitively (hard and optional).
+    pub(crate) fn ancestors(&self, tool_id: &str) -> BTreeSet<String> {
+        self.walk(tool_id, |tool| {
+            tool.ordering_dependencies().cloned().collect::<Vec<_>>()
+        })
+    }
+
+    /// Ids of every tool that waits for `tool_id`, directly or transitively (hard and optional).
+    pub(crate) fn descendants(&self, tool_id: &str) -> BTreeSet<String> {
+        self.walk(tool_id, |tool| {
+            self.items
+                .values()
+                .filter(|candidate| candidate.ordering_dependencies().any(|id| id == &tool.id))
+                .map(|candidate| candidate.id.clone())
+                .collect()
+        })
+    }
+
+    /// Shortest chain of dependencies leading from `from` to `to`, both included, or `None`
+    /// when `from` does not wait for `to`. Optional dependencies count as edges.
+    pub(crate) fn dependency_path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([self.items.get(from)?]);
+        while let Some(tool) = queue.pop_front() {
+            if tool.id == to {
+                let mut path = vec![to.to_string()];
+                let mut current = to;
+                while let Some(parent) = previous.get(current) {
+                    path.push(parent.to_string());
+                    current = parent;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let mut next = tool.ordering_dependencies().collect::<Vec<_>>();
+            next.sort();
+            for dependency in next {
+                if dependency != from
+                    && !previous.contains_key(dependency.as_str())
+                    && let Some(item) = self.items.get(dependency)
+                {
+                    previous.insert(&item.id, &tool.id);
+                    queue.push_back(item);
+                }
+            }
+        }
+        None
+    }
+
+    /// Tools that must re-run when `tool_id`'s script changes: the tool itself followed by
+    /// every transitive dependent, in run order.
+    pub(crate) fn impact(&self, tool_id: &str) -> Vec<&ToolItem> {
+        let mut affected = self.descendants(tool_id);
+        affected.insert(tool_id.to_string());
+        self.in_run_order(&affected)
+    }
+
+    /// Ids of the tools named in `ids` plus those carrying one of `tags`, and with
+    /// `with_dependencies` everything they wait for.
+    pub(crate) fn select(
+        &self,
+        ids: &[String],
+        tags: &[String],
+        with_dependencies: bool,
+    ) -> Result<BTreeSet<String>, ToolError> {
+        let mut selected = BTreeSet::new();
+        for id in ids {
+            if !self.items.contains_key(id) {
+                return Err(ToolError::UnknownTool(id.clone()));
+            }
+            selected.insert(id.clone());
+        }
+        for tag in tags {
+            let tagged = self
+                .items
+                .values()
+                .filter(|tool| tool.tags.contains(tag))
+                .map(|tool| tool.id.clone())
+                .collect::<Vec<_>>();
+            if tagged.is_empty() {
+                return Err(ToolError::UnknownTag(tag.clone()));
+            }
+            selected.extend(tagged);
+        }
+        if with_dependencies {
+            let dependencies = selected
+                .iter()
+                .flat_map(|id| self.ancestors(id))
+                .collect::<Vec<_>>();
+            selected.extend(dependencies);
+        }
+        Ok(selected)
+    }
+
+    /// The tools named by `ids`, ordered the way they run.
+    pub(crate) fn in_run_order(&self, ids: &BTreeSet<String>) -> Vec<&ToolItem> {
+        self.ordered_ids
+            .iter()
+            .filter(|id| ids.contains(*id))
+            .filter_map(|id| self.items.get(id))
+            .collect()
+    }
+
+    fn walk(&self, tool_id: &str, next: impl Fn(&ToolItem) -> Vec<String>) -> BTreeSet<String> {
+        let mut found = BTreeSet::new();
+        let mut queue = VecDeque::from([tool_id.to_string()]);
+        while let Some(id) = queue.pop_front() {
+            let Some(tool) = self.items.get(&id) else {
+                continue;
+            };
+            for neighbor in next(tool) {
+                if neighbor != tool_id && found.insert(neighbor.clone()) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        found
+    }
+
+    pub(crate) fn get(&self, tool_id: &str) -> Option<&ToolItem> {
+        self.items.get(tool_id)
+    }
+
     pub(crate) fn execution_stage_index(&self, tool_id: &str) -> Option<usize> {
         let mut stage_map: HashMap<String, usize> = HashMap::new();
 
         for id in &self.ordered_ids {
             if let Some(tool) = self.items.get(id) {
                 let max_dependency_stage = tool
-                    .dependencies
-                    .iter()
+                    .ordering_dependencies()
                     .filter_map(|dependency| stage_map.get(dependency))
                     .max()
                     .copied();
@@ -451,16 +764,36 @@ impl Tools {
     }
 
     pub(crate) fn tool_path(&self, tool: &ToolItem) -> PathBuf {
-        let mut root = config::expand_home_path(&self.root);
-        root.push(&tool.root);
-        root.push(&tool.file);
-        root
+        self.tool_dir(tool).join(&tool.file)
+    }
+
+    /// Directory under the root that holds the tool's script.
+    pub(crate) fn tool_dir(&self, tool: &ToolItem) -> PathBuf {
+        config::expand_home_path(&self.root).join(&tool.root)
+    }
+
+    pub(crate) fn check_path(&self, tool: &ToolItem) -> Option<PathBuf> {
+        tool.check
+            .as_ref()
+            .map(|check| self.tool_dir(tool).join(check))
     }
 
     pub(crate) fn root(&self) -> &str {
         &self.root
     }
 
+    pub(crate) fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    pub(crate) fn secrets(&self) -> &[String] {
+        &self.secrets
+    }
+
+    pub(crate) fn exclude(&self) -> &[String] {
+        &self.exclude
+    }
+
     fn format_marked_tool(&self, tool: &ToolItem, highlight_id: Option<&str>) -> String {
         format!(
             "{} {}",
@@ -494,19 +827,36 @@ impl Tools {
         let mut dependents = self
             .items
             .values()
-            .filter(|candidate| candidate.dependencies.iter().any(|dep| dep == &tool.id))
+            .filter_map(|candidate| {
+                if candidate.dependencies.contains(&tool.id) {
+                    Some((candidate, false))
+                } else if candidate.optional_dependencies.contains(&tool.id) {
+                    Some((candidate, true))
+                } else {
+                    None
+                }
+            })
             .collect::<Vec<_>>();
-        dependents.sort_by(|a, b| a.name.cmp(&b.name));
+        dependents.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
 
         let last_index = dependents.len().saturating_sub(1);
-        for (index, dependent) in dependents.into_iter().enumerate() {
-            let connector = if index == last_index { "`--" } else { "|--" };
+        for (index, (dependent, optional)) in dependents.into_iter().enumerate() {
+            // Optional (order-only) edges are drawn with dots instead of dashes.
+            let connector = match (index == last_index, optional) {
+                (true, false) => "`--",
+                (false, false) => "|--",
+                (true, true) => "`..",
+                (false, true) => "|..",
+            };
             let newly_visited = visited.insert(dependent.id.clone());
             let mut line = format!(
                 "{prefix}{connector} {} {}",
                 Self::marker_for(highlight_id, &dependent.id),
                 dependent.display_name()
             );
+            if optional {
+                line.push_str(" (optional)");
+            }
             if !newly_visited {
                 line.push_str(" (repeat)");
                 lines.push(line);
@@ -543,6 +893,15 @@ impl fmt::Display for ToolError {
             ToolError::SelfDependency(id) => {
                 write!(f, "Tool '{id}' cannot depend on itself")
             }
+            ToolError::Conflict {
+                tool_id,
+                conflict_id,
+            } => write!(
+                f,
+                "Tool '{tool_id}' conflicts with configured tool '{conflict_id}'"
+            ),
+            ToolError::UnknownTool(id) => write!(f, "Unknown tool '{id}'"),
+            ToolError::UnknownTag(tag) => write!(f, "No tool is tagged '{tag}'"),
             ToolError::CycleDetected => write!(f, "Cycle detected in tool dependencies"),
         }
     }
@@ -550,18 +909,31 @@ impl fmt::Display for ToolError {
 
 impl std::error::Error for ToolError {}
 
+/// Resolves the id of every configured tool, in order, the same way [`Tools`] does.
+/// Entries that repeat an explicit id keep it so the duplicate can be reported.
+pub(crate) fn resolve_tool_ids(tools: &[config::Tool]) -> Vec<String> {
+    let mut name_counts: HashMap<String, usize> = HashMap::new();
+    let mut taken: HashSet<String> = HashSet::new();
+
+    tools
+        .iter()
+        .map(|tool| {
+            let id = generate_tool_id(&mut name_counts, &taken, tool);
+            taken.insert(id.clone());
+            id
+        })
+        .collect()
+}
+
 fn generate_tool_id(
     name_counts: &mut HashMap<String, usize>,
-    items: &HashMap<String, ToolItem>,
+    items: &HashSet<String>,
     tool: &crate::config::Tool,
 ) -> String {
     if let Some(id) = tool.identifier() {
         id
     } else {
-        let mut base = tool.name().to_lowercase().replace(' ', "-");
-        if base.is_empty() {
-            base = "tool".to_string();
-        }
+        let base = tool.base_id();
 
         let count = name_counts.entry(base.clone()).or_insert(0);
         let mut candidate = if *count == 0 {
@@ -570,7 +942,7 @@ fn generate_tool_id(
             format!("{base}-{}", *count)
         };
 
-        while items.contains_key(&candidate) {
+        while items.contains(&candidate) {
             *count += 1;
             candidate = format!("{base}-{}", *count);
         }
@@ -592,6 +964,7 @@ mod tests {
             root: None,
             file: None,
             dependencies: dependencies.into_iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
         }
     }
 
@@ -601,6 +974,13 @@ mod tests {
             name: id.to_string(),
             root: id.to_string(),
             file: format!("{id}.sh"),
+            interpreter: "zsh".to_string(),
+            env: BTreeMap::new(),
+            optional_dependencies: vec![],
+            conflicts: vec![],
+            tags: vec![],
+            check: None,
+            links: BTreeMap::new(),
             dependencies: dependencies.into_iter().map(|s| s.to_string()).collect(),
         }
     }
@@ -608,23 +988,35 @@ mod tests {
     #[test]
     fn test_generate_tool_id() {
         let mut name_counts = HashMap::new();
-        let mut items = HashMap::new();
+        let mut items = HashSet::new();
 
         let tool1 = create_tool("Tool A", Some("tool-a"), vec![]);
         let id1 = generate_tool_id(&mut name_counts, &items, &tool1);
         assert_eq!(id1, "tool-a");
-        items.insert(id1.clone(), create_tool_item(&id1, vec![]));
+        items.insert(id1.clone());
 
         let tool2 = create_tool("Tool B", None, vec![]);
         let id2 = generate_tool_id(&mut name_counts, &items, &tool2);
         assert_eq!(id2, "tool-b");
-        items.insert(id2.clone(), create_tool_item(&id2, vec![]));
+        items.insert(id2.clone());
 
         let tool3 = create_tool("Tool B", None, vec![]);
         let id3 = generate_tool_id(&mut name_counts, &items, &tool3);
         assert_eq!(id3, "tool-b-1");
     }
 
+    #[test]
+    fn test_resolve_tool_ids() {
+        let tools = vec![
+            create_tool("Tool B", None, vec![]),
+            create_tool("Tool B", None, vec![]),
+            create_tool("Other", Some("tool-b-1"), vec![]),
+        ];
+
+        let ids = resolve_tool_ids(&tools);
+        assert_eq!(ids, vec!["tool-b", "tool-b-1", "tool-b-1"]);
+    }
+
     #[test]
     fn test_topological_order_valid() {
         let mut items = HashMap::new();
@@ -655,6 +1047,9 @@ mod tests {
 
         let tools = Tools {
             root: "/".to_string(),
+            variables: Variables::default(),
+            secrets: Vec::new(),
+            exclude: Vec::new(),
             ordered_ids: vec![
                 "a".to_string(),
                 "c".to_string(),
@@ -679,7 +1074,7 @@ mod tests {
     fn test_self_dependency_error() {
         let tool = create_tool("SelfDep", Some("self-dep"), vec!["self-dep"]);
         let mut name_counts = HashMap::new();
-        let items = HashMap::new();
+        let items = HashSet::new();
 
         // Directly test generate_tool_id and self-dependency check logic
         let id = generate_tool_id(&mut name_counts, &items, &tool);
@@ -723,6 +1118,175 @@ mod tests {
         assert!(warnings[0].contains("missing"));
     }
 
+    #[test]
+    fn test_optional_dependencies_order_only_when_present() {
+        let mut items = HashMap::new();
+        let mut app = create_tool_item("app", vec![]);
+        app.optional_dependencies = vec!["base".to_string(), "absent".to_string()];
+        items.insert("app".to_string(), app);
+        items.insert("base".to_string(), create_tool_item("base", vec![]));
+
+        Tools::drop_absent_optional_dependencies(&mut items);
+        let (_, warnings) = Tools::sanitize_dependencies(&mut items, true);
+        assert!(warnings.is_empty());
+        assert_eq!(items["app"].optional_dependencies, vec!["base".to_string()]);
+
+        let ordered_ids = Tools::topological_order(&items).unwrap();
+        assert_eq!(ordered_ids, vec!["base".to_string(), "app".to_string()]);
+        let tools = Tools {
+            root: "/".to_string(),
+            variables: Variables::default(),
+            secrets: Vec::new(),
+            exclude: Vec::new(),
+            ordered_ids,
+            items,
+        };
+        assert_eq!(tools.execution_stage_index("app"), Some(1));
+        assert_eq!(tools.execution_stages().len(), 2);
+        let lines = tools.dependency_map_lines(None);
+        assert!(lines.contains(&"`.. - app (app) (optional)".to_string()));
+    }
+
+    #[test]
+    fn test_check_conflicts_strict_and_relaxed() {
+        let mut items = HashMap::new();
+        let mut docker = create_tool_item("docker", vec![]);
+        docker.conflicts = vec!["podman".to_string(), "colima".to_string()];
+        let mut podman = create_tool_item("podman", vec![]);
+        podman.conflicts = vec!["docker".to_string()];
+        items.insert("docker".to_string(), docker);
+        items.insert("podman".to_string(), podman);
+
+        assert!(matches!(
+            Tools::check_conflicts(&items, true),
+            Err(ToolError::Conflict { .. })
+        ));
+        let warnings = Tools::check_conflicts(&items, false).unwrap();
+        assert_eq!(
+            warnings,
+            vec!["Tool 'docker' conflicts with configured tool 'podman'".to_string()]
+        );
+
+        let tools = Tools {
+            root: "/".to_string(),
+            variables: Variables::default(),
+            secrets: Vec::new(),
+            exclude: Vec::new(),
+            ordered_ids: vec!["docker".to_string(), "podman".to_string()],
+            items,
+        };
+        let lines = tools.dependency_map_lines(None);
+        assert!(lines.contains(&"x docker (docker) conflicts with podman (podman)".to_string()));
+        assert!(lines.contains(&"x docker (docker) conflicts with colima".to_string()));
+        assert_eq!(
+            lines.iter().filter(|line| line.starts_with("x ")).count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_ancestors_and_descendants() {
+        let tools = Tools::new_with_test_data();
+
+        let ancestors = tools.ancestors("zsh");
+        assert_eq!(ancestors.len(), 5);
+        assert!(ancestors.contains("brew"));
+        assert_eq!(
+            tools.descendants("gcloud"),
+            BTreeSet::from(["zsh".to_string()])
+        );
+        assert_eq!(tools.descendants("brew").len(), 5);
+        assert!(tools.ancestors("brew").is_empty());
+        assert!(tools.ancestors("missing").is_empty());
+    }
+
+    #[test]
+    fn test_select_by_id_tag_and_dependencies() {
+        let mut tools = Tools::new_with_test_data();
+        tools.items.get_mut("helm").unwrap().tags = vec!["kubernetes".to_string()];
+        tools.items.get_mut("krew").unwrap().tags = vec!["kubernetes".to_string()];
+
+        assert_eq!(
+            tools.select(&["zsh".to_string()], &[], false).unwrap(),
+            BTreeSet::from(["zsh".to_string()])
+        );
+        assert_eq!(
+            tools.select(&["zsh".to_string()], &[], true).unwrap().len(),
+            6
+        );
+        let kubernetes = tools
+            .select(&[], &["kubernetes".to_string()], true)
+            .unwrap();
+        assert!(kubernetes.contains("helm") && kubernetes.contains("krew"));
+        assert!(kubernetes.contains("brew"));
+        assert!(!kubernetes.contains("zsh"));
+        assert!(matches!(
+            tools.select(&["nope".to_string()], &[], false),
+            Err(ToolError::UnknownTool(_))
+        ));
+        assert!(matches!(
+            tools.select(&[], &["nope".to_string()], false),
+            Err(ToolError::UnknownTag(_))
+        ));
+    }
+
+    #[test]
+    fn test_dependency_path_and_impact() {
+        let tools = Tools::new_with_test_data();
+
+        assert_eq!(
+            tools.dependency_path("zsh", "brew").unwrap().len(),
+            3,
+            "zsh waits for brew through one intermediate tool"
+        );
+        assert_eq!(
+            tools.dependency_path("gcloud", "brew"),
+            Some(vec!["gcloud".to_string(), "brew".to_string()])
+        );
+
+        let impact = tools
+            .impact("gcloud")
+            .iter()
+            .map(|tool| tool.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(impact, ["gcloud", "zsh"]);
+        assert_eq!(tools.impact("brew").len(), 6);
+        assert_eq!(tools.impact("brew")[0].id, "brew");
+    }
+
+    #[test]
+    fn test_dependency_path_without_a_path() {
+        let tools = Tools::new_with_test_data();
+
+        // Dependencies only lead one way, and siblings do not wait for each other.
+        assert_eq!(tools.dependency_path("brew", "zsh"), None);
+        assert_eq!(tools.dependency_path("gcloud", "helm"), None);
+    }
+
+    #[test]
+    fn test_dependency_path_to_itself() {
+        let tools = Tools::new_with_test_data();
+
+        assert_eq!(
+            tools.dependency_path("brew", "brew"),
+            Some(vec!["brew".to_string()])
+        );
+        assert_eq!(
+            tools.dependency_path("zsh", "zsh"),
+            Some(vec!["zsh".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_dependency_path_and_impact_with_unknown_ids() {
+        let tools = Tools::new_with_test_data();
+
+        assert_eq!(tools.dependency_path("nope", "brew"), None);
+        assert_eq!(tools.dependency_path("zsh", "nope"), None);
+        assert_eq!(tools.dependency_path("nope", "nope"), None);
+        assert!(tools.impact("nope").is_empty());
+    }
+
     #[test]
     fn test_execution_stage_index() {
         let mut items = HashMap::new();
@@ -732,6 +1296,9 @@ mod tests {
 
         let tools = Tools {
             root: "/".to_string(),
+            variables: Variables::default(),
+            secrets: Vec::new(),
+            exclude: Vec::new(),
             ordered_ids: vec!["a".to_string(), "b".to_string(), "c".to_string()],
             items,
         };
@@ -757,6 +1324,9 @@ mod tests {
 
         let tools = Tools {
             root: "/".to_string(),
+            variables: Variables::default(),
+            secrets: Vec::new(),
+            exclude: Vec::new(),
             ordered_ids: vec!["a".to_string(), "b".to_string(), "c".to_string()],
             items,
         };
@@ -775,6 +1345,9 @@ mod tests {
 
         let tools = Tools {
             root: "/".to_string(),
+            variables: Variables::default(),
+            secrets: Vec::new(),
+            exclude: Vec::new(),
             ordered_ids: vec!["a".to_string(), "b".to_string()],
             items,
         };
@@ -793,6 +1366,9 @@ mod tests {
 
         let tools = Tools {
             root: "/".to_string(),
+            variables: Variables::default(),
+            secrets: Vec::new(),
+            exclude: Vec::new(),
             ordered_ids: vec!["a".to_string(), "b".to_string()],
             items,
         };
@@ -811,6 +1387,9 @@ mod tests {
 
         let tools = Tools {
             root: "/".to_string(),
+            variables: Variables::default(),
+            secrets: Vec::new(),
+            exclude: Vec::new(),
             ordered_ids: vec!["a".to_string(), "b".to_string()],
             items,
         };
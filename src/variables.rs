@@ -0,0 +1,247 @@
+//! Config-level `Variables`, per-profile overrides, and `{{ name }}` interpolation.
+
+use crate::expand::{self, Undefined};
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use std::collections::BTreeMap;
+use std::env;
+
+/// Environment variable that selects a profile when `--profile` is not given.
+pub(crate) const PROFILE_ENV_VAR: &str = "DOTFILES_PROFILE";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Variable {
+    pub value: String,
+    /// Profile that overrode the base value, if any.
+    pub profile: Option<String>,
+}
+
+/// Variables resolved for one machine: the base set merged with the active profile.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Variables {
+    profile: Option<String>,
+    values: BTreeMap<String, Variable>,
+}
+
+impl Variables {
+    /// Merges `base` with the overrides of `profile` and expands environment variables in
+    /// every value. Undefined environment variables are an error.
+    pub(crate) fn resolve(
+        base: &BTreeMap<String, String>,
+        overrides: Option<(&str, &BTreeMap<String, String>)>,
+    ) -> Result<Self> {
+        let mut values = BTreeMap::new();
+        for (name, value) in base {
+            values.insert(name.clone(), resolve_value(name, value, None)?);
+        }
+        if let Some((profile, overrides)) = overrides {
+            for (name, value) in overrides {
+                values.insert(name.clone(), resolve_value(name, value, Some(profile))?);
+            }
+        }
+        Ok(Self {
+            profile: overrides.map(|(profile, _)| profile.to_string()),
+            values,
+        })
+    }
+
+    pub(crate) fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Adds the `machine.hostname`, `machine.os`, `machine.arch`, and `machine.profile` facts
+    /// available to templates. Variables of the same name are left as configured.
+    pub(crate) fn with_machine_facts(&self) -> Self {
+        let facts = [
+            ("machine.hostname", short_hostname().unwrap_or_default()),
+            ("machine.os", env::consts::OS.to_string()),
+            ("machine.arch", env::consts::ARCH.to_string()),
+            ("machine.profile", self.profile.clone().unwrap_or_default()),
+        ];
+        let mut variables = self.clone();
+        for (name, value) in facts {
+            variables
+                .values
+                .entry(name.to_string())
+                .or_insert(Variable {
+                    value,
+                    profile: None,
+                });
+        }
+        variables
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&String, &Variable)> {
+        self.values.iter()
+    }
+
+    /// Replaces every `{{ name }}` in `input` with the variable's value.
+    pub(crate) fn interpolate(&self, input: &str) -> Result<String> {
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input;
+        while let Some(start) = rest.find("{{") {
+            output.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let end = after
+                .find("}}")
+                .ok_or_else(|| eyre!("Missing '}}}}' in '{input}'"))?;
+            let name = after[..end].trim();
+            let variable = self
+                .values
+                .get(name)
+                .ok_or_else(|| eyre!("Unknown variable '{name}' in '{input}'"))?;
+            output.push_str(&variable.value);
+            rest = &after[end + 2..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+}
+
+fn resolve_value(name: &str, value: &str, profile: Option<&str>) -> Result<Variable> {
+    let value = expand::expand(value, Undefined::Error)
+        .map_err(|error| eyre!("Variable '{name}': {error}"))?;
+    Ok(Variable {
+        value,
+        profile: profile.map(str::to_string),
+    })
+}
+
+/// Picks the active profile: `explicit`, then `$DOTFILES_PROFILE`, then a profile named after
+/// this machine's short hostname. A profile requested by name must exist.
+pub(crate) fn select_profile<'a>(
+    explicit: Option<&str>,
+    mut available: impl Iterator<Item = &'a String>,
+) -> Result<Option<String>> {
+    let requested = explicit.map(str::to_string).or_else(|| {
+        env::var(PROFILE_ENV_VAR)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+    });
+    if let Some(requested) = requested {
+        return if available.any(|name| *name == requested) {
+            Ok(Some(requested))
+        } else {
+            Err(eyre!("Profile '{requested}' is not defined in Profiles"))
+        };
+    }
+
+    Ok(short_hostname().filter(|host| available.any(|name| name == host)))
+}
+
+/// Hostname up to the first `.`, e.g. `laptop` for `laptop.example.com`.
+pub(crate) fn short_hostname() -> Option<String> {
+    let hostname = hostname()?;
+    let short = hostname.split('.').next().unwrap_or_default();
+    (!short.is_empty()).then(|| short.to_string())
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let hostname = nix::unistd::gethostname().ok()?;
+    Some(hostname.to_string_lossy().into_owned())
+}
+
+/// Without `gethostname`, use what the environment provides (`COMPUTERNAME` on Windows).
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    ["COMPUTERNAME", "HOSTNAME"]
+        .into_iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.trim().is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_applies_profile_overrides() {
+        let base = map(&[("email", "me@home"), ("project", "personal")]);
+        let work = map(&[("email", "me@work")]);
+
+        let variables = Variables::resolve(&base, Some(("work", &work))).unwrap();
+
+        assert_eq!(variables.profile(), Some("work"));
+        let resolved = variables.iter().collect::<Vec<_>>();
+        assert_eq!(resolved[0].0, "email");
+        assert_eq!(resolved[0].1.value, "me@work");
+        assert_eq!(resolved[0].1.profile.as_deref(), Some("work"));
+        assert_eq!(resolved[1].1.value, "personal");
+        assert_eq!(resolved[1].1.profile, None);
+    }
+
+    #[test]
+    fn test_resolve_rejects_undefined_environment_variable() {
+        let base = map(&[("token", "${DOTFILES_UNDEFINED_VAR_12345}")]);
+        let error = Variables::resolve(&base, None).unwrap_err();
+        assert!(error.to_string().contains("Variable 'token'"));
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let variables = Variables::resolve(&map(&[("project", "acme")]), None).unwrap();
+
+        assert_eq!(
+            variables.interpolate("gcloud/{{ project }}.zsh").unwrap(),
+            "gcloud/acme.zsh"
+        );
+        assert_eq!(
+            variables.interpolate("{{project}}-{{project}}").unwrap(),
+            "acme-acme"
+        );
+        assert_eq!(variables.interpolate("plain").unwrap(), "plain");
+        assert!(
+            variables
+                .interpolate("{{ missing }}")
+                .unwrap_err()
+                .to_string()
+                .contains("Unknown variable 'missing'")
+        );
+        assert!(variables.interpolate("{{ project").is_err());
+    }
+
+    #[test]
+    fn test_with_machine_facts_keeps_configured_values() {
+        let variables =
+            Variables::resolve(&map(&[("machine.os", "custom")]), Some(("work", &map(&[]))))
+                .unwrap()
+                .with_machine_facts();
+
+        assert_eq!(
+            variables
+                .interpolate("{{ machine.os }} {{ machine.arch }} {{ machine.profile }}")
+                .unwrap(),
+            format!("custom {} work", env::consts::ARCH)
+        );
+    }
+
+    #[test]
+    fn test_with_machine_facts_resolves_hostname() {
+        let variables = Variables::resolve(&map(&[]), None)
+            .unwrap()
+            .with_machine_facts();
+
+        let hostname = variables.interpolate("{{ machine.hostname }}").unwrap();
+        assert_eq!(Some(hostname.clone()), short_hostname());
+        assert!(!hostname.is_empty());
+        assert!(!hostname.contains('.'));
+    }
+
+    #[test]
+    fn test_select_profile_explicit_must_exist() {
+        let profiles = [String::from("work"), String::from("home")];
+
+        assert_eq!(
+            select_profile(Some("home"), profiles.iter()).unwrap(),
+            Some("home".to_string())
+        );
+        assert!(select_profile(Some("travel"), profiles.iter()).is_err());
+    }
+}